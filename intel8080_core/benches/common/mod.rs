@@ -0,0 +1,42 @@
+//! Program and port shared by the throughput benchmark and example
+
+use intel8080_core::port::Port;
+
+// Copies and checksums a block of memory through a subroutine, touching
+// moves, arithmetic, memory operands, the stack and conditional jumps
+pub const PROGRAM: &str = "
+        ORG 0
+        LXI SP,0F000H
+LOOP:   LXI H,1000H
+        LXI D,2000H
+        MVI B,80H
+        CALL COPY
+        LXI H,2000H
+        MVI B,80H
+        XRA A
+SUM:    ADD M
+        RLC
+        INX H
+        DCR B
+        JNZ SUM
+        STA 3000H
+        OUT 1
+        JMP LOOP
+COPY:   MOV A,M
+        STAX D
+        INX H
+        INX D
+        DCR B
+        JNZ COPY
+        RET
+";
+
+pub struct NullPort;
+
+impl Port for NullPort {
+    fn read_in(&self, _port_num: u8) -> u8 {
+        0
+    }
+
+    fn write_out(&mut self, _port_num: u8, _value: u8) {}
+}
@@ -0,0 +1,34 @@
+//! Interpreter speed on a mix of typical instructions, reported as
+//! instructions per second. Criterion keeps the results of earlier runs, so
+//! running it before and after a change shows the difference.
+//!
+//! cargo bench -p intel8080_core --bench throughput
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use intel8080_core::{assembler::assemble, processor::Processor};
+
+mod common;
+
+use common::{NullPort, PROGRAM};
+
+const INSTRUCTIONS: u64 = 10_000;
+
+fn execute(criterion: &mut Criterion) {
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+
+    let mut group = criterion.benchmark_group("throughput");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    group.bench_function("execute", |bencher| {
+        bencher.iter(|| {
+            for _ in 0..INSTRUCTIONS {
+                processor.execute(&mut NullPort).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, execute);
+criterion_main!(benches);
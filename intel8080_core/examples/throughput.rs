@@ -0,0 +1,43 @@
+//! Measures how fast the interpreter runs a mix of typical instructions.
+//!
+//! cargo run --release -p intel8080_core --example throughput [cycles]
+//!
+//! For comparing changes use the criterion benchmark in benches/throughput.rs
+
+use std::{env, time::Instant};
+
+use intel8080_core::{assembler::assemble, processor::Processor};
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use common::{NullPort, PROGRAM};
+
+const CLOCK_SPEED: f64 = 2_000_000.0;
+
+fn main() {
+    let target_cycles: u64 = env::args()
+        .nth(1)
+        .and_then(|cycles| cycles.parse().ok())
+        .unwrap_or(500_000_000);
+
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+
+    let mut cycles: u64 = 0;
+    let mut instructions: u64 = 0;
+    let start = Instant::now();
+    while cycles < target_cycles {
+        cycles += processor.execute(&mut NullPort).unwrap() as u64;
+        instructions += 1;
+    }
+    let seconds = start.elapsed().as_secs_f64();
+
+    println!(
+        "{instructions} instructions, {cycles} cycles in {seconds:.3} s: {:.1} MIPS, {:.1} MHz, {:.0}x a 2 MHz 8080",
+        instructions as f64 / seconds / 1e6,
+        cycles as f64 / seconds / 1e6,
+        cycles as f64 / seconds / CLOCK_SPEED,
+    );
+}
@@ -0,0 +1,895 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    errors::{Error, Result},
+    helpers::word_to_bytes,
+    intel_hex::{self, Segment},
+};
+
+/// Output of a successful assembly
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Assembly {
+    /// Emitted code and data, in source order
+    pub segments: Vec<Segment>,
+    /// Labels and EQU constants, with names in upper case
+    pub symbols: BTreeMap<String, u16>,
+    /// Address of the first byte emitted by each source line, keyed by line number
+    pub lines: BTreeMap<usize, u16>,
+}
+
+impl Assembly {
+    /// Lowest address that holds emitted bytes
+    pub fn origin(&self) -> u16 {
+        self.segments
+            .iter()
+            .filter(|segment| !segment.bytes.is_empty())
+            .map(|segment| segment.address)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Flat image starting at `origin()`, with gaps left by ORG and DS zeroed.
+    /// Load it with `Processor::load_rom(&binary, assembly.origin())`.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let origin = self.origin() as usize;
+        let mut binary = Vec::new();
+
+        for segment in &self.segments {
+            let start = segment.address as usize - origin;
+            let end = start + segment.bytes.len();
+            if binary.len() < end {
+                binary.resize(end, 0);
+            }
+            binary[start..end].copy_from_slice(&segment.bytes);
+        }
+
+        binary
+    }
+
+    pub fn to_intel_hex(&self) -> String {
+        intel_hex::encode(&self.segments)
+    }
+}
+
+/// Assembles Intel 8080 source in two passes: the first assigns addresses
+/// to labels, the second encodes instructions and data.
+pub fn assemble(source: &str) -> Result<Assembly> {
+    let mut assembler = Assembler::default();
+
+    for pass in [Pass::First, Pass::Second] {
+        assembler.start_pass(pass);
+
+        for (index, text) in source.lines().enumerate() {
+            let line_num = index + 1;
+            assembler.line_num = line_num;
+            let line = parse_line(text).map_err(|message| Error::Assembly { line_num, message })?;
+
+            let ended = assembler
+                .assemble_line(&line)
+                .map_err(|message| Error::Assembly { line_num, message })?;
+            if ended {
+                break;
+            }
+        }
+    }
+
+    Ok(Assembly {
+        segments: assembler.segments,
+        symbols: assembler.symbols,
+        lines: assembler.lines,
+    })
+}
+
+type LineResult<T> = std::result::Result<T, String>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pass {
+    First,
+    Second,
+}
+
+struct Assembler {
+    pass: Pass,
+    address: u16,
+    symbols: BTreeMap<String, u16>,
+    segments: Vec<Segment>,
+    line_num: usize,
+    lines: BTreeMap<usize, u16>,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Self {
+            pass: Pass::First,
+            address: 0,
+            symbols: BTreeMap::new(),
+            segments: Vec::new(),
+            line_num: 0,
+            lines: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Line {
+    label: Option<String>,
+    mnemonic: Option<String>,
+    operands: Vec<String>,
+}
+
+/// How an instruction's operands are encoded into its opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    /// No operands
+    Implied,
+    /// MOV d,s
+    Move,
+    /// Destination register in bits 3-5 and an immediate byte
+    RegisterByte,
+    /// Register pair in bits 4-5 and an immediate word
+    PairWord,
+    /// Immediate word
+    Word,
+    /// LDAX/STAX, only B and D are allowed
+    IndirectPair,
+    /// Source register in bits 0-2
+    Source,
+    /// Immediate byte
+    Byte,
+    /// Destination register in bits 3-5
+    Destination,
+    /// Register pair in bits 4-5
+    Pair,
+    /// PUSH/POP, where PSW replaces SP
+    StackPair,
+    /// RST number in bits 3-5
+    Restart,
+}
+
+impl Encoding {
+    fn length(self) -> u16 {
+        match self {
+            Encoding::RegisterByte | Encoding::Byte => 2,
+            Encoding::PairWord | Encoding::Word => 3,
+            _ => 1,
+        }
+    }
+}
+
+fn lookup_mnemonic(mnemonic: &str) -> Option<(Encoding, u8)> {
+    let entry = match mnemonic {
+        "NOP" => (Encoding::Implied, 0x00),
+        "HLT" => (Encoding::Implied, 0x76),
+        "XCHG" => (Encoding::Implied, 0xEB),
+        "DAA" => (Encoding::Implied, 0x27),
+        "RLC" => (Encoding::Implied, 0x07),
+        "RRC" => (Encoding::Implied, 0x0F),
+        "RAL" => (Encoding::Implied, 0x17),
+        "RAR" => (Encoding::Implied, 0x1F),
+        "CMA" => (Encoding::Implied, 0x2F),
+        "CMC" => (Encoding::Implied, 0x3F),
+        "STC" => (Encoding::Implied, 0x37),
+        "RET" => (Encoding::Implied, 0xC9),
+        "RNZ" => (Encoding::Implied, 0xC0),
+        "RZ" => (Encoding::Implied, 0xC8),
+        "RNC" => (Encoding::Implied, 0xD0),
+        "RC" => (Encoding::Implied, 0xD8),
+        "RPO" => (Encoding::Implied, 0xE0),
+        "RPE" => (Encoding::Implied, 0xE8),
+        "RP" => (Encoding::Implied, 0xF0),
+        "RM" => (Encoding::Implied, 0xF8),
+        "PCHL" => (Encoding::Implied, 0xE9),
+        "XTHL" => (Encoding::Implied, 0xE3),
+        "SPHL" => (Encoding::Implied, 0xF9),
+        "EI" => (Encoding::Implied, 0xFB),
+        "DI" => (Encoding::Implied, 0xF3),
+
+        "MOV" => (Encoding::Move, 0x40),
+        "MVI" => (Encoding::RegisterByte, 0x06),
+        "LXI" => (Encoding::PairWord, 0x01),
+
+        "LDA" => (Encoding::Word, 0x3A),
+        "STA" => (Encoding::Word, 0x32),
+        "LHLD" => (Encoding::Word, 0x2A),
+        "SHLD" => (Encoding::Word, 0x22),
+        "JMP" => (Encoding::Word, 0xC3),
+        "JNZ" => (Encoding::Word, 0xC2),
+        "JZ" => (Encoding::Word, 0xCA),
+        "JNC" => (Encoding::Word, 0xD2),
+        "JC" => (Encoding::Word, 0xDA),
+        "JPO" => (Encoding::Word, 0xE2),
+        "JPE" => (Encoding::Word, 0xEA),
+        "JP" => (Encoding::Word, 0xF2),
+        "JM" => (Encoding::Word, 0xFA),
+        "CALL" => (Encoding::Word, 0xCD),
+        "CNZ" => (Encoding::Word, 0xC4),
+        "CZ" => (Encoding::Word, 0xCC),
+        "CNC" => (Encoding::Word, 0xD4),
+        "CC" => (Encoding::Word, 0xDC),
+        "CPO" => (Encoding::Word, 0xE4),
+        "CPE" => (Encoding::Word, 0xEC),
+        "CP" => (Encoding::Word, 0xF4),
+        "CM" => (Encoding::Word, 0xFC),
+
+        "LDAX" => (Encoding::IndirectPair, 0x0A),
+        "STAX" => (Encoding::IndirectPair, 0x02),
+
+        "ADD" => (Encoding::Source, 0x80),
+        "ADC" => (Encoding::Source, 0x88),
+        "SUB" => (Encoding::Source, 0x90),
+        "SBB" => (Encoding::Source, 0x98),
+        "ANA" => (Encoding::Source, 0xA0),
+        "XRA" => (Encoding::Source, 0xA8),
+        "ORA" => (Encoding::Source, 0xB0),
+        "CMP" => (Encoding::Source, 0xB8),
+
+        "ADI" => (Encoding::Byte, 0xC6),
+        "ACI" => (Encoding::Byte, 0xCE),
+        "SUI" => (Encoding::Byte, 0xD6),
+        "SBI" => (Encoding::Byte, 0xDE),
+        "ANI" => (Encoding::Byte, 0xE6),
+        "XRI" => (Encoding::Byte, 0xEE),
+        "ORI" => (Encoding::Byte, 0xF6),
+        "CPI" => (Encoding::Byte, 0xFE),
+        "IN" => (Encoding::Byte, 0xDB),
+        "OUT" => (Encoding::Byte, 0xD3),
+
+        "INR" => (Encoding::Destination, 0x04),
+        "DCR" => (Encoding::Destination, 0x05),
+
+        "INX" => (Encoding::Pair, 0x03),
+        "DCX" => (Encoding::Pair, 0x0B),
+        "DAD" => (Encoding::Pair, 0x09),
+
+        "PUSH" => (Encoding::StackPair, 0xC5),
+        "POP" => (Encoding::StackPair, 0xC1),
+
+        "RST" => (Encoding::Restart, 0xC7),
+
+        _ => return None,
+    };
+
+    Some(entry)
+}
+
+fn is_directive(mnemonic: &str) -> bool {
+    matches!(mnemonic, "ORG" | "DB" | "DW" | "DS" | "EQU" | "SET" | "END")
+}
+
+impl Assembler {
+    fn start_pass(&mut self, pass: Pass) {
+        self.pass = pass;
+        self.address = 0;
+        self.segments.clear();
+        self.lines.clear();
+    }
+
+    /// Returns true when the END directive was reached
+    fn assemble_line(&mut self, line: &Line) -> LineResult<bool> {
+        let Some(mnemonic) = &line.mnemonic else {
+            if let Some(label) = &line.label {
+                self.define(label, self.address)?;
+            }
+            return Ok(false);
+        };
+
+        match mnemonic.as_str() {
+            "EQU" | "SET" => {
+                let label = line
+                    .label
+                    .as_ref()
+                    .ok_or_else(|| format!("{mnemonic} requires a name"))?;
+                let value = self.evaluate_now(single_operand(line)?)?;
+
+                // SET symbols may be redefined, EQU symbols may not
+                if mnemonic == "SET" {
+                    self.symbols.insert(label.clone(), value as u16);
+                } else {
+                    self.define(label, value as u16)?;
+                }
+                return Ok(false);
+            }
+            _ => {
+                if let Some(label) = &line.label {
+                    self.define(label, self.address)?;
+                }
+            }
+        }
+
+        match mnemonic.as_str() {
+            "ORG" => {
+                self.address = self.evaluate_now(single_operand(line)?)? as u16;
+            }
+            "DS" => {
+                let size = self.evaluate_now(single_operand(line)?)?;
+                self.address = self.address.wrapping_add(size as u16);
+            }
+            "DB" => {
+                if line.operands.is_empty() {
+                    return Err("DB requires at least one operand".to_string());
+                }
+                for operand in &line.operands {
+                    match parse_string(operand) {
+                        Some(string) if string.len() != 1 => self.emit(&string),
+                        _ => {
+                            let value = self.evaluate(operand)?;
+                            let byte = self.byte_value(value)?;
+                            self.emit(&[byte]);
+                        }
+                    }
+                }
+            }
+            "DW" => {
+                if line.operands.is_empty() {
+                    return Err("DW requires at least one operand".to_string());
+                }
+                for operand in &line.operands {
+                    let (low_byte, high_byte) = word_to_bytes(self.evaluate(operand)? as u16);
+                    self.emit(&[low_byte, high_byte]);
+                }
+            }
+            "END" => return Ok(true),
+            _ => {
+                let (encoding, opcode) = lookup_mnemonic(mnemonic)
+                    .ok_or_else(|| format!("unknown mnemonic '{mnemonic}'"))?;
+
+                if self.pass == Pass::First {
+                    self.address = self.address.wrapping_add(encoding.length());
+                } else {
+                    let bytes = self.encode(encoding, opcode, &line.operands)?;
+                    self.emit(&bytes);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn encode(&self, encoding: Encoding, opcode: u8, operands: &[String]) -> LineResult<Vec<u8>> {
+        let expected = match encoding {
+            Encoding::Implied => 0,
+            Encoding::Move | Encoding::RegisterByte | Encoding::PairWord => 2,
+            _ => 1,
+        };
+        if operands.len() != expected {
+            return Err(format!(
+                "expected {expected} operand(s) but found {}",
+                operands.len()
+            ));
+        }
+
+        let bytes = match encoding {
+            Encoding::Implied => vec![opcode],
+            Encoding::Move => {
+                let destination = self.register(&operands[0])?;
+                let source = self.register(&operands[1])?;
+                if destination == 6 && source == 6 {
+                    return Err("MOV M,M is not a valid instruction".to_string());
+                }
+                vec![opcode | destination << 3 | source]
+            }
+            Encoding::RegisterByte => {
+                let destination = self.register(&operands[0])?;
+                let value = self.evaluate(&operands[1])?;
+                vec![opcode | destination << 3, self.byte_value(value)?]
+            }
+            Encoding::PairWord => {
+                let pair = self.register_pair(&operands[0], "SP")?;
+                let (low_byte, high_byte) = word_to_bytes(self.evaluate(&operands[1])? as u16);
+                vec![opcode | pair << 4, low_byte, high_byte]
+            }
+            Encoding::Word => {
+                let (low_byte, high_byte) = word_to_bytes(self.evaluate(&operands[0])? as u16);
+                vec![opcode, low_byte, high_byte]
+            }
+            Encoding::IndirectPair => {
+                let pair = self.register_pair(&operands[0], "SP")?;
+                if pair > 1 {
+                    return Err(format!("register pair must be B or D, not {}", operands[0]));
+                }
+                vec![opcode | pair << 4]
+            }
+            Encoding::Source => vec![opcode | self.register(&operands[0])?],
+            Encoding::Byte => {
+                let value = self.evaluate(&operands[0])?;
+                vec![opcode, self.byte_value(value)?]
+            }
+            Encoding::Destination => vec![opcode | self.register(&operands[0])? << 3],
+            Encoding::Pair => vec![opcode | self.register_pair(&operands[0], "SP")? << 4],
+            Encoding::StackPair => vec![opcode | self.register_pair(&operands[0], "PSW")? << 4],
+            Encoding::Restart => {
+                let num = self.evaluate(&operands[0])?;
+                if !(0..=7).contains(&num) {
+                    return Err(format!("RST number must be 0-7, not {num}"));
+                }
+                vec![opcode | (num as u8) << 3]
+            }
+        };
+
+        Ok(bytes)
+    }
+
+    fn define(&mut self, name: &str, value: u16) -> LineResult<()> {
+        if self.pass == Pass::First && self.symbols.insert(name.to_string(), value).is_some() {
+            return Err(format!("symbol '{name}' is defined more than once"));
+        }
+
+        Ok(())
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        if self.pass == Pass::Second {
+            if !bytes.is_empty() {
+                self.lines.entry(self.line_num).or_insert(self.address);
+            }
+
+            match self.segments.last_mut() {
+                Some(segment)
+                    if segment.address.wrapping_add(segment.bytes.len() as u16) == self.address =>
+                {
+                    segment.bytes.extend_from_slice(bytes);
+                }
+                _ => self.segments.push(Segment {
+                    address: self.address,
+                    bytes: bytes.to_vec(),
+                }),
+            }
+        }
+        self.address = self.address.wrapping_add(bytes.len() as u16);
+    }
+
+    /// Evaluates an operand. Forward references read as 0 in the first pass
+    /// and are resolved in the second.
+    fn evaluate(&self, expression: &str) -> LineResult<i64> {
+        let allow_undefined = self.pass == Pass::First;
+        Evaluator::new(expression, &self.symbols, self.address, allow_undefined)?.evaluate()
+    }
+
+    /// Evaluates an operand that decides addresses, so it must be known
+    /// during the first pass
+    fn evaluate_now(&self, expression: &str) -> LineResult<i64> {
+        Evaluator::new(expression, &self.symbols, self.address, false)?.evaluate()
+    }
+
+    fn byte_value(&self, value: i64) -> LineResult<u8> {
+        if self.pass == Pass::Second && !(-128..=255).contains(&value) {
+            return Err(format!("value {value} does not fit in a byte"));
+        }
+
+        Ok(value as u8)
+    }
+
+    fn register(&self, operand: &str) -> LineResult<u8> {
+        let register = match operand.to_ascii_uppercase().as_str() {
+            "B" => 0,
+            "C" => 1,
+            "D" => 2,
+            "E" => 3,
+            "H" => 4,
+            "L" => 5,
+            "M" => 6,
+            "A" => 7,
+            _ => match self.evaluate(operand) {
+                Ok(value @ 0..=7) => value as u8,
+                _ => return Err(format!("'{operand}' is not a register")),
+            },
+        };
+
+        Ok(register)
+    }
+
+    /// Parses B, D, H or `last`, which is SP or PSW depending on the instruction
+    fn register_pair(&self, operand: &str, last: &str) -> LineResult<u8> {
+        let name = operand.to_ascii_uppercase();
+        let pair = match name.as_str() {
+            "B" | "BC" => 0,
+            "D" | "DE" => 1,
+            "H" | "HL" => 2,
+            _ if name == last => 3,
+            _ => return Err(format!("'{operand}' is not a valid register pair")),
+        };
+
+        Ok(pair)
+    }
+}
+
+fn single_operand(line: &Line) -> LineResult<&str> {
+    match line.operands.as_slice() {
+        [operand] => Ok(operand),
+        _ => Err(format!(
+            "{} takes exactly one operand",
+            line.mnemonic.as_deref().unwrap_or_default()
+        )),
+    }
+}
+
+fn parse_line(text: &str) -> LineResult<Line> {
+    let text = strip_comment(text);
+    let mut line = Line::default();
+    let mut rest = text.trim_start();
+    let starts_in_first_column = rest.len() == text.len();
+
+    // Label, either with a colon or as a bare name in the first column
+    let (first, after_first) = split_token(rest);
+    if first.is_empty() {
+        return Ok(line);
+    }
+    let first_upper = first.to_ascii_uppercase();
+    let (second, _) = split_token(after_first);
+    let second_upper = second.to_ascii_uppercase();
+
+    if let Some(label) = first.strip_suffix(':') {
+        line.label = Some(symbol_name(label)?);
+        rest = after_first;
+    } else if matches!(second_upper.as_str(), "EQU" | "SET")
+        || (starts_in_first_column
+            && lookup_mnemonic(&first_upper).is_none()
+            && !is_directive(&first_upper))
+    {
+        line.label = Some(symbol_name(first)?);
+        rest = after_first;
+    }
+
+    let (mnemonic, operands) = split_token(rest);
+    if !mnemonic.is_empty() {
+        line.mnemonic = Some(mnemonic.to_ascii_uppercase());
+        line.operands = split_operands(operands)?;
+    }
+
+    Ok(line)
+}
+
+fn symbol_name(name: &str) -> LineResult<String> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || "_?@.".contains(c))
+        && name.chars().all(is_symbol_char);
+
+    if valid {
+        Ok(name.to_ascii_uppercase())
+    } else {
+        Err(format!("'{name}' is not a valid label"))
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_?@.".contains(c)
+}
+
+fn split_token(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+
+    (&text[..end], text[end..].trim_start())
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, ';') => return &text[..index],
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            _ => {}
+        }
+    }
+
+    text
+}
+
+fn split_operands(text: &str) -> LineResult<Vec<String>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut depth = 0;
+
+    for c in text.chars() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                operands.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+
+    if quote.is_some() {
+        return Err("unterminated string".to_string());
+    }
+    operands.push(current.trim().to_string());
+
+    if operands.iter().any(String::is_empty) {
+        return Err("empty operand".to_string());
+    }
+
+    Ok(operands)
+}
+
+/// Returns the bytes of a quoted string operand, with doubled quotes unescaped
+fn parse_string(operand: &str) -> Option<Vec<u8>> {
+    let quote = operand.chars().next().filter(|&c| c == '\'' || c == '"')?;
+    let inner = operand.strip_prefix(quote)?.strip_suffix(quote)?;
+
+    let doubled = format!("{quote}{quote}");
+    if inner.replace(&doubled, "").contains(quote) {
+        return None;
+    }
+
+    Some(inner.replace(&doubled, &quote.to_string()).into_bytes())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Here,
+    Operator(String),
+    LeftParen,
+    RightParen,
+}
+
+/// Recursive descent evaluator for operand expressions. Precedence from
+/// lowest to highest: OR XOR, AND, NOT, SHL SHR, + -, * / MOD, unary.
+struct Evaluator<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    symbols: &'a BTreeMap<String, u16>,
+    here: u16,
+    allow_undefined: bool,
+}
+
+impl<'a> Evaluator<'a> {
+    fn new(
+        expression: &str,
+        symbols: &'a BTreeMap<String, u16>,
+        here: u16,
+        allow_undefined: bool,
+    ) -> LineResult<Self> {
+        Ok(Self {
+            tokens: tokenize(expression)?,
+            position: 0,
+            symbols,
+            here,
+            allow_undefined,
+        })
+    }
+
+    fn evaluate(mut self) -> LineResult<i64> {
+        let value = self.or_expression()?;
+        match self.tokens.get(self.position) {
+            None => Ok(value),
+            Some(token) => Err(format!("unexpected {token:?} in expression")),
+        }
+    }
+
+    fn next_operator(&mut self, operators: &[&str]) -> Option<String> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(operator)) if operators.contains(&operator.as_str()) => {
+                self.position += 1;
+                Some(operator.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn or_expression(&mut self) -> LineResult<i64> {
+        let mut value = self.and_expression()?;
+        while let Some(operator) = self.next_operator(&["OR", "XOR"]) {
+            let rhs = self.and_expression()?;
+            value = if operator == "OR" {
+                value | rhs
+            } else {
+                value ^ rhs
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn and_expression(&mut self) -> LineResult<i64> {
+        let mut value = self.not_expression()?;
+        while self.next_operator(&["AND"]).is_some() {
+            value &= self.not_expression()?;
+        }
+
+        Ok(value)
+    }
+
+    fn not_expression(&mut self) -> LineResult<i64> {
+        if self.next_operator(&["NOT"]).is_some() {
+            return Ok(!self.not_expression()? & 0xFFFF);
+        }
+
+        self.shift_expression()
+    }
+
+    fn shift_expression(&mut self) -> LineResult<i64> {
+        let mut value = self.additive_expression()?;
+        while let Some(operator) = self.next_operator(&["SHL", "SHR"]) {
+            let amount = self.additive_expression()?.clamp(0, 63);
+            value = if operator == "SHL" {
+                value << amount
+            } else {
+                value >> amount
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn additive_expression(&mut self) -> LineResult<i64> {
+        let mut value = self.multiplicative_expression()?;
+        while let Some(operator) = self.next_operator(&["+", "-"]) {
+            let rhs = self.multiplicative_expression()?;
+            value = if operator == "+" {
+                value.checked_add(rhs)
+            } else {
+                value.checked_sub(rhs)
+            }
+            .ok_or_else(overflow)?;
+        }
+
+        Ok(value)
+    }
+
+    fn multiplicative_expression(&mut self) -> LineResult<i64> {
+        let mut value = self.unary_expression()?;
+        while let Some(operator) = self.next_operator(&["*", "/", "MOD"]) {
+            let rhs = self.unary_expression()?;
+            value = match operator.as_str() {
+                "*" => value.checked_mul(rhs),
+                _ if rhs == 0 => return Err("division by zero".to_string()),
+                "/" => value.checked_div(rhs),
+                _ => value.checked_rem(rhs),
+            }
+            .ok_or_else(overflow)?;
+        }
+
+        Ok(value)
+    }
+
+    fn unary_expression(&mut self) -> LineResult<i64> {
+        match self.next_operator(&["-", "+", "HIGH", "LOW"]).as_deref() {
+            Some("-") => self.unary_expression()?.checked_neg().ok_or_else(overflow),
+            Some("+") => self.unary_expression(),
+            Some("HIGH") => Ok((self.unary_expression()? >> 8) & 0xFF),
+            Some("LOW") => Ok(self.unary_expression()? & 0xFF),
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> LineResult<i64> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "expression ended unexpectedly".to_string())?;
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Ok(value),
+            Token::Here => Ok(self.here as i64),
+            Token::Symbol(name) => match self.symbols.get(&name) {
+                Some(&value) => Ok(value as i64),
+                None if self.allow_undefined => Ok(0),
+                None => Err(format!("undefined symbol '{name}'")),
+            },
+            Token::LeftParen => {
+                let value = self.or_expression()?;
+                if self.tokens.get(self.position) != Some(&Token::RightParen) {
+                    return Err("missing ')' in expression".to_string());
+                }
+                self.position += 1;
+                Ok(value)
+            }
+            token => Err(format!("unexpected {token:?} in expression")),
+        }
+    }
+}
+
+fn overflow() -> String {
+    "expression overflows 64 bits".to_string()
+}
+
+fn tokenize(expression: &str) -> LineResult<Vec<Token>> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c.is_whitespace() {
+            index += 1;
+        } else if c == '\'' || c == '"' {
+            // Character constants of one or two characters
+            let mut value = 0;
+            let mut length = 0;
+            index += 1;
+            loop {
+                match chars.get(index) {
+                    None => return Err("unterminated character constant".to_string()),
+                    Some(&q) if q == c && chars.get(index + 1) != Some(&c) => break,
+                    Some(&q) => {
+                        index += if q == c { 2 } else { 1 };
+                        value = (value << 8) | (q as i64 & 0xFF);
+                        length += 1;
+                    }
+                }
+            }
+            index += 1;
+            if length == 0 || length > 2 {
+                return Err("character constant must be one or two characters".to_string());
+            }
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_digit() {
+            let start = index;
+            while index < chars.len() && chars[index].is_ascii_alphanumeric() {
+                index += 1;
+            }
+            let literal: String = chars[start..index].iter().collect();
+            tokens.push(Token::Number(parse_number(&literal)?));
+        } else if c.is_ascii_alphabetic() || "_?@.".contains(c) {
+            let start = index;
+            while index < chars.len() && is_symbol_char(chars[index]) {
+                index += 1;
+            }
+            let name = chars[start..index]
+                .iter()
+                .collect::<String>()
+                .to_ascii_uppercase();
+            match name.as_str() {
+                "OR" | "XOR" | "AND" | "NOT" | "SHL" | "SHR" | "MOD" | "HIGH" | "LOW" => {
+                    tokens.push(Token::Operator(name))
+                }
+                _ => tokens.push(Token::Symbol(name)),
+            }
+        } else {
+            let token = match c {
+                '$' => Token::Here,
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '+' | '-' | '*' | '/' => Token::Operator(c.to_string()),
+                _ => return Err(format!("unexpected character '{c}' in expression")),
+            };
+            tokens.push(token);
+            index += 1;
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses decimal, 0x-prefixed hex, or Intel suffixed H/B/O/Q/D numbers
+fn parse_number(literal: &str) -> LineResult<i64> {
+    let upper = literal.to_ascii_uppercase();
+
+    let (digits, radix) = if let Some(hex) = upper.strip_prefix("0X") {
+        (hex, 16)
+    } else if let Some(hex) = upper.strip_suffix('H') {
+        (hex, 16)
+    } else if let Some(binary) = upper.strip_suffix('B') {
+        (binary, 2)
+    } else if let Some(octal) = upper.strip_suffix(['O', 'Q']) {
+        (octal, 8)
+    } else if let Some(decimal) = upper.strip_suffix('D') {
+        (decimal, 10)
+    } else {
+        (upper.as_str(), 10)
+    };
+
+    i64::from_str_radix(digits, radix).map_err(|_| format!("invalid number '{literal}'"))
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use crate::{
+    bus::Bus,
+    errors::{Error, Result},
+    memory::{Fault, FaultPolicy},
+    port::Port,
+};
+
+/// Page selected in each window. Clones share the selection, so one can be
+/// handed to whatever drives the switching while the memory keeps another.
+#[derive(Clone, Debug)]
+pub struct Banks {
+    selected: Arc<[AtomicUsize]>,
+    pages: usize,
+}
+
+impl Banks {
+    /// Maps `page` into `window`
+    pub fn select(&self, window: usize, page: usize) -> Result<()> {
+        self.check_window(window)?;
+        if page >= self.pages {
+            return Err(Error::Banking(format!(
+                "page {page} is out of range, there are {} pages",
+                self.pages
+            )));
+        }
+        self.selected[window].store(page, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Selects the page a latch or port value picks. Page numbers wrap at
+    /// the number of pages, like latch bits with nothing connected to them.
+    fn latch(&self, window: usize, value: u8) {
+        self.selected[window].store(value as usize % self.pages, Ordering::Relaxed);
+    }
+
+    pub fn selected(&self, window: usize) -> usize {
+        self.selected[window].load(Ordering::Relaxed)
+    }
+
+    pub fn windows(&self) -> usize {
+        self.selected.len()
+    }
+
+    fn check_window(&self, window: usize) -> Result<()> {
+        if window >= self.windows() {
+            return Err(Error::Banking(format!(
+                "window {window} is out of range, there are {} windows",
+                self.windows()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Address space split into equally sized windows, each showing one page of
+/// a larger backing store. Window `n` starts out on page `n`.
+#[derive(Debug)]
+pub struct BankedMemory {
+    data: Vec<u8>,
+    page_bits: u32,
+    rom: Vec<bool>,
+    banks: Banks,
+    // Addresses whose writes select a page instead of storing a byte
+    latches: BTreeMap<u16, usize>,
+    rom_writes: FaultPolicy,
+    faults: Vec<Fault>,
+}
+
+impl BankedMemory {
+    /// `page_size` has to be a power of two no larger than the address space
+    pub fn new(page_size: usize, pages: usize) -> Result<Self> {
+        if !page_size.is_power_of_two() || page_size > 0x10000 {
+            return Err(Error::Banking(format!(
+                "page size {page_size} is not a power of two up to 0x10000"
+            )));
+        }
+        if pages == 0 {
+            return Err(Error::Banking("there has to be at least one page".into()));
+        }
+
+        let windows = 0x10000 / page_size;
+        let banks = Banks {
+            selected: (0..windows)
+                .map(|window| AtomicUsize::new(window % pages))
+                .collect(),
+            pages,
+        };
+
+        Ok(Self {
+            data: vec![0; page_size * pages],
+            page_bits: page_size.trailing_zeros(),
+            rom: vec![false; pages],
+            banks,
+            latches: BTreeMap::new(),
+            rom_writes: FaultPolicy::default(),
+            faults: Vec::new(),
+        })
+    }
+
+    /// Handle on the bank selection, for example for `BankPorts`
+    pub fn banks(&self) -> Banks {
+        self.banks.clone()
+    }
+
+    pub fn page_size(&self) -> usize {
+        1 << self.page_bits
+    }
+
+    pub fn pages(&self) -> usize {
+        self.rom.len()
+    }
+
+    /// Window containing `address`
+    pub fn window(&self, address: u16) -> usize {
+        (address as usize) >> self.page_bits
+    }
+
+    /// Marks a page as ROM, writes to it fail wherever it is mapped
+    pub fn set_rom(&mut self, page: usize, rom: bool) -> Result<()> {
+        if page >= self.pages() {
+            return Err(Error::Banking(format!(
+                "page {page} is out of range, there are {} pages",
+                self.pages()
+            )));
+        }
+        self.rom[page] = rom;
+
+        Ok(())
+    }
+
+    /// What writes to ROM pages do, like `Memory::set_rom_write_policy`
+    pub fn set_rom_write_policy(&mut self, policy: FaultPolicy) {
+        self.rom_writes = policy;
+    }
+
+    /// Returns the ROM writes logged since the last call, oldest first
+    pub fn take_faults(&mut self) -> Vec<Fault> {
+        std::mem::take(&mut self.faults)
+    }
+
+    /// Makes writes to `address` select the page of `window` instead of
+    /// reaching memory, like a latch decoded from the address bus
+    pub fn add_latch(&mut self, address: u16, window: usize) -> Result<()> {
+        self.banks.check_window(window)?;
+        self.latches.insert(address, window);
+
+        Ok(())
+    }
+
+    /// Copies `data` into the backing store starting at the first byte of
+    /// `page`, ROM pages included. It may continue into the pages after.
+    pub fn load_pages(&mut self, page: usize, data: &[u8]) -> Result<()> {
+        let start = page << self.page_bits;
+        let space_left = self.data.len().saturating_sub(start);
+
+        if data.len() > space_left {
+            return Err(Error::RomSize {
+                rom_size: data.len(),
+                space_left,
+            });
+        }
+        self.data[start..start + data.len()].copy_from_slice(data);
+
+        Ok(())
+    }
+
+    /// Index into the backing store and page of an address
+    fn decode(&self, address: u16) -> (usize, usize) {
+        let page = self.banks.selected(self.window(address));
+        let mask = (1 << self.page_bits) - 1;
+
+        ((page << self.page_bits) | (address as usize & mask), page)
+    }
+}
+
+impl Bus for BankedMemory {
+    fn read(&mut self, address: u16) -> Result<u8> {
+        Ok(self.data[self.decode(address).0])
+    }
+
+    fn write(&mut self, address: u16, value: u8) -> Result<()> {
+        if let Some(&window) = self.latches.get(&address) {
+            self.banks.latch(window, value);
+            return Ok(());
+        }
+
+        let (index, page) = self.decode(address);
+        if !self.rom[page] {
+            self.data[index] = value;
+            return Ok(());
+        }
+
+        let fault = Fault::RomWrite { address, value };
+        match &self.rom_writes {
+            FaultPolicy::Error => Err(Error::RomWrite {
+                address,
+                region: Some(format!("page {page}")),
+            }),
+            FaultPolicy::Ignore => Ok(()),
+            FaultPolicy::Log => {
+                self.faults.push(fault);
+                Ok(())
+            }
+            FaultPolicy::Callback(callback) => (callback.lock().unwrap())(fault).map(drop),
+        }
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        Some(self.data[self.decode(address).0])
+    }
+
+    /// Stores into the page currently selected, ROM pages included
+    fn poke(&mut self, address: u16, value: u8) -> bool {
+        let (index, _) = self.decode(address);
+        self.data[index] = value;
+        true
+    }
+
+    /// Copies into the pages currently selected, ROM pages included
+    fn load_rom(&mut self, rom: &[u8], address: u16) -> Result<()> {
+        for (offset, &byte) in rom.iter().enumerate() {
+            let (index, _) = self.decode(address.wrapping_add(offset as u16));
+            self.data[index] = byte;
+        }
+
+        Ok(())
+    }
+}
+
+/// Port wrapper that turns OUTs on bank ports into page selections. Other
+/// ports reach the inner port as usual.
+pub struct BankPorts<P: Port> {
+    inner: P,
+    banks: Banks,
+    ports: BTreeMap<u8, usize>,
+}
+
+impl<P: Port> BankPorts<P> {
+    pub fn new(inner: P, banks: Banks) -> Self {
+        Self {
+            inner,
+            banks,
+            ports: BTreeMap::new(),
+        }
+    }
+
+    /// Makes OUTs on `port_num` select the page of `window`
+    pub fn with_port(mut self, port_num: u8, window: usize) -> Result<Self> {
+        self.banks.check_window(window)?;
+        self.ports.insert(port_num, window);
+
+        Ok(self)
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.inner
+    }
+}
+
+impl<P: Port> Port for BankPorts<P> {
+    fn read_in(&self, port_num: u8) -> u8 {
+        self.inner.read_in(port_num)
+    }
+
+    fn write_out(&mut self, port_num: u8, value: u8) {
+        match self.ports.get(&port_num) {
+            Some(&window) => self.banks.latch(window, value),
+            None => self.inner.write_out(port_num, value),
+        }
+    }
+}
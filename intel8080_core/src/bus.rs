@@ -0,0 +1,39 @@
+use crate::errors::Result;
+
+/// Direction of a memory or port access
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Address space seen by the processor. Reads take `&mut self` so that
+/// implementations can have side effects such as latches or bank switching.
+/// They return `Result` because unmapped reads under `FaultPolicy::Error`
+/// and memory-mapped devices can fail; plain RAM only ever returns `Ok`.
+pub trait Bus {
+    fn read(&mut self, address: u16) -> Result<u8>;
+    fn write(&mut self, address: u16, value: u8) -> Result<()>;
+
+    /// Reads without side effects for debuggers and disassemblers.
+    /// Returns None if the address cannot be inspected safely.
+    fn peek(&self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    /// Stores a byte without side effects, ROM included, for debuggers
+    /// restoring memory. Returns false if the address cannot be written
+    /// this way.
+    fn poke(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+
+    /// Copies a ROM image into the address space starting at `address`
+    fn load_rom(&mut self, rom: &[u8], address: u16) -> Result<()> {
+        for (offset, &byte) in rom.iter().enumerate() {
+            self.write(address.wrapping_add(offset as u16), byte)?;
+        }
+
+        Ok(())
+    }
+}
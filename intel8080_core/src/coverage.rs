@@ -0,0 +1,166 @@
+use std::{fmt::Write as _, ops::RangeInclusive};
+
+use crate::errors::{Error, Result};
+
+const ADDRESSES: usize = 0x10000;
+const BITMAP_SIZE: usize = ADDRESSES / 8;
+
+/// How the processor touched an address
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Usage {
+    /// Fetched as the first byte of an instruction
+    Opcode,
+    /// Fetched as an immediate or address operand
+    Operand,
+    /// Read as data, including stack pops
+    Read,
+    /// Written, including stack pushes
+    Write,
+}
+
+impl Usage {
+    pub const ALL: [Usage; 4] = [Usage::Opcode, Usage::Operand, Usage::Read, Usage::Write];
+
+    fn bit(self) -> u8 {
+        match self {
+            Usage::Opcode => 0b0001,
+            Usage::Operand => 0b0010,
+            Usage::Read => 0b0100,
+            Usage::Write => 0b1000,
+        }
+    }
+}
+
+/// Records for every address how it was used. Install it with
+/// `Processor::set_coverage`. Instructions supplied by interrupts are not
+/// fetched from memory and leave no coverage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coverage {
+    usage: Vec<u8>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            usage: vec![0; ADDRESSES],
+        }
+    }
+
+    pub fn is(&self, address: u16, usage: Usage) -> bool {
+        self.usage[address as usize] & usage.bit() != 0
+    }
+
+    /// True if the address was fetched as part of an instruction
+    pub fn is_code(&self, address: u16) -> bool {
+        self.is(address, Usage::Opcode) || self.is(address, Usage::Operand)
+    }
+
+    pub fn is_untouched(&self, address: u16) -> bool {
+        self.usage[address as usize] == 0
+    }
+
+    /// Number of addresses in `range` used as `usage`
+    pub fn count(&self, usage: Usage, range: RangeInclusive<u16>) -> usize {
+        range.filter(|&address| self.is(address, usage)).count()
+    }
+
+    /// Adds the coverage of another run, such as a different test input
+    pub fn merge(&mut self, other: &Coverage) {
+        for (usage, other_usage) in self.usage.iter_mut().zip(&other.usage) {
+            *usage |= other_usage;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.usage.fill(0);
+    }
+
+    /// Packs the coverage into 4 bitmaps of 8 KiB, one per `Usage` in the
+    /// order of `Usage::ALL`. Bit `n % 8` of byte `n / 8` stands for
+    /// address `n`.
+    pub fn to_bitmap(&self) -> Vec<u8> {
+        let mut bitmap = vec![0; BITMAP_SIZE * Usage::ALL.len()];
+        for (index, usage) in Usage::ALL.into_iter().enumerate() {
+            let map = &mut bitmap[index * BITMAP_SIZE..(index + 1) * BITMAP_SIZE];
+            for (address, &used) in self.usage.iter().enumerate() {
+                if used & usage.bit() != 0 {
+                    map[address / 8] |= 1 << (address % 8);
+                }
+            }
+        }
+
+        bitmap
+    }
+
+    /// Reads coverage written by `to_bitmap`
+    pub fn from_bitmap(bitmap: &[u8]) -> Result<Self> {
+        if bitmap.len() != BITMAP_SIZE * Usage::ALL.len() {
+            return Err(Error::InvalidCoverage(bitmap.len()));
+        }
+
+        let mut coverage = Self::new();
+        for (index, usage) in Usage::ALL.into_iter().enumerate() {
+            let map = &bitmap[index * BITMAP_SIZE..(index + 1) * BITMAP_SIZE];
+            for (address, used) in coverage.usage.iter_mut().enumerate() {
+                if map[address / 8] & (1 << (address % 8)) != 0 {
+                    *used |= usage.bit();
+                }
+            }
+        }
+
+        Ok(coverage)
+    }
+
+    /// Table of how much of each named region was used as code, read, written
+    /// or never touched
+    pub fn report(&self, regions: &[(&str, RangeInclusive<u16>)]) -> String {
+        let mut report = format!(
+            "{:<16}{:>12}{:>8}{:>14}{:>14}{:>14}{:>14}\n",
+            "region", "range", "bytes", "code", "read", "written", "untouched"
+        );
+
+        for (name, range) in regions {
+            let size = range.clone().count();
+            let code = range
+                .clone()
+                .filter(|&address| self.is_code(address))
+                .count();
+            let untouched = range
+                .clone()
+                .filter(|&address| self.is_untouched(address))
+                .count();
+            let percent = |count: usize| {
+                let percent = if size == 0 {
+                    0.0
+                } else {
+                    count as f64 * 100.0 / size as f64
+                };
+                format!("{count} {percent:5.1}%")
+            };
+
+            let _ = writeln!(
+                report,
+                "{:<16}{:>12}{:>8}{:>14}{:>14}{:>14}{:>14}",
+                name,
+                format!("{:04X}-{:04X}", range.start(), range.end()),
+                size,
+                percent(code),
+                percent(self.count(Usage::Read, range.clone())),
+                percent(self.count(Usage::Write, range.clone())),
+                percent(untouched),
+            );
+        }
+
+        report
+    }
+
+    pub(crate) fn mark(&mut self, address: u16, usage: Usage) {
+        self.usage[address as usize] |= usage.bit();
+    }
+}
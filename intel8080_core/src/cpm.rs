@@ -0,0 +1,136 @@
+use std::io::{self, Stdout, Write};
+
+use crate::{
+    bus::Bus, errors::Result, helpers::word_to_bytes, port::Port, processor::Processor,
+    registers::Register,
+};
+
+/// Address CP/M loads .COM programs at
+pub const COM_LOAD_ADDRESS: u16 = 0x0100;
+
+const WARM_BOOT: u16 = 0x0000;
+const BDOS_ENTRY: u16 = 0x0005;
+
+/// The BDOS itself is a single RET at the top of memory. Programs read the
+/// address at 0x0006 to find the top of the TPA and place their stack there.
+const BDOS_ADDRESS: u16 = 0xFE00;
+
+// BDOS functions
+const CONSOLE_OUTPUT: u8 = 2;
+const PRINT_STRING: u8 = 9;
+
+/// Minimal CP/M environment for running .COM test programs such as the
+/// 8080 CPU exercisers. BDOS console output is sent to `output` and the
+/// program stops when it jumps to the warm boot vector at 0x0000.
+pub struct CpmMachine<W: Write = Stdout> {
+    processor: Processor,
+    output: W,
+    cycles: u64,
+}
+
+impl CpmMachine<Stdout> {
+    pub fn with_stdout(program: &[u8]) -> Result<Self> {
+        Self::new(program, io::stdout())
+    }
+}
+
+impl<W: Write> CpmMachine<W> {
+    pub fn new(program: &[u8], output: W) -> Result<Self> {
+        let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+
+        // Warm boot vector halts, the BDOS vector jumps to a RET
+        processor.load_rom(&[0x76], WARM_BOOT)?;
+        let (low_byte, high_byte) = word_to_bytes(BDOS_ADDRESS);
+        processor.load_rom(&[0xC3, low_byte, high_byte], BDOS_ENTRY)?;
+        processor.load_rom(&[0xC9], BDOS_ADDRESS)?;
+
+        processor.load_rom(program, COM_LOAD_ADDRESS)?;
+        processor.set_register(Register::PC, COM_LOAD_ADDRESS);
+        processor.set_register(Register::SP, BDOS_ADDRESS);
+
+        Ok(Self {
+            processor,
+            output,
+            cycles: 0,
+        })
+    }
+
+    /// Executes one instruction, returning false once the program has exited
+    pub fn step(&mut self) -> Result<bool> {
+        match self.processor.pc() {
+            WARM_BOOT => return Ok(false),
+            BDOS_ENTRY => self.bdos_call()?,
+            _ => {}
+        }
+
+        self.cycles += self.processor.execute(&mut NullPort)? as u64;
+
+        Ok(true)
+    }
+
+    /// Runs until the program exits and returns the cycles it took
+    pub fn run(&mut self) -> Result<u64> {
+        while self.step()? {}
+        self.output.flush()?;
+
+        Ok(self.cycles)
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn processor(&self) -> &Processor {
+        &self.processor
+    }
+
+    pub fn processor_mut(&mut self) -> &mut Processor {
+        &mut self.processor
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    fn bdos_call(&mut self) -> Result<()> {
+        match self.processor.register(Register::C) as u8 {
+            CONSOLE_OUTPUT => {
+                let character = self.processor.register(Register::E) as u8;
+                self.output.write_all(&[character])?;
+            }
+            PRINT_STRING => {
+                let mut address = self.processor.register(Register::DE);
+                let memory = self.processor.bus_mut();
+
+                // Strings are terminated by '$', give up after wrapping memory
+                for _ in 0..=u16::MAX {
+                    let character = memory.read(address)?;
+                    if character == b'$' {
+                        break;
+                    }
+                    self.output.write_all(&[character])?;
+                    address = address.wrapping_add(1);
+                }
+            }
+            // Other functions are not needed by the test programs
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Test programs do not use IN or OUT
+struct NullPort;
+
+impl Port for NullPort {
+    fn read_in(&self, _port_num: u8) -> u8 {
+        0
+    }
+
+    fn write_out(&mut self, _port_num: u8, _value: u8) {}
+}
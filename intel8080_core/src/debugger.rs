@@ -0,0 +1,625 @@
+use std::{
+    cell::Cell,
+    collections::{BTreeSet, VecDeque},
+};
+
+pub use crate::bus::Access;
+use crate::{
+    bus::Bus,
+    disassembler::{Flow, disassemble_variant},
+    errors::Result,
+    memory::Memory,
+    port::Port,
+    processor::{Checkpoint, Processor},
+};
+
+/// Why the debugger handed control back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// A step, step over or step out finished
+    Step,
+    /// Run-to arrived at its target address
+    Reached(u16),
+    /// Execution arrived at a PC breakpoint, the instruction has not run yet
+    Breakpoint(u16),
+    /// The last instruction accessed a watched memory address
+    Watchpoint {
+        address: u16,
+        value: u8,
+        access: Access,
+    },
+    /// The last instruction used IN or OUT on a watched port
+    PortBreakpoint {
+        port_num: u8,
+        value: u8,
+        access: Access,
+    },
+    /// The processor is halted and no interrupt can wake it
+    Halted,
+    /// The cycle budget given to `run_for` or `rewind` was used up
+    CycleLimit,
+    /// Reverse execution reached the oldest recorded instruction
+    HistoryStart,
+}
+
+/// A subroutine entered through CALL, RST or an interrupt
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+    /// Address of the CALL or RST, or of the interrupted instruction
+    pub call_site: u16,
+    /// First address of the subroutine
+    pub entry: u16,
+    pub return_address: u16,
+    /// Stack address holding the return address
+    pub sp: u16,
+    pub interrupt: bool,
+}
+
+/// Bus wrapper that records accesses to watched addresses
+#[derive(Clone, Debug)]
+pub struct DebugBus<B: Bus> {
+    inner: B,
+    read_watchpoints: BTreeSet<u16>,
+    write_watchpoints: BTreeSet<u16>,
+    hits: Vec<(u16, u8, Access)>,
+    // Overwritten bytes, kept while reverse execution is enabled
+    journal: Option<Vec<(u16, u8)>>,
+}
+
+impl<B: Bus> DebugBus<B> {
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+}
+
+impl<B: Bus> Bus for DebugBus<B> {
+    fn read(&mut self, address: u16) -> Result<u8> {
+        let value = self.inner.read(address)?;
+        if self.read_watchpoints.contains(&address) {
+            self.hits.push((address, value, Access::Read));
+        }
+
+        Ok(value)
+    }
+
+    fn write(&mut self, address: u16, value: u8) -> Result<()> {
+        let old_value = self.journal.as_ref().and_then(|_| self.inner.peek(address));
+        self.inner.write(address, value)?;
+        // Only writes that stored their value are journaled, which leaves
+        // out ROM, latches and most devices
+        if let (Some(journal), Some(old_value)) = (&mut self.journal, old_value)
+            && old_value != value
+            && self.inner.peek(address) == Some(value)
+        {
+            journal.push((address, old_value));
+        }
+        if self.write_watchpoints.contains(&address) {
+            self.hits.push((address, value, Access::Write));
+        }
+
+        Ok(())
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        self.inner.peek(address)
+    }
+
+    fn poke(&mut self, address: u16, value: u8) -> bool {
+        self.inner.poke(address, value)
+    }
+
+    fn load_rom(&mut self, rom: &[u8], address: u16) -> Result<()> {
+        self.inner.load_rom(rom, address)
+    }
+}
+
+/// Everything needed to undo one instruction or interrupt
+#[derive(Clone, Debug)]
+struct UndoEntry {
+    checkpoint: Checkpoint,
+    cycles: u64,
+    // Previous values of the bytes written, in write order
+    memory: Vec<(u16, u8)>,
+    // Call stack length once popped frames were removed, and those frames
+    call_stack_kept: usize,
+    popped_frames: Vec<CallFrame>,
+}
+
+/// Port wrapper that records the first IN or OUT on a watched port
+struct DebugPort<'a, P: Port> {
+    inner: &'a mut P,
+    in_breakpoints: &'a BTreeSet<u8>,
+    out_breakpoints: &'a BTreeSet<u8>,
+    hit: Cell<Option<StopReason>>,
+}
+
+impl<P: Port> DebugPort<'_, P> {
+    fn record(&self, port_num: u8, value: u8, access: Access) {
+        if self.hit.get().is_none() {
+            self.hit.set(Some(StopReason::PortBreakpoint {
+                port_num,
+                value,
+                access,
+            }));
+        }
+    }
+}
+
+impl<P: Port> Port for DebugPort<'_, P> {
+    fn read_in(&self, port_num: u8) -> u8 {
+        let value = self.inner.read_in(port_num);
+        if self.in_breakpoints.contains(&port_num) {
+            self.record(port_num, value, Access::Read);
+        }
+
+        value
+    }
+
+    fn write_out(&mut self, port_num: u8, value: u8) {
+        self.inner.write_out(port_num, value);
+        if self.out_breakpoints.contains(&port_num) {
+            self.record(port_num, value, Access::Write);
+        }
+    }
+}
+
+/// Runs a processor under control of PC breakpoints, memory watchpoints and
+/// port breakpoints. Breakpoints are checked before an instruction runs,
+/// watchpoints stop after the instruction that triggered them.
+#[derive(Clone, Debug)]
+pub struct Debugger<B: Bus = Memory> {
+    processor: Processor<DebugBus<B>>,
+    breakpoints: BTreeSet<u16>,
+    in_breakpoints: BTreeSet<u8>,
+    out_breakpoints: BTreeSet<u8>,
+    cycles: u64,
+    call_stack: Vec<CallFrame>,
+    // Set when the last run stopped somewhere no breakpoint had been checked
+    break_on_entry: bool,
+    history: VecDeque<UndoEntry>,
+    history_capacity: usize,
+}
+
+impl<B: Bus> Debugger<B> {
+    pub fn new(processor: Processor<B>) -> Self {
+        Self {
+            processor: processor.map_bus(|inner| DebugBus {
+                inner,
+                read_watchpoints: BTreeSet::new(),
+                write_watchpoints: BTreeSet::new(),
+                hits: Vec::new(),
+                journal: None,
+            }),
+            breakpoints: BTreeSet::new(),
+            in_breakpoints: BTreeSet::new(),
+            out_breakpoints: BTreeSet::new(),
+            cycles: 0,
+            call_stack: Vec::new(),
+            break_on_entry: false,
+            history: VecDeque::new(),
+            history_capacity: 0,
+        }
+    }
+
+    /// Removes the debugger and returns the processor on its original bus
+    pub fn into_processor(self) -> Processor<B> {
+        self.processor.map_bus(|bus| bus.inner)
+    }
+
+    pub fn processor(&self) -> &Processor<DebugBus<B>> {
+        &self.processor
+    }
+
+    pub fn processor_mut(&mut self) -> &mut Processor<DebugBus<B>> {
+        &mut self.processor
+    }
+
+    /// Total cycles executed under the debugger
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Subroutines the processor is currently in, innermost last. Frames are
+    /// tracked from executed CALL and RST instructions and from interrupts,
+    /// and dropped once their return address is popped.
+    pub fn call_stack(&self) -> &[CallFrame] {
+        &self.call_stack
+    }
+
+    /// Runs an interrupt acknowledge cycle like `Processor::interrupt` and
+    /// records the interrupt in the call stack
+    pub fn interrupt(&mut self, instruction: &[u8], port: &mut impl Port) -> Result<Option<u32>> {
+        let (pc, sp) = (self.processor.pc(), self.processor.sp());
+        let undo = self.begin_undo();
+        let Some(cycles) = self.processor.interrupt(instruction, port)? else {
+            return Ok(None);
+        };
+        self.finish_undo(undo, self.call_stack.len(), Vec::new());
+        self.cycles += cycles as u64;
+
+        // Stop at a breakpoint on the interrupt handler
+        self.break_on_entry = true;
+        if self.processor.sp() == sp.wrapping_sub(2) {
+            self.call_stack.push(CallFrame {
+                call_site: pc,
+                entry: self.processor.pc(),
+                return_address: pc,
+                sp: self.processor.sp(),
+                interrupt: true,
+            });
+        }
+
+        Ok(Some(cycles))
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, address: u16, access: Access) {
+        self.watchpoints_mut(access).insert(address);
+    }
+
+    pub fn remove_watchpoint(&mut self, address: u16, access: Access) -> bool {
+        self.watchpoints_mut(access).remove(&address)
+    }
+
+    /// Stops after IN (`Access::Read`) or OUT (`Access::Write`) on `port_num`
+    pub fn add_port_breakpoint(&mut self, port_num: u8, access: Access) {
+        self.port_breakpoints_mut(access).insert(port_num);
+    }
+
+    pub fn remove_port_breakpoint(&mut self, port_num: u8, access: Access) -> bool {
+        self.port_breakpoints_mut(access).remove(&port_num)
+    }
+
+    /// Records up to `capacity` instructions and interrupts so that they can
+    /// be undone, 0 turns recording off and drops the history. Port
+    /// accesses, bank switching and changes made through `processor_mut`
+    /// are not recorded, nor are bytes the bus cannot peek and poke.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history_capacity = capacity;
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+        self.processor.bus_mut().journal = (capacity > 0).then(Vec::new);
+    }
+
+    /// Number of instructions and interrupts that can be undone
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Executes a single instruction
+    pub fn step(&mut self, port: &mut impl Port) -> Result<StopReason> {
+        self.run_until(port, None, |_, _| Some(StopReason::Step))
+    }
+
+    /// Executes one instruction, running a CALL or RST until it returns
+    pub fn step_over(&mut self, port: &mut impl Port) -> Result<StopReason> {
+        let processor = &self.processor;
+        let Some(instruction) =
+            disassemble_variant(processor.bus(), processor.pc(), processor.variant())
+        else {
+            return self.step(port);
+        };
+        if !matches!(instruction.flow, Flow::Call | Flow::Restart) {
+            return self.step(port);
+        }
+
+        // Recursive calls pass the return address with a deeper stack. SP is
+        // compared as a distance so that stacks wrapping past 0 still work.
+        let return_address = instruction.next_address();
+        let sp = self.processor.sp();
+        self.run_until(port, None, |processor, _| {
+            (processor.pc() == return_address && processor.sp().wrapping_sub(sp) as i16 >= 0)
+                .then_some(StopReason::Step)
+        })
+    }
+
+    /// Runs until the current subroutine returns to its caller
+    pub fn step_out(&mut self, port: &mut impl Port) -> Result<StopReason> {
+        // Returns from nested calls leave SP at or below its current value
+        let sp = self.processor.sp();
+        self.run_until(port, None, |processor, flow| {
+            (flow == Flow::Return && processor.sp().wrapping_sub(sp) as i16 > 0)
+                .then_some(StopReason::Step)
+        })
+    }
+
+    /// Runs until PC reaches `address`
+    pub fn run_to(&mut self, address: u16, port: &mut impl Port) -> Result<StopReason> {
+        self.run_until(port, None, |processor, _| {
+            (processor.pc() == address).then_some(StopReason::Reached(address))
+        })
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit or the processor halts
+    pub fn run(&mut self, port: &mut impl Port) -> Result<StopReason> {
+        self.run_until(port, None, |_, _| None)
+    }
+
+    /// Like `run` but also stops once at least `cycles` cycles have passed
+    pub fn run_for(&mut self, cycles: u64, port: &mut impl Port) -> Result<StopReason> {
+        self.run_until(port, Some(cycles), |_, _| None)
+    }
+
+    /// Undoes the last instruction or interrupt
+    pub fn step_back(&mut self) -> Result<StopReason> {
+        self.run_back(|_, _| Some(StopReason::Step))
+    }
+
+    /// Runs backwards until PC reaches a breakpoint or an instruction that
+    /// wrote a write watchpoint is undone. The watchpoint reports the value
+    /// that instruction wrote.
+    pub fn run_back_to_breakpoint(&mut self) -> Result<StopReason> {
+        self.run_back(|debugger, overwritten| {
+            let pc = debugger.processor.pc();
+            let write_watchpoints = &debugger.processor.bus().write_watchpoints;
+
+            overwritten
+                .iter()
+                .find(|(address, _)| write_watchpoints.contains(address))
+                .map(|&(address, value)| StopReason::Watchpoint {
+                    address,
+                    value,
+                    access: Access::Write,
+                })
+                .or_else(|| {
+                    debugger
+                        .breakpoints
+                        .contains(&pc)
+                        .then_some(StopReason::Breakpoint(pc))
+                })
+        })
+    }
+
+    /// Runs backwards until at least `cycles` cycles have been undone
+    pub fn rewind(&mut self, cycles: u64) -> Result<StopReason> {
+        // Going back further than the start can only end at the history start
+        let target = self.cycles.checked_sub(cycles);
+        self.run_back(|debugger, _| {
+            target
+                .is_some_and(|target| debugger.cycles <= target)
+                .then_some(StopReason::CycleLimit)
+        })
+    }
+
+    fn watchpoints_mut(&mut self, access: Access) -> &mut BTreeSet<u16> {
+        let bus = self.processor.bus_mut();
+        match access {
+            Access::Read => &mut bus.read_watchpoints,
+            Access::Write => &mut bus.write_watchpoints,
+        }
+    }
+
+    fn port_breakpoints_mut(&mut self, access: Access) -> &mut BTreeSet<u8> {
+        match access {
+            Access::Read => &mut self.in_breakpoints,
+            Access::Write => &mut self.out_breakpoints,
+        }
+    }
+
+    /// Executes instructions until `done` returns a reason to stop. `done`
+    /// sees the processor after each instruction along with its flow. The
+    /// first instruction runs even if a breakpoint sits on it so that
+    /// execution can resume from a breakpoint, unless the previous run was
+    /// only cut short by its cycle budget.
+    fn run_until(
+        &mut self,
+        port: &mut impl Port,
+        max_cycles: Option<u64>,
+        mut done: impl FnMut(&Processor<DebugBus<B>>, Flow) -> Option<StopReason>,
+    ) -> Result<StopReason> {
+        let mut spent: u64 = 0;
+        let mut check_breakpoint = std::mem::take(&mut self.break_on_entry);
+
+        loop {
+            let pc = self.processor.pc();
+            if check_breakpoint && self.breakpoints.contains(&pc) {
+                return Ok(StopReason::Breakpoint(pc));
+            }
+            check_breakpoint = true;
+
+            // An interrupt serviced instead of the instruction at PC acts
+            // like an RST returning to PC and fetches nothing from memory
+            let interrupt = self.processor.interrupt_due();
+            let (flow, length) = if interrupt {
+                (Flow::Restart, 0)
+            } else {
+                disassemble_variant(self.processor.bus(), pc, self.processor.variant())
+                    .map_or((Flow::Sequential, 1), |instruction| {
+                        (instruction.flow, instruction.length as u16)
+                    })
+            };
+
+            let (cycles, hit) = self.execute_once(pc, flow, length, interrupt, port)?;
+            spent += cycles as u64;
+
+            if let Some(reason) = hit {
+                return Ok(reason);
+            }
+            if let Some(reason) = done(&self.processor, flow) {
+                return Ok(reason);
+            }
+            if max_cycles.is_some_and(|max_cycles| spent >= max_cycles) {
+                self.break_on_entry = true;
+                return Ok(StopReason::CycleLimit);
+            }
+        }
+    }
+
+    fn begin_undo(&mut self) -> Option<(Checkpoint, u64)> {
+        let journal = self.processor.bus_mut().journal.as_mut()?;
+        journal.clear();
+
+        Some((self.processor.checkpoint(), self.cycles))
+    }
+
+    /// Records an undo entry for the instruction started with `begin_undo`.
+    /// `call_stack_kept` frames of the call stack were left untouched and
+    /// `popped_frames` were removed, innermost first.
+    fn finish_undo(
+        &mut self,
+        undo: Option<(Checkpoint, u64)>,
+        call_stack_kept: usize,
+        popped_frames: Vec<CallFrame>,
+    ) {
+        let Some((checkpoint, cycles)) = undo else {
+            return;
+        };
+        let memory = self
+            .processor
+            .bus_mut()
+            .journal
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+
+        if self.history.len() == self.history_capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(UndoEntry {
+            checkpoint,
+            cycles,
+            memory,
+            call_stack_kept,
+            popped_frames,
+        });
+    }
+
+    /// Undoes recorded entries until `done` returns a reason to stop. `done`
+    /// sees the debugger after each undo along with the addresses it
+    /// restored and the values they held before.
+    fn run_back(
+        &mut self,
+        mut done: impl FnMut(&Self, &[(u16, u8)]) -> Option<StopReason>,
+    ) -> Result<StopReason> {
+        self.break_on_entry = false;
+
+        loop {
+            let Some(entry) = self.history.pop_back() else {
+                return Ok(StopReason::HistoryStart);
+            };
+
+            // Poked rather than written so that latches and devices do not
+            // see the restore and ROM takes it
+            let bus = &mut self.processor.bus_mut().inner;
+            let mut overwritten = Vec::with_capacity(entry.memory.len());
+            for &(address, value) in entry.memory.iter().rev() {
+                overwritten.push((address, bus.peek(address).unwrap_or(value)));
+                bus.poke(address, value);
+            }
+            overwritten.reverse();
+
+            self.processor.restore(&entry.checkpoint);
+            self.cycles = entry.cycles;
+            self.call_stack.truncate(entry.call_stack_kept);
+            self.call_stack
+                .extend(entry.popped_frames.iter().rev().copied());
+
+            if let Some(reason) = done(self, &overwritten) {
+                return Ok(reason);
+            }
+        }
+    }
+
+    /// Executes one instruction or interrupt and reports the first
+    /// watchpoint it hit. Reads of the `length` instruction bytes at `pc`
+    /// are opcode fetches and do not trigger read watchpoints.
+    fn execute_once(
+        &mut self,
+        pc: u16,
+        flow: Flow,
+        length: u16,
+        interrupt: bool,
+        port: &mut impl Port,
+    ) -> Result<(u32, Option<StopReason>)> {
+        let sp = self.processor.sp();
+        self.processor.bus_mut().hits.clear();
+        let undo = self.begin_undo();
+
+        let mut debug_port = DebugPort {
+            inner: port,
+            in_breakpoints: &self.in_breakpoints,
+            out_breakpoints: &self.out_breakpoints,
+            hit: Cell::new(None),
+        };
+        let cycles = self.processor.execute(&mut debug_port)?;
+        self.cycles += cycles as u64;
+        let port_hit = debug_port.hit.get();
+        let (call_stack_kept, popped_frames) = self.track_call(pc, sp, flow, length, interrupt);
+        self.finish_undo(undo, call_stack_kept, popped_frames);
+
+        let memory_hit = self
+            .processor
+            .bus()
+            .hits
+            .iter()
+            .find(|&&(address, _, access)| {
+                access == Access::Write || address.wrapping_sub(pc) >= length
+            })
+            .map(|&(address, value, access)| StopReason::Watchpoint {
+                address,
+                value,
+                access,
+            });
+
+        let stuck = self.processor.is_halted() && !self.processor.interrupt_due();
+        let hit = memory_hit
+            .or(port_hit)
+            .or(stuck.then_some(StopReason::Halted));
+
+        Ok((cycles, hit))
+    }
+
+    /// Updates the call stack after an instruction or interrupt. Returns how
+    /// many frames were kept and the frames that were popped, innermost
+    /// first.
+    fn track_call(
+        &mut self,
+        pc: u16,
+        previous_sp: u16,
+        flow: Flow,
+        length: u16,
+        interrupt: bool,
+    ) -> (usize, Vec<CallFrame>) {
+        let sp = self.processor.sp();
+
+        // Returns and stack adjustments discard the frames they popped. SP
+        // is compared as an offset so that stacks at 0x0000 wrap.
+        let mut popped_frames = Vec::new();
+        while self
+            .call_stack
+            .last()
+            .is_some_and(|frame| sp.wrapping_sub(frame.sp) as i16 > 0)
+        {
+            popped_frames.extend(self.call_stack.pop());
+        }
+        let kept = self.call_stack.len();
+
+        if matches!(flow, Flow::Call | Flow::Restart) && sp == previous_sp.wrapping_sub(2) {
+            self.call_stack.push(CallFrame {
+                call_site: pc,
+                entry: self.processor.pc(),
+                return_address: pc.wrapping_add(length),
+                sp,
+                interrupt,
+            });
+        }
+
+        (kept, popped_frames)
+    }
+}
@@ -0,0 +1,458 @@
+use std::fmt;
+
+use crate::{
+    bus::Bus,
+    coverage::{Coverage, Usage},
+    dispatch::timing_8085,
+    helpers::bytes_to_word,
+    i8085::Variant,
+};
+
+const REGISTERS: [&str; 8] = ["B", "C", "D", "E", "H", "L", "M", "A"];
+const REGISTER_PAIRS: [&str; 4] = ["B", "D", "H", "SP"];
+const STACK_PAIRS: [&str; 4] = ["B", "D", "H", "PSW"];
+
+/// A decoded instruction in Intel syntax
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub bytes: [u8; 3],
+    pub length: u8,
+    pub mnemonic: &'static str,
+    pub operands: String,
+    /// Cycles when no branch is taken
+    pub cycles: u32,
+    /// Cycles for conditional calls and returns when the branch is taken
+    pub cycles_taken: Option<u32>,
+    pub flow: Flow,
+    /// Opcode is an undocumented alias of another instruction
+    pub undocumented: bool,
+}
+
+/// How an instruction affects the flow of execution
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Sequential,
+    Jump,
+    Call,
+    Return,
+    Restart,
+    Halt,
+}
+
+impl Instruction {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.length as usize]
+    }
+
+    /// Address of the instruction that follows this one
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.length as u16)
+    }
+
+    /// Branch target of jumps, calls and restarts
+    pub fn target(&self) -> Option<u16> {
+        match self.flow {
+            Flow::Jump | Flow::Call if self.mnemonic != "PCHL" => {
+                Some(bytes_to_word(self.bytes[1], self.bytes[2]))
+            }
+            Flow::Restart if self.mnemonic == "RSTV" => Some(0x40),
+            Flow::Restart => Some((self.bytes[0] & 0b111000) as u16),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.operands.is_empty() {
+            f.write_str(self.mnemonic)
+        } else {
+            write!(f, "{} {}", self.mnemonic, self.operands)
+        }
+    }
+}
+
+/// Static properties of an opcode, independent of its operand bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub mnemonic: &'static str,
+    pub length: u8,
+    pub cycles: u32,
+    pub cycles_taken: Option<u32>,
+    pub flow: Flow,
+    pub undocumented: bool,
+    operands: Operands,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operands {
+    None,
+    Register(usize),
+    RegisterRegister(usize, usize),
+    RegisterByte(usize),
+    Pair(&'static str),
+    PairWord(&'static str),
+    Byte,
+    Word,
+    Restart(u8),
+}
+
+const fn info(mnemonic: &'static str, length: u8, cycles: u32, operands: Operands) -> OpcodeInfo {
+    OpcodeInfo {
+        mnemonic,
+        length,
+        cycles,
+        cycles_taken: None,
+        flow: Flow::Sequential,
+        undocumented: false,
+        operands,
+    }
+}
+
+/// Looks up the mnemonic, length and cycle counts of an opcode
+pub fn opcode_info(opcode: u8) -> OpcodeInfo {
+    let destination = ((opcode >> 3) & 0b111) as usize;
+    let source = (opcode & 0b111) as usize;
+    let pair = ((opcode >> 4) & 0b11) as usize;
+    let memory_cycles =
+        |register: usize, cycles, memory| if register == 6 { memory } else { cycles };
+
+    match opcode {
+        0x00 => info("NOP", 1, 4, Operands::None),
+        0x76 => OpcodeInfo {
+            flow: Flow::Halt,
+            ..info("HLT", 1, 7, Operands::None)
+        },
+
+        0x40..=0x7F => info(
+            "MOV",
+            1,
+            memory_cycles(source, 5, 7).max(memory_cycles(destination, 5, 7)),
+            Operands::RegisterRegister(destination, source),
+        ),
+        0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => info(
+            "MVI",
+            2,
+            memory_cycles(destination, 7, 10),
+            Operands::RegisterByte(destination),
+        ),
+        0x01 | 0x11 | 0x21 | 0x31 => info("LXI", 3, 10, Operands::PairWord(REGISTER_PAIRS[pair])),
+        0x3A => info("LDA", 3, 13, Operands::Word),
+        0x32 => info("STA", 3, 13, Operands::Word),
+        0x2A => info("LHLD", 3, 16, Operands::Word),
+        0x22 => info("SHLD", 3, 16, Operands::Word),
+        0x0A | 0x1A => info("LDAX", 1, 7, Operands::Pair(REGISTER_PAIRS[pair])),
+        0x02 | 0x12 => info("STAX", 1, 7, Operands::Pair(REGISTER_PAIRS[pair])),
+        0xEB => info("XCHG", 1, 4, Operands::None),
+
+        0x80..=0xBF => {
+            let mnemonic = ["ADD", "ADC", "SUB", "SBB", "ANA", "XRA", "ORA", "CMP"][destination];
+            info(
+                mnemonic,
+                1,
+                memory_cycles(source, 4, 7),
+                Operands::Register(source),
+            )
+        }
+        0xC6 | 0xCE | 0xD6 | 0xDE | 0xE6 | 0xEE | 0xF6 | 0xFE => {
+            let mnemonic = ["ADI", "ACI", "SUI", "SBI", "ANI", "XRI", "ORI", "CPI"][destination];
+            info(mnemonic, 2, 7, Operands::Byte)
+        }
+
+        0x04 | 0x0C | 0x14 | 0x1C | 0x24 | 0x2C | 0x34 | 0x3C => info(
+            "INR",
+            1,
+            memory_cycles(destination, 5, 10),
+            Operands::Register(destination),
+        ),
+        0x05 | 0x0D | 0x15 | 0x1D | 0x25 | 0x2D | 0x35 | 0x3D => info(
+            "DCR",
+            1,
+            memory_cycles(destination, 5, 10),
+            Operands::Register(destination),
+        ),
+        0x03 | 0x13 | 0x23 | 0x33 => info("INX", 1, 5, Operands::Pair(REGISTER_PAIRS[pair])),
+        0x0B | 0x1B | 0x2B | 0x3B => info("DCX", 1, 5, Operands::Pair(REGISTER_PAIRS[pair])),
+        0x09 | 0x19 | 0x29 | 0x39 => info("DAD", 1, 10, Operands::Pair(REGISTER_PAIRS[pair])),
+        0x27 => info("DAA", 1, 4, Operands::None),
+
+        0x07 => info("RLC", 1, 4, Operands::None),
+        0x0F => info("RRC", 1, 4, Operands::None),
+        0x17 => info("RAL", 1, 4, Operands::None),
+        0x1F => info("RAR", 1, 4, Operands::None),
+        0x2F => info("CMA", 1, 4, Operands::None),
+        0x3F => info("CMC", 1, 4, Operands::None),
+        0x37 => info("STC", 1, 4, Operands::None),
+
+        0xC3 => OpcodeInfo {
+            flow: Flow::Jump,
+            ..info("JMP", 3, 10, Operands::Word)
+        },
+        0xC2 | 0xCA | 0xD2 | 0xDA | 0xE2 | 0xEA | 0xF2 | 0xFA => {
+            let mnemonic = ["JNZ", "JZ", "JNC", "JC", "JPO", "JPE", "JP", "JM"][destination];
+            OpcodeInfo {
+                flow: Flow::Jump,
+                ..info(mnemonic, 3, 10, Operands::Word)
+            }
+        }
+        0xCD => OpcodeInfo {
+            flow: Flow::Call,
+            ..info("CALL", 3, 17, Operands::Word)
+        },
+        0xC4 | 0xCC | 0xD4 | 0xDC | 0xE4 | 0xEC | 0xF4 | 0xFC => {
+            let mnemonic = ["CNZ", "CZ", "CNC", "CC", "CPO", "CPE", "CP", "CM"][destination];
+            OpcodeInfo {
+                cycles_taken: Some(17),
+                flow: Flow::Call,
+                ..info(mnemonic, 3, 11, Operands::Word)
+            }
+        }
+        0xC9 => OpcodeInfo {
+            flow: Flow::Return,
+            ..info("RET", 1, 10, Operands::None)
+        },
+        0xC0 | 0xC8 | 0xD0 | 0xD8 | 0xE0 | 0xE8 | 0xF0 | 0xF8 => {
+            let mnemonic = ["RNZ", "RZ", "RNC", "RC", "RPO", "RPE", "RP", "RM"][destination];
+            OpcodeInfo {
+                cycles_taken: Some(11),
+                flow: Flow::Return,
+                ..info(mnemonic, 1, 5, Operands::None)
+            }
+        }
+        0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => OpcodeInfo {
+            flow: Flow::Restart,
+            ..info("RST", 1, 11, Operands::Restart(destination as u8))
+        },
+        0xE9 => OpcodeInfo {
+            flow: Flow::Jump,
+            ..info("PCHL", 1, 5, Operands::None)
+        },
+
+        0xC5 | 0xD5 | 0xE5 | 0xF5 => info("PUSH", 1, 11, Operands::Pair(STACK_PAIRS[pair])),
+        0xC1 | 0xD1 | 0xE1 | 0xF1 => info("POP", 1, 10, Operands::Pair(STACK_PAIRS[pair])),
+        0xE3 => info("XTHL", 1, 18, Operands::None),
+        0xF9 => info("SPHL", 1, 5, Operands::None),
+
+        0xDB => info("IN", 2, 10, Operands::Byte),
+        0xD3 => info("OUT", 2, 10, Operands::Byte),
+        0xFB => info("EI", 1, 4, Operands::None),
+        0xF3 => info("DI", 1, 4, Operands::None),
+
+        // Undocumented aliases
+        0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => OpcodeInfo {
+            undocumented: true,
+            ..info("NOP", 1, 4, Operands::None)
+        },
+        0xCB => OpcodeInfo {
+            flow: Flow::Jump,
+            undocumented: true,
+            ..info("JMP", 3, 10, Operands::Word)
+        },
+        0xD9 => OpcodeInfo {
+            flow: Flow::Return,
+            undocumented: true,
+            ..info("RET", 1, 10, Operands::None)
+        },
+        0xDD | 0xED | 0xFD => OpcodeInfo {
+            flow: Flow::Call,
+            undocumented: true,
+            ..info("CALL", 3, 17, Operands::Word)
+        },
+    }
+}
+
+/// Like `opcode_info` for the opcodes and timings of `variant`. The 8085
+/// always decodes its undocumented opcodes, they are marked as such unless
+/// the variant runs them.
+pub fn variant_opcode_info(variant: Variant, opcode: u8) -> OpcodeInfo {
+    let info_8080 = opcode_info(opcode);
+    if !variant.is_8085() {
+        return info_8080;
+    }
+
+    let undocumented = variant != Variant::Intel8085Undocumented;
+    let info_8085 = match opcode {
+        0x20 => return info("RIM", 1, 4, Operands::None),
+        0x30 => return info("SIM", 1, 4, Operands::None),
+        0x08 => info("DSUB", 1, 10, Operands::None),
+        0x10 => info("ARHL", 1, 7, Operands::None),
+        0x18 => info("RDEL", 1, 10, Operands::None),
+        0x28 => info("LDHI", 2, 10, Operands::Byte),
+        0x38 => info("LDSI", 2, 10, Operands::Byte),
+        0xCB => OpcodeInfo {
+            cycles_taken: Some(12),
+            flow: Flow::Restart,
+            ..info("RSTV", 1, 6, Operands::None)
+        },
+        0xD9 => info("SHLX", 1, 10, Operands::None),
+        0xDD => OpcodeInfo {
+            cycles_taken: Some(10),
+            flow: Flow::Jump,
+            ..info("JNK", 3, 7, Operands::Word)
+        },
+        0xFD => OpcodeInfo {
+            cycles_taken: Some(10),
+            flow: Flow::Jump,
+            ..info("JK", 3, 7, Operands::Word)
+        },
+        0xED => info("LHLX", 1, 10, Operands::None),
+        _ => {
+            let (cycles, cycles_taken) = timing_8085(
+                opcode,
+                info_8080.cycles as u8,
+                info_8080.cycles_taken.unwrap_or(info_8080.cycles) as u8,
+            );
+            return OpcodeInfo {
+                cycles: cycles as u32,
+                // Conditional jumps gain a taken count
+                cycles_taken: (info_8080.cycles_taken.is_some() || cycles_taken != cycles)
+                    .then_some(cycles_taken as u32),
+                ..info_8080
+            };
+        }
+    };
+
+    OpcodeInfo {
+        undocumented,
+        ..info_8085
+    }
+}
+
+/// Decodes the instruction at the start of `bytes`, which is located at
+/// `address`. Returns None if the slice ends before the instruction does.
+pub fn disassemble(bytes: &[u8], address: u16) -> Option<Instruction> {
+    disassemble_for(bytes, address, Variant::Intel8080)
+}
+
+/// Decodes the instruction at the start of `bytes` as `variant` runs it
+pub fn disassemble_for(bytes: &[u8], address: u16, variant: Variant) -> Option<Instruction> {
+    let info = variant_opcode_info(variant, *bytes.first()?);
+    let length = info.length as usize;
+    if bytes.len() < length {
+        return None;
+    }
+
+    let mut instruction_bytes = [0; 3];
+    instruction_bytes[..length].copy_from_slice(&bytes[..length]);
+
+    Some(decode(info, instruction_bytes, address))
+}
+
+/// Decodes every instruction in `bytes`, stopping at a truncated instruction
+pub fn disassemble_all(bytes: &[u8], origin: u16) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while let Some(instruction) = disassemble(&bytes[offset..], origin.wrapping_add(offset as u16))
+    {
+        offset += instruction.length as usize;
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+/// Decodes `bytes` like `disassemble_all`, using coverage to tell code from
+/// data. Bytes read or written but never executed become `DB` bytes, as do
+/// untouched bytes that would decode into them. Untouched code is decoded
+/// since it may be on a path the run did not take.
+pub fn disassemble_covered(bytes: &[u8], origin: u16, coverage: &Coverage) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let address = origin.wrapping_add(offset as u16);
+        let decoded = disassemble(&bytes[offset..], address).filter(|instruction| {
+            coverage.is(address, Usage::Opcode)
+                || (0..instruction.length as u16)
+                    .all(|index| coverage.is_untouched(address.wrapping_add(index)))
+        });
+
+        let instruction = decoded.unwrap_or_else(|| data_byte(bytes[offset], address));
+        offset += instruction.length as usize;
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+/// Decodes the instruction at `address` without side effects on the bus.
+/// Returns None if the bus cannot be peeked at that address.
+pub fn disassemble_bus(bus: &impl Bus, address: u16) -> Option<Instruction> {
+    disassemble_variant(bus, address, Variant::Intel8080)
+}
+
+/// Like `disassemble_bus` for the opcodes and timings of `variant`
+pub fn disassemble_variant(bus: &impl Bus, address: u16, variant: Variant) -> Option<Instruction> {
+    let info = variant_opcode_info(variant, bus.peek(address)?);
+
+    let mut instruction_bytes = [0; 3];
+    for (offset, byte) in instruction_bytes
+        .iter_mut()
+        .enumerate()
+        .take(info.length as usize)
+    {
+        *byte = bus.peek(address.wrapping_add(offset as u16))?;
+    }
+
+    Some(decode(info, instruction_bytes, address))
+}
+
+fn decode(info: OpcodeInfo, bytes: [u8; 3], address: u16) -> Instruction {
+    let byte = bytes[1];
+    let word = bytes_to_word(bytes[1], bytes[2]);
+
+    let operands = match info.operands {
+        Operands::None => String::new(),
+        Operands::Register(register) => REGISTERS[register].to_string(),
+        Operands::RegisterRegister(destination, source) => {
+            format!("{},{}", REGISTERS[destination], REGISTERS[source])
+        }
+        Operands::RegisterByte(register) => {
+            format!("{},{}", REGISTERS[register], intel_hex(byte as u16, 2))
+        }
+        Operands::Pair(pair) => pair.to_string(),
+        Operands::PairWord(pair) => format!("{},{}", pair, intel_hex(word, 4)),
+        Operands::Byte => intel_hex(byte as u16, 2),
+        Operands::Word => intel_hex(word, 4),
+        Operands::Restart(num) => num.to_string(),
+    };
+
+    Instruction {
+        address,
+        bytes,
+        length: info.length,
+        mnemonic: info.mnemonic,
+        operands,
+        cycles: info.cycles,
+        cycles_taken: info.cycles_taken,
+        flow: info.flow,
+        undocumented: info.undocumented,
+    }
+}
+
+/// A byte listed as data with `DB`
+fn data_byte(byte: u8, address: u16) -> Instruction {
+    Instruction {
+        address,
+        bytes: [byte, 0, 0],
+        length: 1,
+        mnemonic: "DB",
+        operands: intel_hex(byte as u16, 2),
+        cycles: 0,
+        cycles_taken: None,
+        flow: Flow::Sequential,
+        undocumented: false,
+    }
+}
+
+/// Formats a number as an Intel hex literal such as `2400H` or `0FFH`
+fn intel_hex(value: u16, digits: usize) -> String {
+    let hex = format!("{:0digits$X}H", value);
+    if hex.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        format!("0{hex}")
+    } else {
+        hex
+    }
+}
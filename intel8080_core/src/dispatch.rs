@@ -0,0 +1,456 @@
+use std::marker::PhantomData;
+
+use crate::{bus::Bus, errors::Result, port::Port, processor::Processor};
+
+// Bits of the F register, V and K only exist on the 8085
+pub(crate) const CY: u8 = 0x01;
+pub(crate) const V: u8 = 0x02;
+pub(crate) const P: u8 = 0x04;
+pub(crate) const AC: u8 = 0x10;
+pub(crate) const K: u8 = 0x20;
+pub(crate) const Z: u8 = 0x40;
+pub(crate) const S: u8 = 0x80;
+pub(crate) const SZAP: u8 = S | Z | AC | P;
+pub(crate) const ALL: u8 = SZAP | CY;
+
+/// S, Z and P flags of every 8-bit result
+pub(crate) const SZP_FLAGS: [u8; 256] = szp_flags();
+
+/// Executes an instruction after its operand has been fetched and PC moved
+/// past it. Returns true if a conditional call or return was taken.
+pub(crate) type Handler<B, Pt> = fn(&mut Processor<B>, &mut Pt, u8, u16) -> Result<bool>;
+
+pub(crate) struct Entry<B: Bus, Pt: Port> {
+    pub handler: Handler<B, Pt>,
+    pub length: u8,
+    pub cycles: u8,
+    /// Cycles when a conditional call or return is taken
+    pub cycles_taken: u8,
+    /// Flags the instruction changes, the others keep their value
+    pub flags: u8,
+}
+
+// Derived impls would needlessly require B and Pt to be Clone
+impl<B: Bus, Pt: Port> Clone for Entry<B, Pt> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B: Bus, Pt: Port> Copy for Entry<B, Pt> {}
+
+/// Decode tables for a bus and port type
+pub(crate) struct Dispatch<B, Pt>(PhantomData<fn(B, Pt)>);
+
+impl<B: Bus, Pt: Port> Dispatch<B, Pt> {
+    /// The 8080, the 8080 running undocumented opcodes as aliases, the 8085
+    /// and the 8085 with its undocumented opcodes
+    pub(crate) const TABLES: [[Entry<B, Pt>; 256]; 4] = [
+        Self::I8080,
+        aliases_8080(Self::I8080),
+        i8085(Self::I8080),
+        undocumented_8085(i8085(Self::I8080)),
+    ];
+
+    const I8080: [Entry<B, Pt>; 256] = [
+        entry(Processor::nop_opcode, 1, 4, 0),              // 00 NOP
+        entry(Processor::lxi_opcode::<0, _>, 3, 10, 0),     // 01 LXI B
+        entry(Processor::stax_opcode::<0, _>, 1, 7, 0),     // 02 STAX B
+        entry(Processor::inx_opcode::<0, _>, 1, 5, 0),      // 03 INX B
+        entry(Processor::inr_opcode::<0, _>, 1, 5, SZAP),   // 04 INR B
+        entry(Processor::dcr_opcode::<0, _>, 1, 5, SZAP),   // 05 DCR B
+        entry(Processor::mvi_opcode::<0, _>, 2, 7, 0),      // 06 MVI B
+        entry(Processor::rlc_opcode, 1, 4, CY),             // 07 RLC
+        entry(Processor::unknown_opcode, 1, 4, 0),          // 08 undocumented
+        entry(Processor::dad_opcode::<0, _>, 1, 10, CY),    // 09 DAD B
+        entry(Processor::ldax_opcode::<0, _>, 1, 7, 0),     // 0A LDAX B
+        entry(Processor::dcx_opcode::<0, _>, 1, 5, 0),      // 0B DCX B
+        entry(Processor::inr_opcode::<1, _>, 1, 5, SZAP),   // 0C INR C
+        entry(Processor::dcr_opcode::<1, _>, 1, 5, SZAP),   // 0D DCR C
+        entry(Processor::mvi_opcode::<1, _>, 2, 7, 0),      // 0E MVI C
+        entry(Processor::rrc_opcode, 1, 4, CY),             // 0F RRC
+        entry(Processor::unknown_opcode, 1, 4, 0),          // 10 undocumented
+        entry(Processor::lxi_opcode::<1, _>, 3, 10, 0),     // 11 LXI D
+        entry(Processor::stax_opcode::<1, _>, 1, 7, 0),     // 12 STAX D
+        entry(Processor::inx_opcode::<1, _>, 1, 5, 0),      // 13 INX D
+        entry(Processor::inr_opcode::<2, _>, 1, 5, SZAP),   // 14 INR D
+        entry(Processor::dcr_opcode::<2, _>, 1, 5, SZAP),   // 15 DCR D
+        entry(Processor::mvi_opcode::<2, _>, 2, 7, 0),      // 16 MVI D
+        entry(Processor::ral_opcode, 1, 4, CY),             // 17 RAL
+        entry(Processor::unknown_opcode, 1, 4, 0),          // 18 undocumented
+        entry(Processor::dad_opcode::<1, _>, 1, 10, CY),    // 19 DAD D
+        entry(Processor::ldax_opcode::<1, _>, 1, 7, 0),     // 1A LDAX D
+        entry(Processor::dcx_opcode::<1, _>, 1, 5, 0),      // 1B DCX D
+        entry(Processor::inr_opcode::<3, _>, 1, 5, SZAP),   // 1C INR E
+        entry(Processor::dcr_opcode::<3, _>, 1, 5, SZAP),   // 1D DCR E
+        entry(Processor::mvi_opcode::<3, _>, 2, 7, 0),      // 1E MVI E
+        entry(Processor::rar_opcode, 1, 4, CY),             // 1F RAR
+        entry(Processor::unknown_opcode, 1, 4, 0),          // 20 undocumented
+        entry(Processor::lxi_opcode::<2, _>, 3, 10, 0),     // 21 LXI H
+        entry(Processor::shld_opcode, 3, 16, 0),            // 22 SHLD
+        entry(Processor::inx_opcode::<2, _>, 1, 5, 0),      // 23 INX H
+        entry(Processor::inr_opcode::<4, _>, 1, 5, SZAP),   // 24 INR H
+        entry(Processor::dcr_opcode::<4, _>, 1, 5, SZAP),   // 25 DCR H
+        entry(Processor::mvi_opcode::<4, _>, 2, 7, 0),      // 26 MVI H
+        entry(Processor::daa_opcode, 1, 4, ALL),            // 27 DAA
+        entry(Processor::unknown_opcode, 1, 4, 0),          // 28 undocumented
+        entry(Processor::dad_opcode::<2, _>, 1, 10, CY),    // 29 DAD H
+        entry(Processor::lhld_opcode, 3, 16, 0),            // 2A LHLD
+        entry(Processor::dcx_opcode::<2, _>, 1, 5, 0),      // 2B DCX H
+        entry(Processor::inr_opcode::<5, _>, 1, 5, SZAP),   // 2C INR L
+        entry(Processor::dcr_opcode::<5, _>, 1, 5, SZAP),   // 2D DCR L
+        entry(Processor::mvi_opcode::<5, _>, 2, 7, 0),      // 2E MVI L
+        entry(Processor::cma_opcode, 1, 4, 0),              // 2F CMA
+        entry(Processor::unknown_opcode, 1, 4, 0),          // 30 undocumented
+        entry(Processor::lxi_opcode::<3, _>, 3, 10, 0),     // 31 LXI SP
+        entry(Processor::sta_opcode, 3, 13, 0),             // 32 STA
+        entry(Processor::inx_opcode::<3, _>, 1, 5, 0),      // 33 INX SP
+        entry(Processor::inr_opcode::<6, _>, 1, 10, SZAP),  // 34 INR M
+        entry(Processor::dcr_opcode::<6, _>, 1, 10, SZAP),  // 35 DCR M
+        entry(Processor::mvi_opcode::<6, _>, 2, 10, 0),     // 36 MVI M
+        entry(Processor::stc_opcode, 1, 4, CY),             // 37 STC
+        entry(Processor::unknown_opcode, 1, 4, 0),          // 38 undocumented
+        entry(Processor::dad_opcode::<3, _>, 1, 10, CY),    // 39 DAD SP
+        entry(Processor::lda_opcode, 3, 13, 0),             // 3A LDA
+        entry(Processor::dcx_opcode::<3, _>, 1, 5, 0),      // 3B DCX SP
+        entry(Processor::inr_opcode::<7, _>, 1, 5, SZAP),   // 3C INR A
+        entry(Processor::dcr_opcode::<7, _>, 1, 5, SZAP),   // 3D DCR A
+        entry(Processor::mvi_opcode::<7, _>, 2, 7, 0),      // 3E MVI A
+        entry(Processor::cmc_opcode, 1, 4, CY),             // 3F CMC
+        entry(Processor::mov_opcode::<0, 0, _>, 1, 5, 0),   // 40 MOV B,B
+        entry(Processor::mov_opcode::<0, 1, _>, 1, 5, 0),   // 41 MOV B,C
+        entry(Processor::mov_opcode::<0, 2, _>, 1, 5, 0),   // 42 MOV B,D
+        entry(Processor::mov_opcode::<0, 3, _>, 1, 5, 0),   // 43 MOV B,E
+        entry(Processor::mov_opcode::<0, 4, _>, 1, 5, 0),   // 44 MOV B,H
+        entry(Processor::mov_opcode::<0, 5, _>, 1, 5, 0),   // 45 MOV B,L
+        entry(Processor::mov_opcode::<0, 6, _>, 1, 7, 0),   // 46 MOV B,M
+        entry(Processor::mov_opcode::<0, 7, _>, 1, 5, 0),   // 47 MOV B,A
+        entry(Processor::mov_opcode::<1, 0, _>, 1, 5, 0),   // 48 MOV C,B
+        entry(Processor::mov_opcode::<1, 1, _>, 1, 5, 0),   // 49 MOV C,C
+        entry(Processor::mov_opcode::<1, 2, _>, 1, 5, 0),   // 4A MOV C,D
+        entry(Processor::mov_opcode::<1, 3, _>, 1, 5, 0),   // 4B MOV C,E
+        entry(Processor::mov_opcode::<1, 4, _>, 1, 5, 0),   // 4C MOV C,H
+        entry(Processor::mov_opcode::<1, 5, _>, 1, 5, 0),   // 4D MOV C,L
+        entry(Processor::mov_opcode::<1, 6, _>, 1, 7, 0),   // 4E MOV C,M
+        entry(Processor::mov_opcode::<1, 7, _>, 1, 5, 0),   // 4F MOV C,A
+        entry(Processor::mov_opcode::<2, 0, _>, 1, 5, 0),   // 50 MOV D,B
+        entry(Processor::mov_opcode::<2, 1, _>, 1, 5, 0),   // 51 MOV D,C
+        entry(Processor::mov_opcode::<2, 2, _>, 1, 5, 0),   // 52 MOV D,D
+        entry(Processor::mov_opcode::<2, 3, _>, 1, 5, 0),   // 53 MOV D,E
+        entry(Processor::mov_opcode::<2, 4, _>, 1, 5, 0),   // 54 MOV D,H
+        entry(Processor::mov_opcode::<2, 5, _>, 1, 5, 0),   // 55 MOV D,L
+        entry(Processor::mov_opcode::<2, 6, _>, 1, 7, 0),   // 56 MOV D,M
+        entry(Processor::mov_opcode::<2, 7, _>, 1, 5, 0),   // 57 MOV D,A
+        entry(Processor::mov_opcode::<3, 0, _>, 1, 5, 0),   // 58 MOV E,B
+        entry(Processor::mov_opcode::<3, 1, _>, 1, 5, 0),   // 59 MOV E,C
+        entry(Processor::mov_opcode::<3, 2, _>, 1, 5, 0),   // 5A MOV E,D
+        entry(Processor::mov_opcode::<3, 3, _>, 1, 5, 0),   // 5B MOV E,E
+        entry(Processor::mov_opcode::<3, 4, _>, 1, 5, 0),   // 5C MOV E,H
+        entry(Processor::mov_opcode::<3, 5, _>, 1, 5, 0),   // 5D MOV E,L
+        entry(Processor::mov_opcode::<3, 6, _>, 1, 7, 0),   // 5E MOV E,M
+        entry(Processor::mov_opcode::<3, 7, _>, 1, 5, 0),   // 5F MOV E,A
+        entry(Processor::mov_opcode::<4, 0, _>, 1, 5, 0),   // 60 MOV H,B
+        entry(Processor::mov_opcode::<4, 1, _>, 1, 5, 0),   // 61 MOV H,C
+        entry(Processor::mov_opcode::<4, 2, _>, 1, 5, 0),   // 62 MOV H,D
+        entry(Processor::mov_opcode::<4, 3, _>, 1, 5, 0),   // 63 MOV H,E
+        entry(Processor::mov_opcode::<4, 4, _>, 1, 5, 0),   // 64 MOV H,H
+        entry(Processor::mov_opcode::<4, 5, _>, 1, 5, 0),   // 65 MOV H,L
+        entry(Processor::mov_opcode::<4, 6, _>, 1, 7, 0),   // 66 MOV H,M
+        entry(Processor::mov_opcode::<4, 7, _>, 1, 5, 0),   // 67 MOV H,A
+        entry(Processor::mov_opcode::<5, 0, _>, 1, 5, 0),   // 68 MOV L,B
+        entry(Processor::mov_opcode::<5, 1, _>, 1, 5, 0),   // 69 MOV L,C
+        entry(Processor::mov_opcode::<5, 2, _>, 1, 5, 0),   // 6A MOV L,D
+        entry(Processor::mov_opcode::<5, 3, _>, 1, 5, 0),   // 6B MOV L,E
+        entry(Processor::mov_opcode::<5, 4, _>, 1, 5, 0),   // 6C MOV L,H
+        entry(Processor::mov_opcode::<5, 5, _>, 1, 5, 0),   // 6D MOV L,L
+        entry(Processor::mov_opcode::<5, 6, _>, 1, 7, 0),   // 6E MOV L,M
+        entry(Processor::mov_opcode::<5, 7, _>, 1, 5, 0),   // 6F MOV L,A
+        entry(Processor::mov_opcode::<6, 0, _>, 1, 7, 0),   // 70 MOV M,B
+        entry(Processor::mov_opcode::<6, 1, _>, 1, 7, 0),   // 71 MOV M,C
+        entry(Processor::mov_opcode::<6, 2, _>, 1, 7, 0),   // 72 MOV M,D
+        entry(Processor::mov_opcode::<6, 3, _>, 1, 7, 0),   // 73 MOV M,E
+        entry(Processor::mov_opcode::<6, 4, _>, 1, 7, 0),   // 74 MOV M,H
+        entry(Processor::mov_opcode::<6, 5, _>, 1, 7, 0),   // 75 MOV M,L
+        entry(Processor::hlt_opcode, 1, 7, 0),              // 76 HLT
+        entry(Processor::mov_opcode::<6, 7, _>, 1, 7, 0),   // 77 MOV M,A
+        entry(Processor::mov_opcode::<7, 0, _>, 1, 5, 0),   // 78 MOV A,B
+        entry(Processor::mov_opcode::<7, 1, _>, 1, 5, 0),   // 79 MOV A,C
+        entry(Processor::mov_opcode::<7, 2, _>, 1, 5, 0),   // 7A MOV A,D
+        entry(Processor::mov_opcode::<7, 3, _>, 1, 5, 0),   // 7B MOV A,E
+        entry(Processor::mov_opcode::<7, 4, _>, 1, 5, 0),   // 7C MOV A,H
+        entry(Processor::mov_opcode::<7, 5, _>, 1, 5, 0),   // 7D MOV A,L
+        entry(Processor::mov_opcode::<7, 6, _>, 1, 7, 0),   // 7E MOV A,M
+        entry(Processor::mov_opcode::<7, 7, _>, 1, 5, 0),   // 7F MOV A,A
+        entry(Processor::alu_opcode::<0, 0, _>, 1, 4, ALL), // 80 ADD B
+        entry(Processor::alu_opcode::<0, 1, _>, 1, 4, ALL), // 81 ADD C
+        entry(Processor::alu_opcode::<0, 2, _>, 1, 4, ALL), // 82 ADD D
+        entry(Processor::alu_opcode::<0, 3, _>, 1, 4, ALL), // 83 ADD E
+        entry(Processor::alu_opcode::<0, 4, _>, 1, 4, ALL), // 84 ADD H
+        entry(Processor::alu_opcode::<0, 5, _>, 1, 4, ALL), // 85 ADD L
+        entry(Processor::alu_opcode::<0, 6, _>, 1, 7, ALL), // 86 ADD M
+        entry(Processor::alu_opcode::<0, 7, _>, 1, 4, ALL), // 87 ADD A
+        entry(Processor::alu_opcode::<1, 0, _>, 1, 4, ALL), // 88 ADC B
+        entry(Processor::alu_opcode::<1, 1, _>, 1, 4, ALL), // 89 ADC C
+        entry(Processor::alu_opcode::<1, 2, _>, 1, 4, ALL), // 8A ADC D
+        entry(Processor::alu_opcode::<1, 3, _>, 1, 4, ALL), // 8B ADC E
+        entry(Processor::alu_opcode::<1, 4, _>, 1, 4, ALL), // 8C ADC H
+        entry(Processor::alu_opcode::<1, 5, _>, 1, 4, ALL), // 8D ADC L
+        entry(Processor::alu_opcode::<1, 6, _>, 1, 7, ALL), // 8E ADC M
+        entry(Processor::alu_opcode::<1, 7, _>, 1, 4, ALL), // 8F ADC A
+        entry(Processor::alu_opcode::<2, 0, _>, 1, 4, ALL), // 90 SUB B
+        entry(Processor::alu_opcode::<2, 1, _>, 1, 4, ALL), // 91 SUB C
+        entry(Processor::alu_opcode::<2, 2, _>, 1, 4, ALL), // 92 SUB D
+        entry(Processor::alu_opcode::<2, 3, _>, 1, 4, ALL), // 93 SUB E
+        entry(Processor::alu_opcode::<2, 4, _>, 1, 4, ALL), // 94 SUB H
+        entry(Processor::alu_opcode::<2, 5, _>, 1, 4, ALL), // 95 SUB L
+        entry(Processor::alu_opcode::<2, 6, _>, 1, 7, ALL), // 96 SUB M
+        entry(Processor::alu_opcode::<2, 7, _>, 1, 4, ALL), // 97 SUB A
+        entry(Processor::alu_opcode::<3, 0, _>, 1, 4, ALL), // 98 SBB B
+        entry(Processor::alu_opcode::<3, 1, _>, 1, 4, ALL), // 99 SBB C
+        entry(Processor::alu_opcode::<3, 2, _>, 1, 4, ALL), // 9A SBB D
+        entry(Processor::alu_opcode::<3, 3, _>, 1, 4, ALL), // 9B SBB E
+        entry(Processor::alu_opcode::<3, 4, _>, 1, 4, ALL), // 9C SBB H
+        entry(Processor::alu_opcode::<3, 5, _>, 1, 4, ALL), // 9D SBB L
+        entry(Processor::alu_opcode::<3, 6, _>, 1, 7, ALL), // 9E SBB M
+        entry(Processor::alu_opcode::<3, 7, _>, 1, 4, ALL), // 9F SBB A
+        entry(Processor::alu_opcode::<4, 0, _>, 1, 4, ALL), // A0 ANA B
+        entry(Processor::alu_opcode::<4, 1, _>, 1, 4, ALL), // A1 ANA C
+        entry(Processor::alu_opcode::<4, 2, _>, 1, 4, ALL), // A2 ANA D
+        entry(Processor::alu_opcode::<4, 3, _>, 1, 4, ALL), // A3 ANA E
+        entry(Processor::alu_opcode::<4, 4, _>, 1, 4, ALL), // A4 ANA H
+        entry(Processor::alu_opcode::<4, 5, _>, 1, 4, ALL), // A5 ANA L
+        entry(Processor::alu_opcode::<4, 6, _>, 1, 7, ALL), // A6 ANA M
+        entry(Processor::alu_opcode::<4, 7, _>, 1, 4, ALL), // A7 ANA A
+        entry(Processor::alu_opcode::<5, 0, _>, 1, 4, ALL), // A8 XRA B
+        entry(Processor::alu_opcode::<5, 1, _>, 1, 4, ALL), // A9 XRA C
+        entry(Processor::alu_opcode::<5, 2, _>, 1, 4, ALL), // AA XRA D
+        entry(Processor::alu_opcode::<5, 3, _>, 1, 4, ALL), // AB XRA E
+        entry(Processor::alu_opcode::<5, 4, _>, 1, 4, ALL), // AC XRA H
+        entry(Processor::alu_opcode::<5, 5, _>, 1, 4, ALL), // AD XRA L
+        entry(Processor::alu_opcode::<5, 6, _>, 1, 7, ALL), // AE XRA M
+        entry(Processor::alu_opcode::<5, 7, _>, 1, 4, ALL), // AF XRA A
+        entry(Processor::alu_opcode::<6, 0, _>, 1, 4, ALL), // B0 ORA B
+        entry(Processor::alu_opcode::<6, 1, _>, 1, 4, ALL), // B1 ORA C
+        entry(Processor::alu_opcode::<6, 2, _>, 1, 4, ALL), // B2 ORA D
+        entry(Processor::alu_opcode::<6, 3, _>, 1, 4, ALL), // B3 ORA E
+        entry(Processor::alu_opcode::<6, 4, _>, 1, 4, ALL), // B4 ORA H
+        entry(Processor::alu_opcode::<6, 5, _>, 1, 4, ALL), // B5 ORA L
+        entry(Processor::alu_opcode::<6, 6, _>, 1, 7, ALL), // B6 ORA M
+        entry(Processor::alu_opcode::<6, 7, _>, 1, 4, ALL), // B7 ORA A
+        entry(Processor::alu_opcode::<7, 0, _>, 1, 4, ALL), // B8 CMP B
+        entry(Processor::alu_opcode::<7, 1, _>, 1, 4, ALL), // B9 CMP C
+        entry(Processor::alu_opcode::<7, 2, _>, 1, 4, ALL), // BA CMP D
+        entry(Processor::alu_opcode::<7, 3, _>, 1, 4, ALL), // BB CMP E
+        entry(Processor::alu_opcode::<7, 4, _>, 1, 4, ALL), // BC CMP H
+        entry(Processor::alu_opcode::<7, 5, _>, 1, 4, ALL), // BD CMP L
+        entry(Processor::alu_opcode::<7, 6, _>, 1, 7, ALL), // BE CMP M
+        entry(Processor::alu_opcode::<7, 7, _>, 1, 4, ALL), // BF CMP A
+        branch(Processor::rccc_opcode::<0, _>, 1, 5, 11),   // C0 RNZ
+        entry(Processor::pop_opcode::<0, _>, 1, 10, 0),     // C1 POP B
+        entry(Processor::jccc_opcode::<0, _>, 3, 10, 0),    // C2 JNZ
+        entry(Processor::jmp_opcode, 3, 10, 0),             // C3 JMP
+        branch(Processor::cccc_opcode::<0, _>, 3, 11, 17),  // C4 CNZ
+        entry(Processor::push_opcode::<0, _>, 1, 11, 0),    // C5 PUSH B
+        entry(Processor::alu_immediate_opcode::<0, _>, 2, 7, ALL), // C6 ADI
+        entry(Processor::rst_opcode::<0, _>, 1, 11, 0),     // C7 RST 0
+        branch(Processor::rccc_opcode::<1, _>, 1, 5, 11),   // C8 RZ
+        entry(Processor::ret_opcode, 1, 10, 0),             // C9 RET
+        entry(Processor::jccc_opcode::<1, _>, 3, 10, 0),    // CA JZ
+        entry(Processor::unknown_opcode, 1, 4, 0),          // CB undocumented
+        branch(Processor::cccc_opcode::<1, _>, 3, 11, 17),  // CC CZ
+        entry(Processor::call_opcode, 3, 17, 0),            // CD CALL
+        entry(Processor::alu_immediate_opcode::<1, _>, 2, 7, ALL), // CE ACI
+        entry(Processor::rst_opcode::<1, _>, 1, 11, 0),     // CF RST 1
+        branch(Processor::rccc_opcode::<2, _>, 1, 5, 11),   // D0 RNC
+        entry(Processor::pop_opcode::<1, _>, 1, 10, 0),     // D1 POP D
+        entry(Processor::jccc_opcode::<2, _>, 3, 10, 0),    // D2 JNC
+        entry(Processor::out_opcode, 2, 10, 0),             // D3 OUT
+        branch(Processor::cccc_opcode::<2, _>, 3, 11, 17),  // D4 CNC
+        entry(Processor::push_opcode::<1, _>, 1, 11, 0),    // D5 PUSH D
+        entry(Processor::alu_immediate_opcode::<2, _>, 2, 7, ALL), // D6 SUI
+        entry(Processor::rst_opcode::<2, _>, 1, 11, 0),     // D7 RST 2
+        branch(Processor::rccc_opcode::<3, _>, 1, 5, 11),   // D8 RC
+        entry(Processor::unknown_opcode, 1, 4, 0),          // D9 undocumented
+        entry(Processor::jccc_opcode::<3, _>, 3, 10, 0),    // DA JC
+        entry(Processor::in_opcode, 2, 10, 0),              // DB IN
+        branch(Processor::cccc_opcode::<3, _>, 3, 11, 17),  // DC CC
+        entry(Processor::unknown_opcode, 1, 4, 0),          // DD undocumented
+        entry(Processor::alu_immediate_opcode::<3, _>, 2, 7, ALL), // DE SBI
+        entry(Processor::rst_opcode::<3, _>, 1, 11, 0),     // DF RST 3
+        branch(Processor::rccc_opcode::<4, _>, 1, 5, 11),   // E0 RPO
+        entry(Processor::pop_opcode::<2, _>, 1, 10, 0),     // E1 POP H
+        entry(Processor::jccc_opcode::<4, _>, 3, 10, 0),    // E2 JPO
+        entry(Processor::xthl_opcode, 1, 18, 0),            // E3 XTHL
+        branch(Processor::cccc_opcode::<4, _>, 3, 11, 17),  // E4 CPO
+        entry(Processor::push_opcode::<2, _>, 1, 11, 0),    // E5 PUSH H
+        entry(Processor::alu_immediate_opcode::<4, _>, 2, 7, ALL), // E6 ANI
+        entry(Processor::rst_opcode::<4, _>, 1, 11, 0),     // E7 RST 4
+        branch(Processor::rccc_opcode::<5, _>, 1, 5, 11),   // E8 RPE
+        entry(Processor::pchl_opcode, 1, 5, 0),             // E9 PCHL
+        entry(Processor::jccc_opcode::<5, _>, 3, 10, 0),    // EA JPE
+        entry(Processor::xchg_opcode, 1, 4, 0),             // EB XCHG
+        branch(Processor::cccc_opcode::<5, _>, 3, 11, 17),  // EC CPE
+        entry(Processor::unknown_opcode, 1, 4, 0),          // ED undocumented
+        entry(Processor::alu_immediate_opcode::<5, _>, 2, 7, ALL), // EE XRI
+        entry(Processor::rst_opcode::<5, _>, 1, 11, 0),     // EF RST 5
+        branch(Processor::rccc_opcode::<6, _>, 1, 5, 11),   // F0 RP
+        entry(Processor::pop_opcode::<3, _>, 1, 10, ALL),   // F1 POP PSW
+        entry(Processor::jccc_opcode::<6, _>, 3, 10, 0),    // F2 JP
+        entry(Processor::di_opcode, 1, 4, 0),               // F3 DI
+        branch(Processor::cccc_opcode::<6, _>, 3, 11, 17),  // F4 CP
+        entry(Processor::push_opcode::<3, _>, 1, 11, 0),    // F5 PUSH PSW
+        entry(Processor::alu_immediate_opcode::<6, _>, 2, 7, ALL), // F6 ORI
+        entry(Processor::rst_opcode::<6, _>, 1, 11, 0),     // F7 RST 6
+        branch(Processor::rccc_opcode::<7, _>, 1, 5, 11),   // F8 RM
+        entry(Processor::sphl_opcode, 1, 5, 0),             // F9 SPHL
+        entry(Processor::jccc_opcode::<7, _>, 3, 10, 0),    // FA JM
+        entry(Processor::ei_opcode, 1, 4, 0),               // FB EI
+        branch(Processor::cccc_opcode::<7, _>, 3, 11, 17),  // FC CM
+        entry(Processor::unknown_opcode, 1, 4, 0),          // FD undocumented
+        entry(Processor::alu_immediate_opcode::<7, _>, 2, 7, ALL), // FE CPI
+        entry(Processor::rst_opcode::<7, _>, 1, 11, 0),     // FF RST 7
+    ];
+}
+
+/// Runs the undocumented 8080 opcodes the way the silicon does, as NOP,
+/// JMP, RET or CALL
+const fn aliases_8080<B: Bus, Pt: Port>(mut table: [Entry<B, Pt>; 256]) -> [Entry<B, Pt>; 256] {
+    let mut opcode = 0x08;
+    while opcode <= 0x38 {
+        table[opcode] = table[0x00];
+        opcode += 0x08;
+    }
+
+    table[0xCB] = table[0xC3];
+    table[0xD9] = table[0xC9];
+    table[0xDD] = table[0xCD];
+    table[0xED] = table[0xCD];
+    table[0xFD] = table[0xCD];
+
+    table
+}
+
+/// Turns the 8080 table into the 8085's by adding RIM and SIM and applying
+/// its timings
+const fn i8085<B: Bus, Pt: Port>(mut table: [Entry<B, Pt>; 256]) -> [Entry<B, Pt>; 256] {
+    let mut opcode = 0;
+    while opcode < 256 {
+        let entry = &mut table[opcode];
+        (entry.cycles, entry.cycles_taken) =
+            timing_8085(opcode as u8, entry.cycles, entry.cycles_taken);
+        opcode += 1;
+    }
+
+    table[0x20] = entry(Processor::rim_opcode, 1, 4, 0);
+    table[0x30] = entry(Processor::sim_opcode, 1, 4, 0);
+
+    table
+}
+
+/// 8085 cycles of an opcode, given its 8080 cycles
+pub(crate) const fn timing_8085(opcode: u8, cycles: u8, cycles_taken: u8) -> (u8, u8) {
+    match opcode {
+        0x76 => (5, 5),
+        // Conditional jumps skip fetching the rest of the target
+        _ if opcode & 0xC7 == 0xC2 => (7, 10),
+        _ if opcode & 0xC7 == 0xC4 => (9, 18),
+        _ if opcode & 0xC7 == 0xC0 => (6, 12),
+        // CALL, PUSH and RST
+        0xCD => (18, 18),
+        _ if opcode & 0xCF == 0xC5 || opcode & 0xC7 == 0xC7 => (12, 12),
+        // XTHL
+        0xE3 => (16, 16),
+        // Register MOV, INR and DCR take 4 states, the 16-bit INX, DCX,
+        // SPHL and PCHL take 6
+        _ if cycles == 5 && (opcode & 0xC0 == 0x40 || opcode & 0xC6 == 0x04) => (4, 4),
+        _ if cycles == 5 => (6, 6),
+        _ => (cycles, cycles_taken),
+    }
+}
+
+/// Adds the undocumented 8085 opcodes and lets arithmetic keep V and K
+const fn undocumented_8085<B: Bus, Pt: Port>(
+    mut table: [Entry<B, Pt>; 256],
+) -> [Entry<B, Pt>; 256] {
+    let mut opcode = 0;
+    while opcode < 256 {
+        let entry = &mut table[opcode];
+        let byte = opcode as u8;
+        // ADD through SBB, CMP and their immediate forms, INR and DCR
+        if byte & 0xE0 == 0x80
+            || byte & 0xF8 == 0xB8
+            || byte & 0xE7 == 0xC6
+            || byte == 0xFE
+            || byte & 0xC6 == 0x04
+        {
+            entry.flags |= V | K;
+        }
+        // INX and DCX
+        if byte & 0xC7 == 0x03 {
+            entry.flags = K;
+        }
+        opcode += 1;
+    }
+
+    table[0xF1].flags = ALL | V | K;
+    table[0x08] = entry(Processor::dsub_opcode, 1, 10, ALL | V | K);
+    table[0x10] = entry(Processor::arhl_opcode, 1, 7, CY);
+    table[0x18] = entry(Processor::rdel_opcode, 1, 10, CY | V);
+    table[0x28] = entry(Processor::ldhi_opcode, 2, 10, 0);
+    table[0x38] = entry(Processor::ldsi_opcode, 2, 10, 0);
+    table[0xCB] = branch(Processor::rstv_opcode, 1, 6, 12);
+    table[0xD9] = entry(Processor::shlx_opcode, 1, 10, 0);
+    table[0xDD] = branch(Processor::jk_opcode::<false, _>, 3, 7, 10);
+    table[0xED] = entry(Processor::lhlx_opcode, 1, 10, 0);
+    table[0xFD] = branch(Processor::jk_opcode::<true, _>, 3, 7, 10);
+
+    table
+}
+
+const fn entry<B: Bus, Pt: Port>(
+    handler: Handler<B, Pt>,
+    length: u8,
+    cycles: u8,
+    flags: u8,
+) -> Entry<B, Pt> {
+    Entry {
+        handler,
+        length,
+        cycles,
+        cycles_taken: cycles,
+        flags,
+    }
+}
+
+const fn branch<B: Bus, Pt: Port>(
+    handler: Handler<B, Pt>,
+    length: u8,
+    cycles: u8,
+    cycles_taken: u8,
+) -> Entry<B, Pt> {
+    Entry {
+        handler,
+        length,
+        cycles,
+        cycles_taken,
+        flags: 0,
+    }
+}
+
+const fn szp_flags() -> [u8; 256] {
+    let mut flags = [0; 256];
+    let mut result = 0;
+    while result < 256 {
+        let byte = result as u8;
+        flags[result] = (byte & S)
+            | if byte == 0 { Z } else { 0 }
+            | if byte.count_ones().is_multiple_of(2) {
+                P
+            } else {
+                0
+            };
+        result += 1;
+    }
+
+    flags
+}
@@ -5,14 +5,33 @@ pub enum Error {
     #[error("Invalid memory address was accesed: {0:#X}")]
     InvalidMemory(usize),
 
+    #[error("Read from unmapped memory{} at {address:#06X}", region_name(.region))]
+    UnmappedRead {
+        address: u16,
+        region: Option<String>,
+    },
+
+    #[error("Write to unmapped memory{} at {address:#06X}", region_name(.region))]
+    UnmappedWrite {
+        address: u16,
+        region: Option<String>,
+    },
+
+    #[error("Write to ROM{} at {address:#06X}", region_name(.region))]
+    RomWrite {
+        address: u16,
+        region: Option<String>,
+    },
+
     #[error("Unknown opcode found: {0:#02X}")]
     UnknownOpcode(u8),
 
+    #[deprecated(note = "never returned, register names fail with `UnknownRegister`")]
     #[error("Failed to parse register: {0}")]
     RegisterParse(u8),
 
-    #[error("Program was halted by opcode")]
-    SystemHalt,
+    #[error("Unknown register name: {0}")]
+    UnknownRegister(String),
 
     #[error("ROM is {rom_size} bytes but there is only {space_left} after target address")]
     RomSize {
@@ -22,6 +41,45 @@ pub enum Error {
 
     #[error("No ROM has been loaded")]
     RomNotLoaded,
+
+    #[error("Save state is truncated or not a processor snapshot")]
+    SnapshotFormat,
+
+    #[error("Save state has version {found} but only version {supported} is supported")]
+    SnapshotVersion { found: u8, supported: u8 },
+
+    #[error("Save state holds {snapshot_size} bytes of RAM but memory is {ram_size} bytes")]
+    SnapshotRamSize {
+        snapshot_size: usize,
+        ram_size: usize,
+    },
+
+    #[error("Save state runs undocumented opcodes through a callback but none is installed")]
+    SnapshotCallback,
+
+    #[error("I/O operation failed:\n{0}")]
+    IO(#[from] std::io::Error),
+
+    #[error("Assembly failed on line {line_num}: {message}")]
+    Assembly { line_num: usize, message: String },
+
+    #[error("Invalid Intel HEX on line {line_num}: {message}")]
+    IntelHex { line_num: usize, message: String },
+
+    #[error("Coverage bitmap is {0} bytes but should be 32768")]
+    InvalidCoverage(usize),
+
+    #[error("Invalid region map: {0}")]
+    RegionMap(String),
+
+    #[error("Invalid bank layout: {0}")]
+    Banking(String),
+}
+
+fn region_name(region: &Option<String>) -> String {
+    region
+        .as_ref()
+        .map_or_else(String::new, |name| format!(" '{name}'"))
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
@@ -0,0 +1,498 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    bus::Bus,
+    debugger::{Access, DebugBus, Debugger, StopReason},
+    errors::{Error, Result},
+    helpers::word_to_bytes,
+    port::Port,
+    processor::Processor,
+    registers::Register,
+};
+
+/// Register layout exposed to GDB, 8-bit registers first then SP and PC
+pub const GDB_REGISTERS: [Register; 10] = [
+    Register::A,
+    Register::F,
+    Register::B,
+    Register::C,
+    Register::D,
+    Register::E,
+    Register::H,
+    Register::L,
+    Register::SP,
+    Register::PC,
+];
+
+/// Target description served to GDB through qXfer
+pub const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.gnu.gdb.i8080.core">
+    <reg name="a" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="f" bitsize="8" type="uint8"/>
+    <reg name="b" bitsize="8" type="uint8"/>
+    <reg name="c" bitsize="8" type="uint8"/>
+    <reg name="d" bitsize="8" type="uint8"/>
+    <reg name="e" bitsize="8" type="uint8"/>
+    <reg name="h" bitsize="8" type="uint8"/>
+    <reg name="l" bitsize="8" type="uint8"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+/// Byte sent by the client to interrupt a running target
+const INTERRUPT: u8 = 0x03;
+
+/// Largest packet the stub accepts or sends, advertised in qSupported
+const PACKET_SIZE: usize = 0x1000;
+
+/// Cycles run between checks for an interrupt from the client
+const CONTINUE_CHUNK: u64 = 10_000;
+
+const ERROR_REPLY: &str = "E01";
+const SIGINT_REPLY: &str = "S02";
+const SIGILL_REPLY: &str = "S04";
+const SIGTRAP_REPLY: &str = "S05";
+const SIGSEGV_REPLY: &str = "S0b";
+
+/// GDB remote serial protocol stub serving a single client over TCP
+pub struct GdbStub {
+    stream: TcpStream,
+    no_ack: bool,
+    last_stop: String,
+}
+
+impl GdbStub {
+    /// Blocks until a GDB client connects to `address`
+    pub fn listen(address: impl ToSocketAddrs) -> Result<Self> {
+        let (stream, _) = TcpListener::bind(address)?.accept()?;
+        Ok(Self::new(stream))
+    }
+
+    pub fn new(stream: TcpStream) -> Self {
+        // Packets are small and sent one at a time, batching only adds latency
+        let _ = stream.set_nodelay(true);
+
+        Self {
+            stream,
+            no_ack: false,
+            last_stop: SIGTRAP_REPLY.to_string(),
+        }
+    }
+
+    /// Serves requests until the client detaches, kills the session or
+    /// disconnects. The processor is left stopped where the client left it.
+    pub fn serve<B: Bus>(
+        &mut self,
+        debugger: &mut Debugger<B>,
+        port: &mut impl Port,
+    ) -> Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            match packet.as_str() {
+                "D" => {
+                    self.write_packet("OK")?;
+                    break;
+                }
+                "k" => break,
+                _ => {
+                    let reply = self.respond(&packet, debugger, port)?;
+                    self.write_packet(&reply)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn respond<B: Bus>(
+        &mut self,
+        packet: &str,
+        debugger: &mut Debugger<B>,
+        port: &mut impl Port,
+    ) -> Result<String> {
+        let Some(command) = packet.chars().next() else {
+            return Ok(String::new());
+        };
+        let args = &packet[command.len_utf8()..];
+
+        let reply = match command {
+            '?' => Some(self.last_stop.clone()),
+            'g' => Some(read_registers(debugger.processor())),
+            'G' => write_registers(debugger.processor_mut(), args),
+            'p' => read_register(debugger.processor(), args),
+            'P' => write_register(debugger.processor_mut(), args),
+            'm' => read_memory(debugger.processor().bus(), args),
+            'M' => write_memory(debugger.processor_mut().bus_mut(), args),
+            'Z' => set_breakpoint(debugger, args, true),
+            'z' => set_breakpoint(debugger, args, false),
+            'c' => return self.resume(args, false, debugger, port),
+            's' => return self.resume(args, true, debugger, port),
+            'b' => return self.reverse(args, debugger),
+            'H' => Some("OK".to_string()),
+            'q' => Some(query(args)),
+            'Q' if args == "StartNoAckMode" => {
+                self.no_ack = true;
+                Some("OK".to_string())
+            }
+            _ => Some(String::new()),
+        };
+
+        Ok(reply.unwrap_or_else(|| ERROR_REPLY.to_string()))
+    }
+
+    /// Handles `c` and `s`, optionally resuming at a new address
+    fn resume<B: Bus>(
+        &mut self,
+        args: &str,
+        step: bool,
+        debugger: &mut Debugger<B>,
+        port: &mut impl Port,
+    ) -> Result<String> {
+        if !args.is_empty() {
+            let Some(address) = parse_hex(args) else {
+                return Ok(ERROR_REPLY.to_string());
+            };
+            debugger
+                .processor_mut()
+                .set_register(Register::PC, address as u16);
+        }
+
+        let result = if step {
+            debugger.step(port).map(stop_reply)
+        } else {
+            self.run(debugger, port)
+        };
+
+        // Execution errors stop the target instead of ending the session
+        let reply = match result {
+            Ok(reply) => reply,
+            Err(Error::IO(error)) => return Err(Error::IO(error)),
+            Err(Error::UnknownOpcode(_)) => SIGILL_REPLY.to_string(),
+            Err(_) => SIGSEGV_REPLY.to_string(),
+        };
+        self.last_stop = reply.clone();
+
+        Ok(reply)
+    }
+
+    /// Handles `bs` and `bc`, which need the debugger to record history
+    fn reverse<B: Bus>(&mut self, args: &str, debugger: &mut Debugger<B>) -> Result<String> {
+        let result = match args {
+            "s" => debugger.step_back(),
+            "c" => debugger.run_back_to_breakpoint(),
+            _ => return Ok(String::new()),
+        };
+
+        let reply = match result {
+            Ok(reason) => stop_reply(reason),
+            Err(Error::IO(error)) => return Err(Error::IO(error)),
+            Err(_) => SIGSEGV_REPLY.to_string(),
+        };
+        self.last_stop = reply.clone();
+
+        Ok(reply)
+    }
+
+    /// Runs until the debugger stops or the client sends an interrupt
+    fn run<B: Bus>(&mut self, debugger: &mut Debugger<B>, port: &mut impl Port) -> Result<String> {
+        loop {
+            let reason = debugger.run_for(CONTINUE_CHUNK, port)?;
+            if reason != StopReason::CycleLimit {
+                return Ok(stop_reply(reason));
+            }
+            if self.interrupted()? {
+                return Ok(SIGINT_REPLY.to_string());
+            }
+        }
+    }
+
+    /// Checks without blocking whether the client asked to interrupt
+    fn interrupted(&mut self) -> Result<bool> {
+        let mut byte = [0];
+        self.stream.set_nonblocking(true)?;
+        let result = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false)?;
+
+        match result {
+            // A closed connection also stops the target
+            Ok(0) => Ok(true),
+            Ok(_) => Ok(byte[0] == INTERRUPT),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        let mut byte = [0];
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    /// Reads the next valid packet, returning None once the client disconnects
+    fn read_packet(&mut self) -> Result<Option<String>> {
+        loop {
+            // Skip acknowledgements and interrupts sent while stopped
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(b'$') => {}
+                Some(_) => continue,
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+
+            let mut checksum = [0; 2];
+            self.stream.read_exact(&mut checksum)?;
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .is_some_and(|checksum| checksum == packet_checksum(&data));
+
+            if !self.no_ack {
+                self.stream.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> Result<()> {
+        let data = escape(data.as_bytes());
+        let checksum = packet_checksum(&data);
+
+        let mut packet = Vec::with_capacity(data.len() + 4);
+        packet.push(b'$');
+        packet.extend_from_slice(&data);
+        packet.extend_from_slice(format!("#{checksum:02x}").as_bytes());
+        self.stream.write_all(&packet)?;
+
+        Ok(())
+    }
+}
+
+/// Escapes the bytes that frame packets as `}` followed by the byte XOR 0x20
+fn escape(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for &byte in data {
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            escaped.extend_from_slice(&[b'}', byte ^ 0x20]);
+        } else {
+            escaped.push(byte);
+        }
+    }
+
+    escaped
+}
+
+fn packet_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text, 16).ok()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn stop_reply(reason: StopReason) -> String {
+    match reason {
+        StopReason::Watchpoint {
+            address,
+            access: Access::Write,
+            ..
+        } => format!("T05watch:{address:04x};"),
+        StopReason::Watchpoint {
+            address,
+            access: Access::Read,
+            ..
+        } => format!("T05rwatch:{address:04x};"),
+        StopReason::HistoryStart => "T05replaylog:begin;".to_string(),
+        _ => SIGTRAP_REPLY.to_string(),
+    }
+}
+
+/// Formats a register the way GDB expects, 16-bit values are little endian
+fn register_hex<B: Bus>(processor: &Processor<DebugBus<B>>, register: Register) -> String {
+    let value = processor.register(register);
+    if register.is_16bit() {
+        let (low_byte, high_byte) = word_to_bytes(value);
+        format!("{low_byte:02x}{high_byte:02x}")
+    } else {
+        format!("{value:02x}")
+    }
+}
+
+fn register_width(register: Register) -> usize {
+    if register.is_16bit() { 4 } else { 2 }
+}
+
+fn parse_register_value(register: Register, text: &str) -> Option<u16> {
+    let bytes = decode_hex(text)?;
+    match (register.is_16bit(), bytes.as_slice()) {
+        (true, &[low_byte, high_byte]) => Some(u16::from_le_bytes([low_byte, high_byte])),
+        (false, &[value]) => Some(value as u16),
+        _ => None,
+    }
+}
+
+fn read_registers<B: Bus>(processor: &Processor<DebugBus<B>>) -> String {
+    GDB_REGISTERS
+        .iter()
+        .map(|&register| register_hex(processor, register))
+        .collect()
+}
+
+fn write_registers<B: Bus>(processor: &mut Processor<DebugBus<B>>, args: &str) -> Option<String> {
+    let mut values = Vec::new();
+    let mut offset = 0;
+    for register in GDB_REGISTERS {
+        let width = register_width(register);
+        values.push(parse_register_value(
+            register,
+            args.get(offset..offset + width)?,
+        )?);
+        offset += width;
+    }
+
+    for (register, value) in GDB_REGISTERS.into_iter().zip(values) {
+        processor.set_register(register, value);
+    }
+
+    Some("OK".to_string())
+}
+
+fn read_register<B: Bus>(processor: &Processor<DebugBus<B>>, args: &str) -> Option<String> {
+    let register = *GDB_REGISTERS.get(parse_hex(args)? as usize)?;
+    Some(register_hex(processor, register))
+}
+
+fn write_register<B: Bus>(processor: &mut Processor<DebugBus<B>>, args: &str) -> Option<String> {
+    let (register_num, value) = args.split_once('=')?;
+    let register = *GDB_REGISTERS.get(parse_hex(register_num)? as usize)?;
+    processor.set_register(register, parse_register_value(register, value)?);
+
+    Some("OK".to_string())
+}
+
+fn read_memory(bus: &impl Bus, args: &str) -> Option<String> {
+    let (address, length) = args.split_once(',')?;
+    let (address, length) = (parse_hex(address)? as u16, parse_hex(length)?);
+
+    // Two hex digits per byte have to fit in a packet, GDB splits larger reads
+    if length as usize > PACKET_SIZE / 2 {
+        return None;
+    }
+
+    // Memory that cannot be peeked without side effects is reported as an error
+    (0..length)
+        .map(|offset| {
+            bus.peek(address.wrapping_add(offset as u16))
+                .map(|byte| format!("{byte:02x}"))
+        })
+        .collect()
+}
+
+/// Writes through `load_rom` so that breakpoints can be patched into ROM
+fn write_memory(bus: &mut impl Bus, args: &str) -> Option<String> {
+    let (location, data) = args.split_once(':')?;
+    let (address, length) = location.split_once(',')?;
+    let bytes = decode_hex(data)?;
+    if bytes.len() != parse_hex(length)? as usize {
+        return None;
+    }
+
+    bus.load_rom(&bytes, parse_hex(address)? as u16).ok()?;
+    Some("OK".to_string())
+}
+
+/// Handles `Z` and `z` packets: type, address and length or kind
+fn set_breakpoint<B: Bus>(debugger: &mut Debugger<B>, args: &str, insert: bool) -> Option<String> {
+    let mut fields = args.split(',');
+    let kind = fields.next()?;
+    let address = parse_hex(fields.next()?)? as u16;
+    let length = parse_hex(fields.next()?)?;
+
+    let accesses: &[Access] = match kind {
+        // Software and hardware breakpoints are the same to the debugger
+        "0" | "1" => {
+            if insert {
+                debugger.add_breakpoint(address);
+            } else {
+                debugger.remove_breakpoint(address);
+            }
+            return Some("OK".to_string());
+        }
+        "2" => &[Access::Write],
+        "3" => &[Access::Read],
+        "4" => &[Access::Read, Access::Write],
+        _ => return Some(String::new()),
+    };
+
+    for offset in 0..length {
+        let address = address.wrapping_add(offset as u16);
+        for &access in accesses {
+            if insert {
+                debugger.add_watchpoint(address, access);
+            } else {
+                debugger.remove_watchpoint(address, access);
+            }
+        }
+    }
+
+    Some("OK".to_string())
+}
+
+fn query(args: &str) -> String {
+    if args.starts_with("Supported") {
+        return format!(
+            "PacketSize={PACKET_SIZE:x};qXfer:features:read+;QStartNoAckMode+;ReverseStep+;ReverseContinue+"
+        );
+    }
+    if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+        return read_target_xml(range).unwrap_or_else(|| ERROR_REPLY.to_string());
+    }
+
+    match args {
+        "Attached" => "1".to_string(),
+        "C" => "QC1".to_string(),
+        "fThreadInfo" => "m1".to_string(),
+        "sThreadInfo" => "l".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Returns the requested `offset,length` slice of the target description
+fn read_target_xml(range: &str) -> Option<String> {
+    let (offset, length) = range.split_once(',')?;
+    let offset = (parse_hex(offset)? as usize).min(TARGET_XML.len());
+    let end = offset
+        .saturating_add(parse_hex(length)? as usize)
+        .min(TARGET_XML.len());
+
+    let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+    Some(format!("{marker}{}", &TARGET_XML[offset..end]))
+}
@@ -10,22 +10,25 @@ pub fn word_to_bytes(data: u16) -> (u8, u8) {
 }
 
 /// Checks if the number of 1s in byte is even
+#[deprecated(note = "the processor computes parity from a lookup table, use `count_ones`")]
 pub fn bit_parity(byte: u8) -> bool {
-    let mut byte = byte;
+    byte.count_ones().is_multiple_of(2)
+}
 
-    let mut shift: u8 = 4;
-    while shift > 0 {
-        let mask = 0xFF >> (8 - shift);
-        byte = (byte & mask) ^ (byte >> shift);
-        shift /= 2;
-    }
-    byte == 0
+/// Carry out of bit 3 when adding b and an incoming carry to a
+#[deprecated(note = "no longer used by the processor")]
+pub fn auxiliary_add(a: u8, b: u8, carry: bool) -> bool {
+    (a & 0xF) + (b & 0xF) + carry as u8 > 0xF
 }
 
-pub fn auxiliary_add(a: u8, b: u8) -> bool {
-    (a & 0xF) + (b & 0xF) > 0xF
+/// The 8080 subtracts by adding the complement of b, so the auxiliary carry
+/// is set when the low nibble does not need to borrow
+#[deprecated(note = "no longer used by the processor")]
+pub fn auxiliary_sub(a: u8, b: u8, borrow: bool) -> bool {
+    (a & 0xF) + (!b & 0xF) + !borrow as u8 > 0xF
 }
 
-pub fn auxiliary_sub(a: u8, b: u8) -> bool {
-    (a & 0xF) < (b & 0xF)
-}
\ No newline at end of file
+/// Encodes the RST instruction a device places on the bus for an interrupt
+pub fn rst_instruction(interrupt_num: u8) -> u8 {
+    0xC7 | ((interrupt_num & 0b111) << 3)
+}
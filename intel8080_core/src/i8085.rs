@@ -0,0 +1,200 @@
+/// Processor model emulated by `Processor`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Variant {
+    #[default]
+    Intel8080,
+    /// 8085 with RIM, SIM, the TRAP and RST 5.5, 6.5 and 7.5 inputs, the
+    /// serial pins and its own cycle timings
+    Intel8085,
+    /// 8085 that also runs the undocumented opcodes DSUB, ARHL, RDEL, LDHI,
+    /// LDSI, SHLX, LHLX, JNK, JK and RSTV and keeps the V and K flags in
+    /// bits 1 and 5 of F
+    Intel8085Undocumented,
+}
+
+impl Variant {
+    pub fn is_8085(self) -> bool {
+        self != Variant::Intel8080
+    }
+}
+
+/// 8085 interrupt inputs besides INTR, highest priority first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InterruptPin {
+    /// Non-maskable, vectors to 0x24. Latched on the rising edge and only
+    /// serviced while still high.
+    Trap,
+    /// Latched on the rising edge, vectors to 0x3C
+    Rst75,
+    /// Level sensitive, vectors to 0x34
+    Rst65,
+    /// Level sensitive, vectors to 0x2C
+    Rst55,
+}
+
+// RIM and SIM bits of the RST masks
+const MASK_55: u8 = 1 << 0;
+const MASK_65: u8 = 1 << 1;
+const MASK_75: u8 = 1 << 2;
+
+/// Interrupt, mask and serial pin state of the 8085
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Pins {
+    masks: u8,
+    trap: bool,
+    trap_latch: bool,
+    rst75_latch: bool,
+    rst65: bool,
+    rst55: bool,
+    sid: bool,
+    sod: bool,
+    // Interrupt enable state before the last TRAP, reported once by RIM
+    trap_enable: Option<bool>,
+}
+
+impl Default for Pins {
+    fn default() -> Self {
+        // Reset masks all three RST inputs
+        Self {
+            masks: MASK_55 | MASK_65 | MASK_75,
+            trap: false,
+            trap_latch: false,
+            rst75_latch: false,
+            rst65: false,
+            rst55: false,
+            sid: false,
+            sod: false,
+            trap_enable: None,
+        }
+    }
+}
+
+impl Pins {
+    pub fn set(&mut self, pin: InterruptPin, high: bool) {
+        match pin {
+            InterruptPin::Trap => {
+                self.trap_latch = high && (self.trap_latch || !self.trap);
+                self.trap = high;
+            }
+            InterruptPin::Rst75 => self.rst75_latch |= high,
+            InterruptPin::Rst65 => self.rst65 = high,
+            InterruptPin::Rst55 => self.rst55 = high,
+        }
+    }
+
+    pub fn set_sid(&mut self, high: bool) {
+        self.sid = high;
+    }
+
+    pub fn sod(&self) -> bool {
+        self.sod
+    }
+
+    /// True while any input asks for an interrupt, masked or not
+    pub fn requesting(&self) -> bool {
+        self.trap_latch || self.rst75_latch || self.rst65 || self.rst55
+    }
+
+    /// True if `take` would return a vector
+    pub fn pending(&self, enabled: bool) -> bool {
+        self.trap_latch
+            || enabled
+                && ((self.rst75_latch && self.masks & MASK_75 == 0)
+                    || (self.rst65 && self.masks & MASK_65 == 0)
+                    || (self.rst55 && self.masks & MASK_55 == 0))
+    }
+
+    /// Vector of the interrupt to service next, if any. `enabled` is true
+    /// when the processor accepts maskable interrupts.
+    pub fn take(&mut self, enabled: bool, interrupts_enabled: bool) -> Option<u16> {
+        if self.trap_latch {
+            self.trap_latch = false;
+            self.trap_enable = Some(interrupts_enabled);
+            return Some(0x24);
+        }
+        if !enabled {
+            return None;
+        }
+
+        if self.rst75_latch && self.masks & MASK_75 == 0 {
+            self.rst75_latch = false;
+            Some(0x3C)
+        } else if self.rst65 && self.masks & MASK_65 == 0 {
+            Some(0x34)
+        } else if self.rst55 && self.masks & MASK_55 == 0 {
+            Some(0x2C)
+        } else {
+            None
+        }
+    }
+
+    /// Latches, levels and masks for a save-state: the masks, the pin levels
+    /// and latches as bits, and the interrupt enable saved by TRAP
+    pub fn to_bytes(self) -> [u8; 3] {
+        let levels = self.trap as u8
+            | (self.trap_latch as u8) << 1
+            | (self.rst75_latch as u8) << 2
+            | (self.rst65 as u8) << 3
+            | (self.rst55 as u8) << 4
+            | (self.sid as u8) << 5
+            | (self.sod as u8) << 6;
+        let trap_enable = match self.trap_enable {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        };
+
+        [self.masks, levels, trap_enable]
+    }
+
+    /// Inverse of `to_bytes`, None if the bytes were not written by it
+    pub fn from_bytes([masks, levels, trap_enable]: [u8; 3]) -> Option<Self> {
+        if masks & !(MASK_55 | MASK_65 | MASK_75) != 0 || levels & 0x80 != 0 {
+            return None;
+        }
+
+        Some(Self {
+            masks,
+            trap: levels & 1 << 0 != 0,
+            trap_latch: levels & 1 << 1 != 0,
+            rst75_latch: levels & 1 << 2 != 0,
+            rst65: levels & 1 << 3 != 0,
+            rst55: levels & 1 << 4 != 0,
+            sid: levels & 1 << 5 != 0,
+            sod: levels & 1 << 6 != 0,
+            trap_enable: match trap_enable {
+                0 => None,
+                1 => Some(false),
+                2 => Some(true),
+                _ => return None,
+            },
+        })
+    }
+
+    /// Value RIM loads into the accumulator:
+    /// `SID I7.5 I6.5 I5.5 IE M7.5 M6.5 M5.5`
+    pub fn rim(&mut self, interrupts_enabled: bool) -> u8 {
+        let enabled = self.trap_enable.take().unwrap_or(interrupts_enabled);
+
+        (self.sid as u8) << 7
+            | (self.rst75_latch as u8) << 6
+            | (self.rst65 as u8) << 5
+            | (self.rst55 as u8) << 4
+            | (enabled as u8) << 3
+            | self.masks
+    }
+
+    /// Applies the accumulator written by SIM:
+    /// `SOD SOE - R7.5 MSE M7.5 M6.5 M5.5`
+    pub fn sim(&mut self, a: u8) {
+        if a & 0x08 != 0 {
+            self.masks = a & (MASK_55 | MASK_65 | MASK_75);
+        }
+        if a & 0x10 != 0 {
+            self.rst75_latch = false;
+        }
+        if a & 0x40 != 0 {
+            self.sod = a & 0x80 != 0;
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use crate::{
+    errors::{Error, Result},
+    helpers::word_to_bytes,
+};
+
+const BYTES_PER_RECORD: usize = 16;
+
+const DATA_RECORD: u8 = 0x00;
+const EOF_RECORD: u8 = 0x01;
+const EXTENDED_SEGMENT_RECORD: u8 = 0x02;
+const START_SEGMENT_RECORD: u8 = 0x03;
+const EXTENDED_LINEAR_RECORD: u8 = 0x04;
+const START_LINEAR_RECORD: u8 = 0x05;
+
+/// A contiguous run of bytes to be loaded at `address`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Segment {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// Encodes segments as Intel HEX text terminated by an EOF record
+pub fn encode(segments: &[Segment]) -> String {
+    let mut text = String::new();
+
+    for segment in segments {
+        for (index, chunk) in segment.bytes.chunks(BYTES_PER_RECORD).enumerate() {
+            let address = segment
+                .address
+                .wrapping_add((index * BYTES_PER_RECORD) as u16);
+            write_record(&mut text, DATA_RECORD, address, chunk);
+        }
+    }
+    write_record(&mut text, EOF_RECORD, 0, &[]);
+
+    text
+}
+
+/// Decodes Intel HEX text into segments, one per data record
+pub fn decode(text: &str) -> Result<Vec<Segment>> {
+    let mut segments: Vec<Segment> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_num = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let record = parse_record(line).map_err(|message| Error::IntelHex { line_num, message })?;
+        let (kind, address, data) = record;
+
+        match kind {
+            DATA_RECORD => segments.push(Segment {
+                address,
+                bytes: data,
+            }),
+            EOF_RECORD => return Ok(segments),
+            EXTENDED_SEGMENT_RECORD | EXTENDED_LINEAR_RECORD => {
+                if data.iter().any(|&byte| byte != 0) {
+                    return Err(Error::IntelHex {
+                        line_num,
+                        message: "addresses above 64K are not supported".to_string(),
+                    });
+                }
+            }
+            START_SEGMENT_RECORD | START_LINEAR_RECORD => {}
+            _ => {
+                return Err(Error::IntelHex {
+                    line_num,
+                    message: format!("unknown record type {kind:02X}"),
+                });
+            }
+        }
+    }
+
+    Err(Error::IntelHex {
+        line_num: text.lines().count(),
+        message: "missing EOF record".to_string(),
+    })
+}
+
+fn write_record(text: &mut String, kind: u8, address: u16, data: &[u8]) {
+    let (low_byte, high_byte) = word_to_bytes(address);
+    let mut checksum = (data.len() as u8)
+        .wrapping_add(low_byte)
+        .wrapping_add(high_byte)
+        .wrapping_add(kind);
+
+    write!(text, ":{:02X}{:04X}{:02X}", data.len(), address, kind).unwrap();
+    for &byte in data {
+        write!(text, "{byte:02X}").unwrap();
+        checksum = checksum.wrapping_add(byte);
+    }
+    writeln!(text, "{:02X}", checksum.wrapping_neg()).unwrap();
+}
+
+fn parse_record(line: &str) -> std::result::Result<(u8, u16, Vec<u8>), String> {
+    let digits = line
+        .strip_prefix(':')
+        .ok_or_else(|| "record does not start with ':'".to_string())?;
+    if digits.len() % 2 != 0 {
+        return Err("record has an odd number of hex digits".to_string());
+    }
+
+    let bytes = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| "record contains invalid hex digits".to_string())?;
+
+    if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+        return Err("record length does not match its byte count".to_string());
+    }
+    if bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+        return Err("record checksum mismatch".to_string());
+    }
+
+    let address = u16::from_be_bytes([bytes[1], bytes[2]]);
+    let data = bytes[4..bytes.len() - 1].to_vec();
+
+    Ok((bytes[3], address, data))
+}
@@ -1,5 +1,21 @@
 pub mod processor;
+pub mod profile;
+pub mod region;
+pub mod assembler;
+pub mod banked;
+pub mod coverage;
+pub mod cpm;
+pub mod debugger;
+pub mod bus;
+pub mod disassembler;
+mod dispatch;
+pub mod gdb;
 pub mod memory;
 pub mod port;
+pub mod registers;
+pub mod snapshot;
+pub mod trace;
 pub mod errors;
-pub mod helpers;
\ No newline at end of file
+pub mod helpers;
+pub mod i8085;
+pub mod intel_hex;
\ No newline at end of file
@@ -1,70 +1,320 @@
-use crate::errors::{Error, Result};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    bus::{Access, Bus},
+    errors::{Error, Result},
+    region::{Region, RegionMap, Target},
+};
+
+/// A write to ROM, or an access to an address the mapper places outside
+/// of memory. Addresses are as seen on the bus, before mapping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    RomWrite { address: u16, value: u8 },
+    UnmappedRead { address: u16 },
+    UnmappedWrite { address: u16, value: u8 },
+}
+
+impl Fault {
+    pub fn address(&self) -> u16 {
+        match *self {
+            Fault::RomWrite { address, .. }
+            | Fault::UnmappedRead { address }
+            | Fault::UnmappedWrite { address, .. } => address,
+        }
+    }
+
+    pub fn access(&self) -> Access {
+        match self {
+            Fault::UnmappedRead { .. } => Access::Read,
+            Fault::RomWrite { .. } | Fault::UnmappedWrite { .. } => Access::Write,
+        }
+    }
+}
+
+/// Decides a fault for `FaultPolicy::Callback`. It may keep state between
+/// faults, clones of the memory share it.
+pub type FaultCallback = Arc<Mutex<dyn FnMut(Fault) -> Result<u8> + Send>>;
+
+/// What `Memory` does on a fault
+#[derive(Clone, Default)]
+pub enum FaultPolicy {
+    /// Fail the access
+    #[default]
+    Error,
+    /// Drop writes and read the open bus value, like most boards do
+    Ignore,
+    /// Like `Ignore`, and keep the fault for `Memory::take_faults`
+    Log,
+    /// Returns the value a read sees, it is discarded for writes. An error
+    /// fails the access.
+    Callback(FaultCallback),
+}
+
+impl FaultPolicy {
+    /// Wraps a closure in `FaultPolicy::Callback`
+    pub fn callback(callback: impl FnMut(Fault) -> Result<u8> + Send + 'static) -> Self {
+        FaultPolicy::Callback(Arc::new(Mutex::new(callback)))
+    }
+}
+
+impl fmt::Debug for FaultPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultPolicy::Error => f.write_str("Error"),
+            FaultPolicy::Ignore => f.write_str("Ignore"),
+            FaultPolicy::Log => f.write_str("Log"),
+            FaultPolicy::Callback(_) => f.write_str("Callback"),
+        }
+    }
+}
+
+/// Device answering the MMIO regions of a region map, shared with the host
+/// and with clones of the memory
+#[derive(Clone)]
+struct Mmio(Arc<Mutex<dyn Bus + Send>>);
+
+impl fmt::Debug for Mmio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Mmio")
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Mapper {
+    /// Returns the backing index of an address and whether it is ROM
+    Function(fn(u16) -> (usize, bool)),
+    Regions(Arc<RegionMap>),
+}
 
 #[derive(Clone, Debug)]
 pub struct Memory {
     data: Vec<u8>,
     size: usize,
-    memory_mapper: fn(u16) -> (usize, bool),
+    mapper: Mapper,
+    mmio: Option<Mmio>,
+    rom_writes: FaultPolicy,
+    unmapped: FaultPolicy,
+    open_bus: u8,
+    faults: Vec<Fault>,
 }
 
 impl Memory {
     pub fn new(size: usize, memory_mapper: fn(u16) -> (usize, bool)) -> Self {
+        Self::with_mapper(size, Mapper::Function(memory_mapper))
+    }
+
+    /// Memory laid out by a region map, with as much backing memory as its
+    /// ROM and RAM regions need
+    pub fn with_regions(regions: RegionMap) -> Self {
+        Self::with_mapper(regions.backing_size(), Mapper::Regions(Arc::new(regions)))
+    }
+
+    fn with_mapper(size: usize, mapper: Mapper) -> Self {
         Self {
             data: vec![0; size],
             size,
-            memory_mapper,
+            mapper,
+            mmio: None,
+            rom_writes: FaultPolicy::default(),
+            unmapped: FaultPolicy::default(),
+            open_bus: 0xFF,
+            faults: Vec::new(),
         }
     }
 
+    /// Installs the device answering the MMIO regions. Without one they are
+    /// treated as unmapped. The caller keeps its own handle on the device,
+    /// and clones of the memory share it rather than copying it. Each MMIO
+    /// access locks the device, RAM and ROM accesses do not.
+    pub fn set_mmio<D: Bus + Send + 'static>(&mut self, device: Arc<Mutex<D>>) {
+        self.mmio = Some(Mmio(device));
+    }
+
+    pub fn regions(&self) -> Option<&RegionMap> {
+        match &self.mapper {
+            Mapper::Regions(regions) => Some(regions),
+            Mapper::Function(_) => None,
+        }
+    }
+
+    /// Region decoding `address`, if the memory has a region map
+    pub fn region(&self, address: u16) -> Option<&Region> {
+        self.regions()?.region(address)
+    }
+
+    pub fn set_rom_write_policy(&mut self, policy: FaultPolicy) {
+        self.rom_writes = policy;
+    }
+
+    /// Policy for reads and writes mapped outside of memory
+    pub fn set_unmapped_policy(&mut self, policy: FaultPolicy) {
+        self.unmapped = policy;
+    }
+
+    /// Value read from unmapped addresses when they are ignored or logged,
+    /// 0xFF by default like an undriven bus
+    pub fn set_open_bus(&mut self, value: u8) {
+        self.open_bus = value;
+    }
+
+    /// Returns the faults logged since the last call, oldest first
+    pub fn take_faults(&mut self) -> Vec<Fault> {
+        std::mem::take(&mut self.faults)
+    }
+
     pub fn load_rom(&mut self, rom: &[u8], address: u16) -> Result<()> {
-        let (address, _) = (self.memory_mapper)(address);
+        let Some(start) = self.backing_index(address) else {
+            return Err(self.error(Fault::UnmappedWrite { address, value: 0 }));
+        };
+        let space_left = self.size.saturating_sub(start);
 
-        if rom.len() > self.size - address {
+        if rom.len() > space_left {
             return Err(Error::RomSize {
                 rom_size: rom.len(),
-                space_left: self.size - address,
+                space_left,
             });
         }
-        self.data[address..address + rom.len()].copy_from_slice(rom);
+        self.data[start..start + rom.len()].copy_from_slice(rom);
 
         Ok(())
     }
 
-    pub fn read(&self, address: u16) -> Result<u8> {
-        let (address, _) = (self.memory_mapper)(address);
-        if address >= self.size {
-            return Err(Error::InvalidMemory(address));
+    /// Fails on ROM, MMIO and unmapped addresses whatever the policies are,
+    /// as there is nothing to hand out a reference to
+    pub fn read_mut(&mut self, address: u16) -> Result<&mut u8> {
+        match self.decode(address) {
+            Target::Ram(index) => Ok(&mut self.data[index]),
+            Target::Rom(_) => Err(self.error(Fault::RomWrite { address, value: 0 })),
+            _ => Err(self.error(Fault::UnmappedWrite { address, value: 0 })),
         }
-        Ok(self.data[address])
     }
 
-    pub fn read_mut(&mut self, address: u16) -> Result<&mut u8> {
-        let (address, is_rom) = (self.memory_mapper)(address);
+    pub fn write(&mut self, address: u16, value: u8) -> Result<()> {
+        match self.decode(address) {
+            Target::Ram(index) => {
+                self.data[index] = value;
+                Ok(())
+            }
+            Target::Rom(_) => self.fault(Fault::RomWrite { address, value }).map(drop),
+            Target::Mmio(offset) if let Some(Mmio(device)) = &self.mmio => {
+                device.lock().unwrap().write(offset, value)
+            }
+            _ => self
+                .fault(Fault::UnmappedWrite { address, value })
+                .map(drop),
+        }
+    }
 
-        if (address >= self.size) || is_rom {
-            return Err(Error::InvalidMemory(address));
+    fn decode(&self, address: u16) -> Target {
+        match &self.mapper {
+            Mapper::Function(memory_mapper) => match memory_mapper(address) {
+                (index, _) if index >= self.size => Target::Unmapped,
+                (index, true) => Target::Rom(index),
+                (index, false) => Target::Ram(index),
+            },
+            Mapper::Regions(regions) => regions.decode(address),
         }
-        Ok(&mut self.data[address])
     }
 
-    pub fn write(&mut self, address: u16, value: u8) -> Result<()> {
-        let (address, is_rom) = (self.memory_mapper)(address);
+    fn backing_index(&self, address: u16) -> Option<usize> {
+        match self.decode(address) {
+            Target::Rom(index) | Target::Ram(index) => Some(index),
+            Target::Mmio(_) | Target::Unmapped => None,
+        }
+    }
 
-        if (address >= self.size) || is_rom {
-            return Err(Error::InvalidMemory(address));
+    fn fault(&mut self, fault: Fault) -> Result<u8> {
+        match self.policy(&fault) {
+            FaultPolicy::Error => Err(self.error(fault)),
+            FaultPolicy::Ignore => Ok(self.open_bus),
+            FaultPolicy::Log => {
+                self.faults.push(fault);
+                Ok(self.open_bus)
+            }
+            FaultPolicy::Callback(callback) => (callback.lock().unwrap())(fault),
         }
-        self.data[address] = value;
+    }
 
-        Ok(())
+    fn policy(&self, fault: &Fault) -> &FaultPolicy {
+        match fault {
+            Fault::RomWrite { .. } => &self.rom_writes,
+            Fault::UnmappedRead { .. } | Fault::UnmappedWrite { .. } => &self.unmapped,
+        }
+    }
+
+    /// Error for a fault, naming the region it hit
+    fn error(&self, fault: Fault) -> Error {
+        let address = fault.address();
+        let region = self.region(address).map(|region| region.name.clone());
+
+        match fault {
+            Fault::RomWrite { .. } => Error::RomWrite { address, region },
+            Fault::UnmappedRead { .. } => Error::UnmappedRead { address, region },
+            Fault::UnmappedWrite { .. } => Error::UnmappedWrite { address, region },
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub(crate) fn contents(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub(crate) fn contents_mut(&mut self) -> &mut [u8] {
+        &mut self.data
     }
 
     pub fn memory_slice(&self, address: u16, size: usize) -> Result<&[u8]> {
-        let (address, _) = (self.memory_mapper)(address);
+        let start = self.backing_index(address).unwrap_or(self.size);
 
-        if address + size >= self.size {
-            Ok(&self.data[address..address + size])
+        if start + size <= self.size {
+            Ok(&self.data[start..start + size])
         } else {
-            Err(Error::InvalidMemory(address + size))
+            Err(Error::InvalidMemory(start + size))
+        }
+    }
+}
+
+impl Bus for Memory {
+    fn read(&mut self, address: u16) -> Result<u8> {
+        match self.decode(address) {
+            Target::Rom(index) | Target::Ram(index) => Ok(self.data[index]),
+            Target::Mmio(offset) if let Some(Mmio(device)) = &self.mmio => {
+                device.lock().unwrap().read(offset)
+            }
+            _ => self.fault(Fault::UnmappedRead { address }),
         }
     }
+
+    fn write(&mut self, address: u16, value: u8) -> Result<()> {
+        Memory::write(self, address, value)
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        match self.decode(address) {
+            Target::Rom(index) | Target::Ram(index) => Some(self.data[index]),
+            Target::Mmio(offset) => self.mmio.as_ref()?.0.lock().unwrap().peek(offset),
+            Target::Unmapped => None,
+        }
+    }
+
+    fn poke(&mut self, address: u16, value: u8) -> bool {
+        match self.decode(address) {
+            Target::Rom(index) | Target::Ram(index) => {
+                self.data[index] = value;
+                true
+            }
+            Target::Mmio(_) | Target::Unmapped => false,
+        }
+    }
+
+    fn load_rom(&mut self, rom: &[u8], address: u16) -> Result<()> {
+        Memory::load_rom(self, rom, address)
+    }
 }
@@ -1,14 +1,45 @@
 use crate::{
+    bus::Bus,
+    coverage::{Coverage, Usage},
+    dispatch::{AC, ALL, CY, Dispatch, K, P, S, SZP_FLAGS, V, Z},
     errors::{Error, Result},
-    helpers::{auxiliary_add, auxiliary_sub, bit_parity, bytes_to_word, word_to_bytes},
+    helpers::{bytes_to_word, word_to_bytes},
+    i8085::{InterruptPin, Pins, Variant},
     memory::Memory,
     port::Port,
+    profile::Profiler,
+    registers::{CpuState, Flags, Register},
+    snapshot::Snapshot,
+    trace::Tracer,
 };
 
-use std::cmp::max;
+/// Cycles burned by each call to execute while halted
+const HALT_IDLE_CYCLES: u32 = 4;
+
+/// Cycles of an 8085 TRAP or RST 5.5, 6.5 or 7.5 acknowledge
+const RESTART_CYCLES: u32 = 12;
+
+/// Runs an opcode the processor does not decode. It gets the opcode, the
+/// processor state with PC past the opcode and the bus, from which it reads
+/// any operands, moving PC past them. Returns the cycles taken.
+pub type UndocumentedCallback = fn(u8, &mut CpuState, &mut dyn Bus) -> Result<u32>;
+
+/// What the processor does with opcodes its variant does not document
+#[derive(Clone, Copy, Debug, Default)]
+pub enum UndocumentedOpcodes {
+    /// Fail with `Error::UnknownOpcode`
+    #[default]
+    Strict,
+    /// Run them like 8080 silicon: 0x08-0x38 as NOP, 0xCB as JMP, 0xD9 as
+    /// RET and 0xDD, 0xED and 0xFD as CALL. The 8085 has no aliases and
+    /// treats this as `Strict`, its undocumented opcodes come with
+    /// `Variant::Intel8085Undocumented`.
+    Alias,
+    Callback(UndocumentedCallback),
+}
 
 #[derive(Clone, Debug)]
-pub struct Processor {
+pub struct Processor<B: Bus = Memory> {
     a: u8,
     b: u8,
     c: u8,
@@ -21,22 +52,124 @@ pub struct Processor {
 
     rom_loaded: bool,
     interrupts_enabled: bool,
+    ei_pending: bool,
+    halted: bool,
+    // A ROM is loaded, the processor is not halted and no 8085 interrupt
+    // input is active
+    running: bool,
+
+    // INTR line state and the instruction the device will supply
+    intr: Option<[u8; 3]>,
+    inta: bool,
+
+    // Instruction supplied on the data bus during an interrupt acknowledge
+    bus_instruction: Option<[u8; 3]>,
+    cycles: u64,
+
+    bus: B,
+    // F register, kept in its packed form
+    f: u8,
+    variant: Variant,
+    undocumented: UndocumentedOpcodes,
+    // Decode table for the variant and undocumented opcode policy
+    table: usize,
+    pins: Pins,
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+}
 
-    ram: Memory,
-    flags: Flags,
+/// Processor state outside the bus, saved by the debugger to undo an
+/// instruction
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Checkpoint {
+    state: CpuState,
+    ei_pending: bool,
+    inta: bool,
+    cycles: u64,
+    pins: Pins,
 }
 
-#[derive(Clone, Debug)]
-struct Flags {
-    s: bool,
-    z: bool,
-    p: bool,
-    cy: bool,
-    ac: bool,
+/// Pads an instruction to 3 bytes the way an undriven data bus reads 0xFF
+fn bus_bytes(instruction: &[u8]) -> [u8; 3] {
+    let mut bus = [0xFF; 3];
+    let length = instruction.len().min(3);
+    bus[..length].copy_from_slice(&instruction[..length]);
+
+    bus
+}
+
+/// V and K flags of adding `left` and `right`, subtractions pass the
+/// complement of their operand. K is set when at least two of the operand
+/// and result signs are negative.
+fn overflow_flags(left: u8, right: u8, result: u8) -> u8 {
+    let overflow = !(left ^ right) & (left ^ result) & 0x80;
+    let underflow = ((left & right) | (left & result) | (right & result)) & 0x80;
+
+    (overflow >> 6) | (underflow >> 2)
 }
 
-impl Processor {
+impl Processor<Memory> {
     pub fn new(ram_size: usize, memory_mapper: fn(u16) -> (usize, bool)) -> Self {
+        Self::with_bus(Memory::new(ram_size, memory_mapper))
+    }
+
+    /// Serializes the processor and RAM contents into a versioned save-state
+    pub fn save_state(&self) -> Vec<u8> {
+        Snapshot {
+            state: self.state(),
+            ei_pending: self.ei_pending,
+            rom_loaded: self.rom_loaded,
+            intr: self.intr,
+            inta: self.inta,
+            cycles: self.cycles,
+            variant: self.variant,
+            undocumented: match self.undocumented {
+                UndocumentedOpcodes::Callback(_) => None,
+                undocumented => Some(undocumented),
+            },
+            pins: self.pins,
+            ram: self.bus.contents().to_vec(),
+        }
+        .to_bytes()
+    }
+
+    /// Restores a save-state taken from a processor with the same RAM size.
+    /// The memory mapper is not part of the save-state and is kept as is.
+    /// Callbacks cannot be saved, so a save-state taken with one only loads
+    /// into a processor that already has a callback installed.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<()> {
+        let snapshot = Snapshot::from_bytes(bytes, self.bus.size())?;
+        let undocumented = match (snapshot.undocumented, self.undocumented) {
+            (Some(undocumented), _) => undocumented,
+            (None, callback @ UndocumentedOpcodes::Callback(_)) => callback,
+            (None, _) => return Err(Error::SnapshotCallback),
+        };
+
+        self.set_variant(snapshot.variant);
+        self.set_undocumented_opcodes(undocumented);
+        self.pins = snapshot.pins;
+        self.set_state(&snapshot.state);
+        self.ei_pending = snapshot.ei_pending;
+        self.rom_loaded = snapshot.rom_loaded;
+        self.update_running();
+        self.intr = snapshot.intr;
+        self.inta = snapshot.inta;
+        self.cycles = snapshot.cycles;
+        self.bus.contents_mut().copy_from_slice(&snapshot.ram);
+
+        Ok(())
+    }
+
+    pub fn memory_slice(&self, address: u16, size: usize) -> Result<&[u8]> {
+        self.bus.memory_slice(address, size)
+    }
+}
+
+impl<B: Bus> Processor<B> {
+    /// Creates a processor on a custom bus. A ROM still has to be loaded
+    /// through `load_rom` before the processor will execute.
+    pub fn with_bus(bus: B) -> Self {
         Self {
             a: 0,
             b: 0,
@@ -47,531 +180,618 @@ impl Processor {
             l: 0,
             sp: 0,
             pc: 0,
-            ram: Memory::new(ram_size, memory_mapper),
+            bus,
             rom_loaded: false,
             interrupts_enabled: false,
-            flags: Flags {
-                s: false,
-                z: false,
-                p: false,
-                cy: false,
-                ac: false,
-            },
+            ei_pending: false,
+            halted: false,
+            running: false,
+            intr: None,
+            inta: false,
+            bus_instruction: None,
+            cycles: 0,
+            f: Flags::default().to_byte(),
+            variant: Variant::default(),
+            undocumented: UndocumentedOpcodes::default(),
+            table: 0,
+            pins: Pins::default(),
+            tracer: None,
+            profiler: None,
+            coverage: None,
         }
     }
 
     pub fn load_rom(&mut self, rom: &[u8], address: u16) -> Result<()> {
-        self.ram.load_rom(rom, address)?;
+        self.bus.load_rom(rom, address)?;
         self.rom_loaded = true;
+        self.update_running();
 
         Ok(())
     }
 
-    pub fn interrupt(&mut self, interrupt_num: u8) -> Result<()> {
-        if !self.interrupts_enabled {
-            return Ok(());
+    /// Runs an interrupt acknowledge cycle, executing `instruction` as if an
+    /// external device had placed it on the data bus. Missing bytes read as
+    /// 0xFF like an undriven bus. Returns the cycles spent, or None if the
+    /// interrupt was not accepted.
+    pub fn interrupt(&mut self, instruction: &[u8], port: &mut impl Port) -> Result<Option<u32>> {
+        if !self.accepts_interrupt() {
+            return Ok(None);
         }
 
-        let (low_byte, high_byte) = word_to_bytes(self.pc);
-        self.push_16bit(low_byte, high_byte)?;
+        let cycles = self.acknowledge_interrupt(bus_bytes(instruction), port)?;
+        self.cycles += cycles as u64;
+        if let Some(tracer) = &mut self.tracer {
+            tracer.add_cycles(cycles);
+        }
 
-        let address = ((interrupt_num & 0b111) << 3) as u16;
-        self.pc = address;
+        Ok(Some(cycles))
+    }
 
-        Ok(())
+    /// Holds the INTR line high until cleared. The request is sampled at
+    /// instruction boundaries and `instruction` is supplied on the data bus
+    /// once the processor acknowledges it.
+    pub fn set_intr(&mut self, instruction: &[u8]) {
+        self.intr = Some(bus_bytes(instruction));
     }
 
-    pub fn memory_slice(&self, address: u16, size: usize) -> Result<&[u8]> {
-        self.ram.memory_slice(address, size)
+    /// Drops the INTR line
+    pub fn clear_intr(&mut self) {
+        self.intr = None;
     }
 
-    pub fn execute(&mut self, port: &mut impl Port) -> Result<u32> {
-        if !self.rom_loaded {
-            return Err(Error::RomNotLoaded);
-        }
+    pub fn intr_pending(&self) -> bool {
+        self.intr.is_some()
+    }
 
-        let opcode: u8 = self.ram.read(self.pc)?;
-        let cycles: u32;
+    /// True if the next `execute` services an interrupt instead of running
+    /// the instruction at PC, from INTR or one of the 8085 inputs
+    pub fn interrupt_due(&self) -> bool {
+        let accepts = self.accepts_interrupt();
 
-        match opcode {
-            // NOP opcodes
-            0x00 => {
-                self.pc += 1;
-                cycles = 4;
-            }
+        (self.variant.is_8085() && self.pins.pending(accepts)) || (self.intr.is_some() && accepts)
+    }
 
-            // HLT opcode
-            0x76 => return Err(Error::SystemHalt),
+    /// Returns true once for every interrupt acknowledge cycle since the last call
+    pub fn take_inta(&mut self) -> bool {
+        std::mem::take(&mut self.inta)
+    }
 
-            // MOV Register to register
-            0x40..=0x7F => {
-                cycles = self.mov_opcode(opcode)?;
-                self.pc += 1;
-            }
+    /// Total cycles run by `execute` and `interrupt`
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
 
-            // MVI opcodes
-            0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E => {
-                let data = self.get_next_byte()?;
-                cycles = self.mvi_opcode(opcode, data)?;
-                self.pc += 2;
-            }
+    /// Returns true while the processor is waiting in HLT for an interrupt
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
 
-            // LXI opcodes
-            0x01 | 0x11 | 0x21 | 0x31 => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.lxi_opcode(opcode, low_byte, high_byte);
-                self.pc += 3;
-                cycles = 10;
-            }
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
 
-            // LDA opcode
-            0x3A => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.lda_opcode(low_byte, high_byte)?;
-                self.pc += 3;
-                cycles = 13;
-            }
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
 
-            // STA opcode
-            0x32 => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.sta_opcode(low_byte, high_byte)?;
-                self.pc += 3;
-                cycles = 13;
-            }
+    pub fn flags(&self) -> Flags {
+        match self.variant {
+            Variant::Intel8085Undocumented => Flags::from_byte_8085(self.f),
+            _ => Flags::from_byte(self.f),
+        }
+    }
 
-            // LHLD opcode
-            0x2A => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.lhld_opcode(low_byte, high_byte)?;
-                self.pc += 3;
-                cycles = 16;
-            }
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.f = match self.variant {
+            Variant::Intel8085Undocumented => flags.to_byte_8085(),
+            _ => flags.to_byte(),
+        };
+    }
 
-            // SHLD opcode
-            0x22 => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.shld_opcode(low_byte, high_byte)?;
-                self.pc += 3;
-                cycles = 16;
-            }
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
 
-            // LDAX opcode
-            0x0A | 0x1A => {
-                self.ldax_opcode(opcode)?;
-                self.pc += 1;
-                cycles = 7;
-            }
+    /// Switches the processor model, keeping registers and memory
+    pub fn set_variant(&mut self, variant: Variant) {
+        let flags = self.flags();
+        self.variant = variant;
+        self.set_flags(flags);
+        self.update_table();
+        self.update_running();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.set_variant(variant);
+        }
+        if let Some(tracer) = &mut self.tracer {
+            tracer.set_variant(variant);
+        }
+    }
 
-            // Stax
-            0x02 | 0x12 => {
-                self.stax_opcode(opcode)?;
-                self.pc += 1;
-                cycles = 7;
-            }
+    pub fn undocumented_opcodes(&self) -> UndocumentedOpcodes {
+        self.undocumented
+    }
 
-            // XCHG opcode
-            0xEB => {
-                self.xchg_opcode();
-                self.pc += 1;
-                cycles = 5;
-            }
+    /// Sets what happens to opcodes the variant does not document. The 8085
+    /// has no aliases, so there `Alias` fails like `Strict` does.
+    pub fn set_undocumented_opcodes(&mut self, undocumented: UndocumentedOpcodes) {
+        self.undocumented = undocumented;
+        self.update_table();
+    }
 
-            // ADD opcodes
-            0x80..=0x87 => {
-                cycles = self.add_opcode(opcode)?;
-                self.pc += 1;
-            }
+    /// Drives an 8085 interrupt input. The inputs are ignored by the 8080.
+    pub fn set_interrupt_pin(&mut self, pin: InterruptPin, high: bool) {
+        self.pins.set(pin, high);
+        self.update_running();
+    }
 
-            // ADI opcode
-            0xC6 => {
-                let immediate = self.get_next_byte()?;
-                self.adi_opcode(immediate)?;
-                self.pc += 2;
-                cycles = 7;
-            }
+    /// Drives the 8085 serial input read by RIM
+    pub fn set_sid(&mut self, high: bool) {
+        self.pins.set_sid(high);
+    }
 
-            // ADC opcodes
-            0x88..=0x8F => {
-                cycles = self.adc_opcode(opcode)?;
-                self.pc += 1;
-            }
+    /// Level of the 8085 serial output written by SIM
+    pub fn sod(&self) -> bool {
+        self.pins.sod()
+    }
 
-            // ACI opcode
-            0xCE => {
-                let immediate = self.get_next_byte()?;
-                self.aci_opcode(immediate)?;
-                self.pc += 2;
-                cycles = 7;
-            }
+    pub fn interrupts_enabled(&self) -> bool {
+        self.interrupts_enabled
+    }
 
-            // Sub opcodes
-            0x90..=0x97 => {
-                cycles = self.sub_opcode(opcode)?;
-                self.pc += 1;
-            }
+    pub fn set_interrupts_enabled(&mut self, enabled: bool) {
+        self.interrupts_enabled = enabled;
+        self.ei_pending = false;
+    }
 
-            // SUI opcode
-            0xD6 => {
-                let immediate = self.get_next_byte()?;
-                self.sui_opcode(immediate)?;
-                self.pc += 2;
-                cycles = 7;
-            }
+    /// Reads a register or register pair, 8-bit registers are zero-extended
+    pub fn register(&self, register: Register) -> u16 {
+        match register {
+            Register::A => self.a as u16,
+            Register::F => self.flags_to_byte() as u16,
+            Register::B => self.b as u16,
+            Register::C => self.c as u16,
+            Register::D => self.d as u16,
+            Register::E => self.e as u16,
+            Register::H => self.h as u16,
+            Register::L => self.l as u16,
+            Register::BC => self.get_bc(),
+            Register::DE => self.get_de(),
+            Register::HL => self.get_hl(),
+            Register::SP => self.sp,
+            Register::PC => self.pc,
+            Register::PSW => bytes_to_word(self.flags_to_byte(), self.a),
+        }
+    }
 
-            // SBB Opcodes
-            0x98..=0x9F => {
-                cycles = self.sbb_opcode(opcode)?;
-                self.pc += 1;
+    /// Writes a register or register pair, 8-bit registers take the low byte
+    pub fn set_register(&mut self, register: Register, value: u16) {
+        let (low_byte, high_byte) = word_to_bytes(value);
+
+        match register {
+            Register::A => self.a = low_byte,
+            Register::F => self.byte_to_flag(low_byte),
+            Register::B => self.b = low_byte,
+            Register::C => self.c = low_byte,
+            Register::D => self.d = low_byte,
+            Register::E => self.e = low_byte,
+            Register::H => self.h = low_byte,
+            Register::L => self.l = low_byte,
+            Register::BC => (self.b, self.c) = (high_byte, low_byte),
+            Register::DE => (self.d, self.e) = (high_byte, low_byte),
+            Register::HL => (self.h, self.l) = (high_byte, low_byte),
+            Register::SP => self.sp = value,
+            Register::PC => self.pc = value,
+            Register::PSW => {
+                self.byte_to_flag(low_byte);
+                self.a = high_byte;
             }
+        }
+    }
 
-            // SBI opcode
-            0xDE => {
-                let immediate = self.get_next_byte()?;
-                self.sbi_opcode(immediate)?;
-                self.pc += 1;
-                cycles = 7;
-            }
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: self.a,
+            b: self.b,
+            c: self.c,
+            d: self.d,
+            e: self.e,
+            h: self.h,
+            l: self.l,
+            sp: self.sp,
+            pc: self.pc,
+            flags: self.flags(),
+            interrupts_enabled: self.interrupts_enabled,
+            halted: self.halted,
+        }
+    }
 
-            // INR opcodes
-            0x04 | 0x0C | 0x14 | 0x1C | 0x24 | 0x2C | 0x34 | 0x3C => {
-                cycles = self.inr_opcode(opcode)?;
-                self.pc += 1;
-            }
+    pub fn set_state(&mut self, state: &CpuState) {
+        self.a = state.a;
+        self.b = state.b;
+        self.c = state.c;
+        self.d = state.d;
+        self.e = state.e;
+        self.h = state.h;
+        self.l = state.l;
+        self.sp = state.sp;
+        self.pc = state.pc;
+        self.set_flags(state.flags);
+        self.halted = state.halted;
+        self.update_running();
+        self.set_interrupts_enabled(state.interrupts_enabled);
+    }
+
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            state: self.state(),
+            ei_pending: self.ei_pending,
+            inta: self.inta,
+            cycles: self.cycles,
+            pins: self.pins,
+        }
+    }
 
-            // DCR opcodes
-            0x05 | 0x0D | 0x15 | 0x1D | 0x25 | 0x2D | 0x35 | 0x3D => {
-                cycles = self.dcr_opcode(opcode)?;
-                self.pc += 1;
-            }
+    pub(crate) fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.pins = checkpoint.pins;
+        self.set_state(&checkpoint.state);
+        self.ei_pending = checkpoint.ei_pending;
+        self.inta = checkpoint.inta;
+        self.cycles = checkpoint.cycles;
+    }
+
+    pub fn bus(&self) -> &B {
+        &self.bus
+    }
+
+    pub fn bus_mut(&mut self) -> &mut B {
+        &mut self.bus
+    }
+
+    /// Moves the processor onto a new bus built from the current one, for
+    /// example to wrap it in a debugging layer
+    pub fn map_bus<C: Bus>(self, map: impl FnOnce(B) -> C) -> Processor<C> {
+        Processor {
+            a: self.a,
+            b: self.b,
+            c: self.c,
+            d: self.d,
+            e: self.e,
+            h: self.h,
+            l: self.l,
+            sp: self.sp,
+            pc: self.pc,
+            rom_loaded: self.rom_loaded,
+            interrupts_enabled: self.interrupts_enabled,
+            ei_pending: self.ei_pending,
+            halted: self.halted,
+            running: self.running,
+            intr: self.intr,
+            inta: self.inta,
+            bus_instruction: self.bus_instruction,
+            cycles: self.cycles,
+            bus: map(self.bus),
+            f: self.f,
+            variant: self.variant,
+            undocumented: self.undocumented,
+            table: self.table,
+            pins: self.pins,
+            tracer: self.tracer,
+            profiler: self.profiler,
+            coverage: self.coverage,
+        }
+    }
 
-            // INX opcodes
-            0x03 | 0x13 | 0x23 | 0x33 => {
-                self.inx_opcode(opcode);
-                self.pc += 1;
-                cycles = 5;
-            }
+    /// Installs or removes an instruction tracer
+    pub fn set_tracer(&mut self, mut tracer: Option<Tracer>) {
+        if let Some(tracer) = &mut tracer {
+            tracer.set_variant(self.variant);
+        }
+        self.tracer = tracer;
+    }
 
-            // DCX opcodes
-            0x0B | 0x1B | 0x2B | 0x3B => {
-                self.dcx_opcode(opcode);
-                self.pc += 1;
-                cycles = 5;
-            }
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
 
-            // DAD opcodes
-            0x09 | 0x19 | 0x29 | 0x39 => {
-                self.dad_opcode(opcode);
-                self.pc += 1;
-                cycles = 10;
-            }
+    pub fn tracer_mut(&mut self) -> Option<&mut Tracer> {
+        self.tracer.as_mut()
+    }
 
-            // DAA opcode
-            0x27 => {
-                self.daa_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+    /// Installs or removes an execution profiler
+    pub fn set_profiler(&mut self, mut profiler: Option<Profiler>) {
+        if let Some(profiler) = &mut profiler {
+            profiler.set_variant(self.variant);
+        }
+        self.profiler = profiler;
+    }
 
-            // ANA opcodes
-            0xA0..=0xA7 => {
-                cycles = self.ana_opcode(opcode)?;
-                self.pc += 1;
-            }
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
 
-            // ANI opcode
-            0xE6 => {
-                let immediate = self.get_next_byte()?;
-                self.ani_opcode(immediate);
-                self.pc += 2;
-                cycles = 7;
-            }
+    pub fn profiler_mut(&mut self) -> Option<&mut Profiler> {
+        self.profiler.as_mut()
+    }
 
-            // ORA opcodes
-            0xB0..=0xB7 => {
-                cycles = self.ora_opcode(opcode)?;
-                self.pc += 1;
-            }
+    /// Installs or removes a coverage map
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) {
+        self.coverage = coverage;
+    }
 
-            // ORI opcode
-            0xF6 => {
-                let immediate = self.get_next_byte()?;
-                self.ori_opcode(immediate);
-                self.pc += 2;
-                cycles = 7;
-            }
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
 
-            // XRA opcodes
-            0xA8..=0xAF => {
-                cycles = self.xra_opcode(opcode)?;
-                self.pc += 1;
-            }
+    pub fn coverage_mut(&mut self) -> Option<&mut Coverage> {
+        self.coverage.as_mut()
+    }
 
-            // XRI opcode
-            0xEE => {
-                let immediate = self.get_next_byte()?;
-                self.xri_opcode(immediate);
-                self.pc += 2;
-                cycles = 7;
-            }
+    pub fn execute(&mut self, port: &mut impl Port) -> Result<u32> {
+        let cycles = self.execute_instruction(port)?;
+        self.cycles += cycles as u64;
+        if let Some(tracer) = &mut self.tracer {
+            tracer.add_cycles(cycles);
+        }
 
-            // CMP opcodes
-            0xB8..=0xBF => {
-                cycles = self.cmp_opcode(opcode)?;
-                self.pc += 1;
-            }
+        Ok(cycles)
+    }
 
-            // CPI opcode
-            0xFE => {
-                let immediate = self.get_next_byte()?;
-                self.cpi_opcode(immediate);
-                self.pc += 2;
-                cycles = 7;
-            }
+    fn execute_instruction(&mut self, port: &mut impl Port) -> Result<u32> {
+        if !self.running || self.intr.is_some() {
+            return self.execute_stopped(port);
+        }
 
-            // RLC opcode
-            0x07 => {
-                self.rlc_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+        // EI only takes effect after the instruction that follows it
+        self.ei_pending = false;
+        self.fetch_and_execute(port)
+    }
 
-            // RRC opcode
-            0x0F => {
-                self.rrc_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+    /// Slow path of `execute` for a missing ROM, a held INTR line or HLT
+    fn execute_stopped(&mut self, port: &mut impl Port) -> Result<u32> {
+        if !self.rom_loaded {
+            return Err(Error::RomNotLoaded);
+        }
 
-            // RAL opcode
-            0x17 => {
-                self.ral_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+        // The 8085 inputs take priority over INTR
+        if self.variant.is_8085()
+            && let Some(vector) = self
+                .pins
+                .take(self.accepts_interrupt(), self.interrupts_enabled)
+        {
+            return self.restart(vector);
+        }
 
-            // RAR opcode
-            0x1F => {
-                self.rar_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+        // Service a held INTR line at the instruction boundary
+        if let Some(instruction) = self.intr
+            && self.accepts_interrupt()
+        {
+            return self.acknowledge_interrupt(instruction, port);
+        }
 
-            // CMA opcode
-            0x2F => {
-                self.cma_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+        // EI only takes effect after the instruction that follows it
+        self.ei_pending = false;
 
-            // CMC opcode
-            0x3F => {
-                self.cmc_opcode();
-                self.pc += 1;
-                cycles = 4;
+        // Idle until an interrupt wakes the processor
+        if self.halted {
+            if let Some(profiler) = &mut self.profiler {
+                profiler.record_idle(HALT_IDLE_CYCLES);
             }
+            return Ok(HALT_IDLE_CYCLES);
+        }
 
-            // STC opcode
-            0x37 => {
-                self.stc_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+        self.fetch_and_execute(port)
+    }
 
-            // JMP opcode
-            0xC3 => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.jmp_opcode(low_byte, high_byte);
-                cycles = 10;
-            }
+    fn fetch_and_execute(&mut self, port: &mut impl Port) -> Result<u32> {
+        let opcode: u8 = self.access(self.pc, Usage::Opcode)?;
+        if self.tracer.is_some() {
+            self.trace(opcode)?;
+        }
 
-            // JC, JNC, JZ, JNZ, JM, JP, JPE, JPO (JCCC) opcodes
-            0xC2 | 0xCA | 0xD2 | 0xDA | 0xE2 | 0xEA | 0xF2 | 0xFA => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.jccc_opcode(opcode, low_byte, high_byte);
-                cycles = 10;
-            }
+        let (pc, sp) = (self.pc, self.sp);
+        let cycles = self.execute_opcode(opcode, port)?;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(pc, opcode, cycles, sp, self.sp, self.pc);
+        }
 
-            // CALL opcode
-            0xCD => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                self.call_opcode(low_byte, high_byte)?;
-                cycles = 17;
-            }
+        Ok(cycles)
+    }
 
-            // CC, CNC, CZ, CNZ, CM, CP, CPE, CPO (CCCC) opcodes
-            0xC4 | 0xCC | 0xD4 | 0xDC | 0xE4 | 0xEC | 0xF4 | 0xFC => {
-                let (low_byte, high_byte) = self.get_next_16bit()?;
-                cycles = self.cccc_opcode(opcode, low_byte, high_byte)?;
-            }
+    fn trace(&mut self, opcode: u8) -> Result<()> {
+        let Some(mut tracer) = self.tracer.take() else {
+            return Ok(());
+        };
 
-            // RET opcode
-            0xC9 => {
-                self.ret_opcode()?;
-                cycles = 10;
-            }
+        // Operands are peeked so that tracing has no side effects on the bus
+        let mut bytes = [opcode, 0, 0, 0];
+        for (offset, byte) in bytes.iter_mut().enumerate().skip(1) {
+            *byte = self
+                .bus
+                .peek(self.pc.wrapping_add(offset as u16))
+                .unwrap_or(0);
+        }
 
-            // RC, RNC, RZ, RNZ, RM, RP, RPE, RPO (RCCC) opcodes
-            0xC0 | 0xC8 | 0xD0 | 0xD8 | 0xE0 | 0xE8 | 0xF0 | 0xF8 => {
-                cycles = self.rccc_opcode(opcode)?;
-            }
+        let result = tracer.record(&self.state(), bytes);
+        self.tracer = Some(tracer);
 
-            // RST opcode
-            0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => {
-                self.rst_opcode(opcode)?;
-                cycles = 11;
-            }
+        result
+    }
 
-            // PCHL opcode
-            0xE9 => {
-                self.pchl();
-                self.pc += 1;
-                cycles = 5;
-            }
+    fn accepts_interrupt(&self) -> bool {
+        self.interrupts_enabled && !self.ei_pending
+    }
 
-            // PUSH opcode
-            0xC5 | 0xD5 | 0xE5 | 0xF5 => {
-                self.push_opcode(opcode)?;
-                self.pc += 1;
-                cycles = 11;
-            }
+    fn acknowledge_interrupt(&mut self, instruction: [u8; 3], port: &mut impl Port) -> Result<u32> {
+        // INTA resets the interrupt enable flip-flop and leaves the halt state
+        self.interrupts_enabled = false;
+        self.halted = false;
+        self.update_running();
+        self.inta = true;
+
+        // PC is not incremented while the instruction comes from the bus
+        self.bus_instruction = Some(instruction);
+        let sp = self.sp;
+        let cycles = self.execute_opcode(instruction[0], port);
+        self.bus_instruction = None;
+
+        if let (Ok(cycles), Some(profiler)) = (&cycles, &mut self.profiler) {
+            profiler.record_interrupt(Some(instruction[0]), *cycles, sp, self.sp, self.pc);
+        }
 
-            // POP opcode
-            0xC1 | 0xD1 | 0xE1 | 0xF1 => {
-                self.pop_opcode(opcode)?;
-                self.pc += 1;
-                cycles = 10;
-            }
+        cycles
+    }
 
-            // XTHL opcode
-            0xE3 => {
-                self.xthl_opcode()?;
-                self.pc += 1;
-                cycles = 18;
-            }
+    /// Calls the 8085 interrupt vector at `vector`, no INTA cycle is run
+    fn restart(&mut self, vector: u16) -> Result<u32> {
+        self.interrupts_enabled = false;
+        self.ei_pending = false;
+        self.halted = false;
+        self.update_running();
 
-            // SPHL opcode
-            0xF9 => {
-                self.sphl();
-                self.pc += 1;
-                cycles = 5;
-            }
+        let (pc, sp) = (self.pc, self.sp);
+        let (low_return, high_return) = word_to_bytes(pc);
+        self.push_16bit(low_return, high_return)?;
+        self.pc = vector;
 
-            // IN opcode
-            0xDB => {
-                let num = self.get_next_byte()?;
-                self.in_opcode(num, port);
-                self.pc += 2;
-                cycles = 10;
-            }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_interrupt(None, RESTART_CYCLES, sp, self.sp, self.pc);
+        }
 
-            // OUT opcode
-            0xD3 => {
-                let num = self.get_next_byte()?;
-                self.out_opcode(num, port);
-                self.pc += 2;
-                cycles = 10;
-            }
+        Ok(RESTART_CYCLES)
+    }
 
-            // EI opcode
-            0xFB => {
-                self.ei_opcode();
-                self.pc += 1;
-                cycles = 4;
-            }
+    fn execute_opcode<Pt: Port>(&mut self, opcode: u8, port: &mut Pt) -> Result<u32> {
+        // Borrowing the whole table lets it be promoted to a static
+        let tables = &Dispatch::<B, Pt>::TABLES;
+        let entry = &tables[self.table][opcode as usize];
 
-            // DI opcode
-            0xF3 => {
-                self.di_opcode();
-                self.pc += 1;
-                cycles = 4;
+        let data = match entry.length {
+            1 => 0,
+            2 => self.fetch_byte(1)? as u16,
+            _ => bytes_to_word(self.fetch_byte(1)?, self.fetch_byte(2)?),
+        };
+        let (pc, f) = (self.pc, self.f);
+        self.advance_pc(entry.length as u16);
+
+        match (entry.handler)(self, port, opcode, data) {
+            Ok(taken) => {
+                self.f = (f & !entry.flags) | (self.f & entry.flags);
+                Ok(if taken {
+                    entry.cycles_taken
+                } else {
+                    entry.cycles
+                } as u32)
+            }
+            Err(Error::UnknownOpcode(opcode))
+                if let UndocumentedOpcodes::Callback(callback) = self.undocumented =>
+            {
+                self.undocumented_callback(callback, opcode, pc)
+            }
+            // Leave PC on the instruction that failed
+            Err(error) => {
+                self.pc = pc;
+                Err(error)
             }
+        }
+    }
 
-            // Invalid opcodes
-            0x10 | 0x20 | 0x30 | 0x08 | 0x18 | 0x28 | 0x38 | 0xD9 | 0xCB | 0xDD | 0xED | 0xFD => {
-                return Err(Error::UnknownOpcode(opcode));
+    fn undocumented_callback(
+        &mut self,
+        callback: UndocumentedCallback,
+        opcode: u8,
+        pc: u16,
+    ) -> Result<u32> {
+        let mut state = self.state();
+        match callback(opcode, &mut state, &mut self.bus) {
+            Ok(cycles) => {
+                self.set_state(&state);
+                Ok(cycles)
+            }
+            Err(error) => {
+                self.pc = pc;
+                Err(error)
             }
         }
-
-        Ok(cycles)
     }
 
     // =====================================================================
     //                           HELPER FUNCTIONS
     // =====================================================================
 
-    fn get_source_reg(&mut self, opcode: u8) -> Result<(u8, bool)> {
-        let mut from_memory = false;
-        let source = match opcode & 0b111 {
-            0b111 => self.a,
-            0b000 => self.b,
-            0b001 => self.c,
-            0b010 => self.d,
-            0b011 => self.e,
-            0b100 => self.h,
-            0b101 => self.l,
-            0b110 => {
-                from_memory = true;
-                self.ram.read(self.get_hl())?
-            }
-            _ => panic!("Failed to parse register {:#b}", opcode & 0b111),
+    fn update_table(&mut self) {
+        self.table = match (self.variant, self.undocumented) {
+            (Variant::Intel8080, UndocumentedOpcodes::Alias) => 1,
+            (Variant::Intel8080, _) => 0,
+            (Variant::Intel8085, _) => 2,
+            (Variant::Intel8085Undocumented, _) => 3,
         };
-
-        Ok((source, from_memory))
     }
 
-    fn get_dest_reg(&mut self, opcode: u8) -> Result<(&mut u8, bool)> {
-        let mut from_memory = false;
+    fn update_running(&mut self) {
+        self.running =
+            self.rom_loaded && !self.halted && !(self.variant.is_8085() && self.pins.requesting());
+    }
+
+    /// Reads register `R` in opcode order B, C, D, E, H, L, M, A
+    fn get_reg<const R: u8>(&mut self) -> Result<u8> {
+        Ok(match R {
+            0 => self.b,
+            1 => self.c,
+            2 => self.d,
+            3 => self.e,
+            4 => self.h,
+            5 => self.l,
+            6 => self.read_data(self.get_hl())?,
+            _ => self.a,
+        })
+    }
+
+    fn set_reg<const R: u8>(&mut self, value: u8) -> Result<()> {
+        match R {
+            0 => self.b = value,
+            1 => self.c = value,
+            2 => self.d = value,
+            3 => self.e = value,
+            4 => self.h = value,
+            5 => self.l = value,
+            6 => self.write_data(self.get_hl(), value)?,
+            _ => self.a = value,
+        }
 
-        let destination: &mut u8 = match (opcode >> 3) & 0b111 {
-            0b111 => &mut self.a,
-            0b000 => &mut self.b,
-            0b001 => &mut self.c,
-            0b010 => &mut self.d,
-            0b011 => &mut self.e,
-            0b100 => &mut self.h,
-            0b101 => &mut self.l,
-            0b110 => {
-                from_memory = true;
-                self.ram.read_mut(self.get_hl())?
-            }
-            _ => panic!("Failed to parse register: {:#b}", (opcode >> 3) & 0b111),
-        };
-        Ok((destination, from_memory))
+        Ok(())
     }
 
-    fn set_reg_pair(&mut self, opcode: u8, low_byte: u8, high_byte: u8) {
-        match (opcode >> 4) & 0b11 {
-            0b00 => (self.b, self.c) = (high_byte, low_byte),
-            0b01 => (self.d, self.e) = (high_byte, low_byte),
-            0b10 => (self.h, self.l) = (high_byte, low_byte),
-            0b11 => self.sp = bytes_to_word(low_byte, high_byte),
-            _ => panic!("Failed to parse register pair: {:#b}", (opcode >> 4) & 0b11),
+    /// Reads register pair `RP` in opcode order BC, DE, HL, SP
+    fn get_pair<const RP: u8>(&self) -> u16 {
+        match RP {
+            0 => self.get_bc(),
+            1 => self.get_de(),
+            2 => self.get_hl(),
+            _ => self.sp,
         }
     }
 
-    fn get_reg_pair(&self, opcode: u8) -> u16 {
-        match (opcode >> 4) & 0b11 {
-            0b00 => self.get_bc(),
-            0b01 => self.get_de(),
-            0b10 => self.get_hl(),
-            0b11 => self.sp,
-            _ => panic!("Failed to parse register pair: {:#b}", (opcode >> 4) & 0b11),
+    fn set_pair<const RP: u8>(&mut self, value: u16) {
+        let (low_byte, high_byte) = word_to_bytes(value);
+
+        match RP {
+            0 => (self.b, self.c) = (high_byte, low_byte),
+            1 => (self.d, self.e) = (high_byte, low_byte),
+            2 => (self.h, self.l) = (high_byte, low_byte),
+            _ => self.sp = value,
         }
     }
 
-    fn get_conditional(&mut self, opcode: u8) -> bool {
-        match (opcode >> 3) & 0b111 {
-            0b000 => !self.flags.z,
-            0b001 => self.flags.z,
-            0b010 => !self.flags.cy,
-            0b011 => self.flags.cy,
-            0b100 => !self.flags.p,
-            0b101 => self.flags.p,
-            0b110 => !self.flags.s,
-            0b111 => self.flags.s,
-            _ => panic!("Failed to parse conditional: {:#b}", (opcode >> 3) & 0b111),
-        }
+    /// Evaluates condition `C` in opcode order NZ, Z, NC, C, PO, PE, P, M
+    fn condition<const C: u8>(&self) -> bool {
+        let flag = match C >> 1 {
+            0 => Z,
+            1 => CY,
+            2 => P,
+            _ => S,
+        };
+
+        (self.f & flag != 0) == (C & 1 != 0)
     }
 
     fn get_bc(&self) -> u16 {
@@ -586,573 +806,693 @@ impl Processor {
         bytes_to_word(self.l, self.h)
     }
 
-    fn get_next_byte(&self) -> Result<u8> {
-        self.ram.read(self.pc + 1)
+    fn fetch_byte(&mut self, offset: u16) -> Result<u8> {
+        match self.bus_instruction {
+            Some(bus) => Ok(bus[offset as usize]),
+            None => self.access(self.pc.wrapping_add(offset), Usage::Operand),
+        }
     }
 
-    fn get_next_16bit(&self) -> Result<(u8, u8)> {
-        let low_byte = self.ram.read(self.pc + 1)?;
-        let high_byte = self.ram.read(self.pc + 2)?;
-        Ok((low_byte, high_byte))
-    }
+    /// Reads memory and records it in the coverage map
+    fn access(&mut self, address: u16, usage: Usage) -> Result<u8> {
+        let value = self.bus.read(address)?;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark(address, usage);
+        }
 
-    fn set_flags_add(&mut self, result_16: u16, result_8: u8, prev_a: u8, b: u8) {
-        self.flags.s = result_8 & 0x80 != 0;
-        self.flags.z = result_8 == 0;
-        self.flags.p = bit_parity(result_8);
-        self.flags.cy = result_16 & 0xFF00 != 0;
-        self.flags.ac = auxiliary_add(prev_a, b);
+        Ok(value)
     }
 
-    fn set_flags_sub(&mut self, result: u8, a: u8, b: u8) {
-        self.flags.s = result & 0x80 != 0;
-        self.flags.z = result == 0;
-        self.flags.p = bit_parity(result);
-        self.flags.cy = a < b;
-        self.flags.ac = auxiliary_sub(a, b);
+    fn read_data(&mut self, address: u16) -> Result<u8> {
+        self.access(address, Usage::Read)
     }
 
-    fn set_flags_and(&mut self, operand: u8) {
-        self.flags.s = self.a & 0x80 != 0;
-        self.flags.z = self.a == 0;
-        self.flags.p = bit_parity(self.a);
-        self.flags.cy = false;
-        self.flags.ac = ((self.a >> 3) & 1) | ((operand >> 3) & 1) != 0;
+    fn write_data(&mut self, address: u16, value: u8) -> Result<()> {
+        self.bus.write(address, value)?;
+        if let Some(coverage) = &mut self.coverage {
+            coverage.mark(address, Usage::Write);
+        }
+
+        Ok(())
     }
 
-    fn set_flags_logical(&mut self, result: u8) {
-        self.flags.s = result & 0x80 != 0;
-        self.flags.z = result == 0;
-        self.flags.p = bit_parity(result);
-        self.flags.cy = false;
-        self.flags.ac = false;
+    fn advance_pc(&mut self, length: u16) {
+        if self.bus_instruction.is_none() {
+            self.pc = self.pc.wrapping_add(length);
+        }
     }
 
     fn push_16bit(&mut self, low_byte: u8, high_byte: u8) -> Result<()> {
-        self.ram.write(self.sp - 1, high_byte)?;
-        self.ram.write(self.sp - 2, low_byte)?;
-        self.sp -= 2;
+        self.write_data(self.sp.wrapping_sub(1), high_byte)?;
+        self.write_data(self.sp.wrapping_sub(2), low_byte)?;
+        self.sp = self.sp.wrapping_sub(2);
 
         Ok(())
     }
 
     fn pop_16bit(&mut self) -> Result<(u8, u8)> {
-        let low_byte = self.ram.read(self.sp)?;
-        let high_byte = self.ram.read(self.sp + 1)?;
-        self.sp += 2;
+        let low_byte = self.read_data(self.sp)?;
+        let high_byte = self.read_data(self.sp.wrapping_add(1))?;
+        self.sp = self.sp.wrapping_add(2);
 
         Ok((low_byte, high_byte))
     }
 
     fn flags_to_byte(&self) -> u8 {
-        let mut byte = 2;
+        self.f
+    }
 
-        byte |= (self.flags.s as u8) << 7;
-        byte |= (self.flags.z as u8) << 6;
-        byte |= (self.flags.ac as u8) << 4;
-        byte |= (self.flags.p as u8) << 2;
-        byte |= self.flags.cy as u8;
+    fn byte_to_flag(&mut self, flag_register: u8) {
+        self.f = match self.variant {
+            Variant::Intel8085Undocumented => flag_register & (ALL | V | K),
+            _ => (flag_register & ALL) | 0x02,
+        };
+    }
 
-        byte
+    /// Adds with carry in, setting every flag
+    fn add_flags(&mut self, value: u8, carry: bool) -> u8 {
+        let result = self.a as u16 + value as u16 + carry as u16;
+        let result_8 = result as u8;
+
+        self.f = SZP_FLAGS[result_8 as usize]
+            | ((self.a ^ value ^ result_8) & AC)
+            | overflow_flags(self.a, value, result_8)
+            | (result > 0xFF) as u8;
+        result_8
     }
 
-    fn byte_to_flag(&mut self, flag_register: u8) {
-        self.flags.s = flag_register & 0x80 != 0;
-        self.flags.z = flag_register & 0x40 != 0;
-        self.flags.ac = flag_register & 0x10 != 0;
-        self.flags.p = flag_register & 0x4 != 0;
-        self.flags.cy = flag_register & 0x1 != 0;
+    /// Subtracts `value` and the borrow from `left`, setting every flag. The
+    /// 8080 adds the complement, so AC is set when the low nibble does not
+    /// borrow.
+    fn sub_flags(&mut self, left: u8, value: u8, borrow: bool) -> u8 {
+        let result = left.wrapping_sub(value).wrapping_sub(borrow as u8);
+
+        self.f = SZP_FLAGS[result as usize]
+            | ((left ^ !value ^ result) & AC)
+            | overflow_flags(left, !value, result)
+            | ((left as u16) < value as u16 + borrow as u16) as u8;
+        result
     }
 
     // =====================================================================
     //                            OPCODE FUNCTIONS
     // =====================================================================
+    //
+    // Handlers run from the dispatch table after the operand has been
+    // fetched into `data` and PC moved past the instruction. Flags outside
+    // an opcode's table mask are restored afterwards, so handlers may set
+    // all of F.
 
-    fn mov_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, source_from_memory) = self.get_source_reg(opcode)?;
-
-        let (destination, dest_to_memory) = self.get_dest_reg(opcode)?;
-        *destination = source;
-
-        if source_from_memory || dest_to_memory {
-            Ok(7)
-        } else {
-            Ok(5)
-        }
+    pub(crate) fn nop_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        Ok(false)
     }
 
-    fn mvi_opcode(&mut self, opcode: u8, immediate: u8) -> Result<u32> {
-        let (register, to_memory) = self.get_dest_reg(opcode)?;
-        *register = immediate;
+    pub(crate) fn hlt_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.halted = true;
+        self.running = false;
 
-        if to_memory { Ok(10) } else { Ok(7) }
+        Ok(false)
     }
 
-    fn lxi_opcode(&mut self, opcode: u8, low_byte: u8, high_byte: u8) {
-        self.set_reg_pair(opcode, low_byte, high_byte);
+    pub(crate) fn unknown_opcode<Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        opcode: u8,
+        _: u16,
+    ) -> Result<bool> {
+        Err(Error::UnknownOpcode(opcode))
     }
 
-    fn lda_opcode(&mut self, low_byte: u8, high_byte: u8) -> Result<()> {
-        let address = bytes_to_word(low_byte, high_byte);
-        self.a = self.ram.read(address)?;
+    pub(crate) fn mov_opcode<const D: u8, const S: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let value = self.get_reg::<S>()?;
+        self.set_reg::<D>(value)?;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn sta_opcode(&mut self, low_byte: u8, high_byte: u8) -> Result<()> {
-        let address = bytes_to_word(low_byte, high_byte);
-        self.ram.write(address, self.a)?;
+    pub(crate) fn mvi_opcode<const D: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        data: u16,
+    ) -> Result<bool> {
+        self.set_reg::<D>(data as u8)?;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn lhld_opcode(&mut self, low_byte: u8, high_byte: u8) -> Result<()> {
-        let address = bytes_to_word(low_byte, high_byte);
-        self.l = self.ram.read(address)?;
-        self.h = self.ram.read(address + 1)?;
+    pub(crate) fn lxi_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        data: u16,
+    ) -> Result<bool> {
+        self.set_pair::<RP>(data);
 
-        Ok(())
+        Ok(false)
     }
 
-    fn shld_opcode(&mut self, low_byte: u8, high_byte: u8) -> Result<()> {
-        let address = bytes_to_word(low_byte, high_byte);
-        self.ram.write(address, self.l)?;
-        self.ram.write(address + 1, self.h)?;
+    pub(crate) fn lda_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, address: u16) -> Result<bool> {
+        self.a = self.read_data(address)?;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn ldax_opcode(&mut self, opcode: u8) -> Result<()> {
-        let address = self.get_reg_pair(opcode);
-        self.a = self.ram.read(address)?;
+    pub(crate) fn sta_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, address: u16) -> Result<bool> {
+        self.write_data(address, self.a)?;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn stax_opcode(&mut self, opcode: u8) -> Result<()> {
-        let address = self.get_reg_pair(opcode);
-        self.ram.write(address, self.a)?;
+    pub(crate) fn lhld_opcode<Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        address: u16,
+    ) -> Result<bool> {
+        self.l = self.read_data(address)?;
+        self.h = self.read_data(address.wrapping_add(1))?;
 
-        Ok(())
-    }
-
-    fn xchg_opcode(&mut self) {
-        let d_prev = self.d;
-        let e_prev = self.e;
-        self.d = self.h;
-        self.e = self.l;
-        self.h = d_prev;
-        self.l = e_prev;
+        Ok(false)
     }
 
-    fn add_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        let result = self.a as u16 + source as u16;
-        let prev_a = self.a;
-        self.a = (result & 0xFF) as u8;
+    pub(crate) fn shld_opcode<Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        address: u16,
+    ) -> Result<bool> {
+        self.write_data(address, self.l)?;
+        self.write_data(address.wrapping_add(1), self.h)?;
 
-        self.set_flags_add(result, self.a, prev_a, source);
-
-        // Cycles count depends on whether or not memory was accessed
-        if from_memory { Ok(7) } else { Ok(4) }
+        Ok(false)
     }
 
-    fn adi_opcode(&mut self, immediate: u8) -> Result<()> {
-        let result = self.a as u16 + immediate as u16;
-        let prev_a = self.a;
-        self.a = (result & 0xFF) as u8;
-
-        self.set_flags_add(result, self.a, prev_a, immediate);
+    pub(crate) fn ldax_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        self.a = self.read_data(self.get_pair::<RP>())?;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn adc_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        let result = self.a as u16 + source as u16 + self.flags.cy as u16;
-        let prev_a = self.a;
-        self.a = (result & 0xFF) as u8;
-
-        self.set_flags_add(
-            result,
-            self.a,
-            prev_a,
-            max(source + self.flags.cy as u8, 255),
-        );
+    pub(crate) fn stax_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        self.write_data(self.get_pair::<RP>(), self.a)?;
 
-        // Cycles count depends on whether or not memory was accessed
-        if from_memory { Ok(7) } else { Ok(4) }
+        Ok(false)
     }
 
-    fn aci_opcode(&mut self, immediate: u8) -> Result<()> {
-        let result = self.a as u16 + immediate as u16 + self.flags.cy as u16;
-        let prev_a = self.a;
-        self.a = (result & 0xFF) as u8;
-
-        self.set_flags_add(
-            result,
-            self.a,
-            prev_a,
-            max(immediate + self.flags.cy as u8, 255),
-        );
+    pub(crate) fn xchg_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        (self.d, self.h) = (self.h, self.d);
+        (self.e, self.l) = (self.l, self.e);
 
-        Ok(())
+        Ok(false)
     }
 
-    fn sub_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        let prev_a = self.a;
-        self.a = self.a.wrapping_sub(source);
-
-        self.set_flags_sub(self.a, prev_a, source);
+    /// ADD, ADC, SUB, SBB, ANA, XRA, ORA or CMP, selected by `OP`
+    fn alu<const OP: u8>(&mut self, value: u8) {
+        let carry = self.f & CY != 0;
 
-        // Cycles count depends on whether or not memory was accessed
-        if from_memory { Ok(7) } else { Ok(4) }
+        match OP {
+            0 => self.a = self.add_flags(value, false),
+            1 => self.a = self.add_flags(value, carry),
+            2 => self.a = self.sub_flags(self.a, value, false),
+            3 => self.a = self.sub_flags(self.a, value, carry),
+            // The 8080 sets AC from bit 3 of the operands, not of the result.
+            // The 8085 always sets it.
+            4 => {
+                let auxiliary = if self.variant.is_8085() {
+                    AC
+                } else {
+                    ((self.a | value) & 0x08) << 1
+                };
+                self.a &= value;
+                self.f = SZP_FLAGS[self.a as usize] | auxiliary;
+            }
+            5 => {
+                self.a ^= value;
+                self.f = SZP_FLAGS[self.a as usize];
+            }
+            6 => {
+                self.a |= value;
+                self.f = SZP_FLAGS[self.a as usize];
+            }
+            _ => {
+                self.sub_flags(self.a, value, false);
+            }
+        }
     }
 
-    fn sui_opcode(&mut self, immediate: u8) -> Result<()> {
-        let prev_a = self.a;
-        self.a = self.a.wrapping_sub(immediate);
+    pub(crate) fn alu_opcode<const OP: u8, const S: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let value = self.get_reg::<S>()?;
+        self.alu::<OP>(value);
 
-        self.set_flags_sub(self.a, prev_a, immediate);
-
-        Ok(())
+        Ok(false)
     }
 
-    fn sbb_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        let prev_a = self.a;
-        self.a = self
-            .a
-            .wrapping_sub(source)
-            .wrapping_sub(self.flags.cy as u8);
-
-        self.set_flags_sub(self.a, prev_a, max(source + self.flags.cy as u8, 255));
+    pub(crate) fn alu_immediate_opcode<const OP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        data: u16,
+    ) -> Result<bool> {
+        self.alu::<OP>(data as u8);
 
-        if from_memory { Ok(7) } else { Ok(4) }
+        Ok(false)
     }
 
-    fn sbi_opcode(&mut self, immediate: u8) -> Result<()> {
-        let prev_a = self.a;
-        self.a = self
-            .a
-            .wrapping_sub(immediate)
-            .wrapping_sub(self.flags.cy as u8);
+    pub(crate) fn inr_opcode<const R: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let result = self.get_reg::<R>()?.wrapping_add(1);
+        self.set_reg::<R>(result)?;
 
-        self.set_flags_sub(self.a, prev_a, max(immediate + self.flags.cy as u8, 255));
+        // The low nibble carries when it wraps to 0
+        self.f = SZP_FLAGS[result as usize]
+            | if result & 0xF == 0 { AC } else { 0 }
+            | overflow_flags(result.wrapping_sub(1), 0x01, result);
 
-        Ok(())
+        Ok(false)
     }
 
-    fn inr_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (register, to_memory) = self.get_dest_reg(opcode)?;
-
-        let prev_val = *register;
-        let result = register.wrapping_add(1);
-        *register = result;
+    pub(crate) fn dcr_opcode<const R: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let result = self.get_reg::<R>()?.wrapping_sub(1);
+        self.set_reg::<R>(result)?;
 
-        self.flags.s = result & 0x80 != 0;
-        self.flags.z = result == 0;
-        self.flags.p = bit_parity(result);
-        self.flags.ac = auxiliary_add(prev_val, 1);
+        // AC is clear only when the low nibble borrows
+        self.f = SZP_FLAGS[result as usize]
+            | if result & 0xF != 0xF { AC } else { 0 }
+            | overflow_flags(result.wrapping_add(1), !0x01, result);
 
-        if to_memory { Ok(10) } else { Ok(5) }
+        Ok(false)
     }
 
-    fn dcr_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (register, to_memory) = self.get_dest_reg(opcode)?;
+    pub(crate) fn inx_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let result = self.get_pair::<RP>().wrapping_add(1);
+        self.set_pair::<RP>(result);
 
-        let prev_val = *register;
-        let result = register.wrapping_sub(1);
-        *register = result;
+        // K is set when the pair wraps around
+        self.f = if result == 0 { K } else { 0 };
 
-        self.flags.s = result & 0x80 != 0;
-        self.flags.z = result == 0;
-        self.flags.p = bit_parity(result);
-        self.flags.ac = auxiliary_sub(prev_val, 1);
-
-        if to_memory { Ok(10) } else { Ok(5) }
+        Ok(false)
     }
 
-    fn inx_opcode(&mut self, opcode: u8) {
-        let register = self.get_reg_pair(opcode);
-        let result = register.wrapping_add(1);
-
-        let low_byte = result as u8;
-        let high_byte = (result >> 8) as u8;
-        self.set_reg_pair(opcode, low_byte, high_byte);
-    }
+    pub(crate) fn dcx_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let result = self.get_pair::<RP>().wrapping_sub(1);
+        self.set_pair::<RP>(result);
 
-    fn dcx_opcode(&mut self, opcode: u8) {
-        let register = self.get_reg_pair(opcode);
-        let result = register.wrapping_sub(1);
+        self.f = if result == 0xFFFF { K } else { 0 };
 
-        let low_byte = result as u8;
-        let high_byte = (result >> 8) as u8;
-        self.set_reg_pair(opcode, low_byte, high_byte);
+        Ok(false)
     }
 
-    fn dad_opcode(&mut self, opcode: u8) {
-        let source = self.get_reg_pair(opcode) as u32;
-        let destination = self.get_hl() as u32;
+    pub(crate) fn dad_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let (result, carry) = self.get_hl().overflowing_add(self.get_pair::<RP>());
+        (self.l, self.h) = word_to_bytes(result);
+        self.f = carry as u8;
 
-        let result = destination.wrapping_add(source);
-        (self.l, self.h) = word_to_bytes(result as u16);
-
-        self.flags.cy = result > 0xFFFF;
+        Ok(false)
     }
 
-    fn daa_opcode(&mut self) {
+    pub(crate) fn daa_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
         let mut adjustment = 0;
+        let mut carry = self.f & CY != 0;
 
-        if (self.a & 0xF) > 9 || self.flags.ac {
+        if (self.a & 0xF) > 9 || self.f & AC != 0 {
             adjustment |= 0x6;
         }
-        if (self.a >> 4) > 9 || self.flags.cy {
+        // The high nibble is also corrected when the low correction carries into it
+        if (self.a >> 4) > 9 || ((self.a >> 4) == 9 && (self.a & 0xF) > 9) || carry {
             adjustment |= 0x60;
+            carry = true;
         }
 
-        let result = self.a.wrapping_add(adjustment);
-        self.set_flags_add(self.a as u16 + adjustment as u16, result, self.a, adjustment);
-        self.a = result;
-    }
-
-    fn ana_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        self.a &= source;
-
-        self.set_flags_and(source);
+        self.a = self.add_flags(adjustment, false);
+        self.f = (self.f & !CY) | carry as u8;
 
-        if from_memory { Ok(7) } else { Ok(4) }
+        Ok(false)
     }
 
-    fn ani_opcode(&mut self, immediate: u8) {
-        self.a &= immediate;
-        self.set_flags_and(immediate);
-    }
-
-    fn ora_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        self.a |= source;
-
-        self.set_flags_logical(self.a);
-
-        if from_memory { Ok(7) } else { Ok(4) }
-    }
+    pub(crate) fn rlc_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.a = self.a.rotate_left(1);
+        self.f = self.a & 1;
 
-    fn ori_opcode(&mut self, immediate: u8) {
-        self.a |= immediate;
-        self.set_flags_logical(self.a);
+        Ok(false)
     }
 
-    fn xra_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        self.a ^= source;
-
-        self.set_flags_logical(self.a);
-
-        if from_memory { Ok(7) } else { Ok(4) }
-    }
+    pub(crate) fn rrc_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.f = self.a & 1;
+        self.a = self.a.rotate_right(1);
 
-    fn xri_opcode(&mut self, immediate: u8) {
-        self.a ^= immediate;
-        self.set_flags_logical(self.a);
+        Ok(false)
     }
 
-    fn cmp_opcode(&mut self, opcode: u8) -> Result<u32> {
-        let (source, from_memory) = self.get_source_reg(opcode)?;
-        let result = self.a.wrapping_sub(source);
+    pub(crate) fn ral_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        let prev_cy = self.f & CY;
+        self.f = self.a >> 7;
+        self.a = (self.a << 1) | prev_cy;
 
-        self.set_flags_sub(result, self.a, source);
-
-        if from_memory { Ok(7) } else { Ok(4) }
+        Ok(false)
     }
 
-    fn cpi_opcode(&mut self, immediate: u8) {
-        let result = self.a.wrapping_sub(immediate);
-        self.set_flags_sub(result, self.a, immediate);
-    }
+    pub(crate) fn rar_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        let prev_cy = self.f & CY;
+        self.f = self.a & 1;
+        self.a = (self.a >> 1) | (prev_cy << 7);
 
-    fn rlc_opcode(&mut self) {
-        self.flags.cy = self.a & 0x80 != 0;
-        self.a = self.a.rotate_left(1);
+        Ok(false)
     }
 
-    fn rrc_opcode(&mut self) {
-        self.flags.cy = self.a & 1 != 0;
-        self.a = self.a.rotate_right(1);
-    }
+    pub(crate) fn cma_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.a = !self.a;
 
-    fn ral_opcode(&mut self) {
-        let prev_cy = self.flags.cy as u8;
-        self.flags.cy = self.a & 0x80 != 0;
-        self.a <<= 1;
-        self.a |= prev_cy;
+        Ok(false)
     }
 
-    fn rar_opcode(&mut self) {
-        let prev_cy = self.flags.cy as u8;
-        self.flags.cy = self.a & 1 != 0;
-        self.a >>= 1;
-        self.a |= prev_cy << 7;
-    }
+    pub(crate) fn cmc_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.f ^= CY;
 
-    fn cma_opcode(&mut self) {
-        self.a = !self.a;
+        Ok(false)
     }
 
-    fn cmc_opcode(&mut self) {
-        self.flags.cy = !self.flags.cy;
-    }
+    pub(crate) fn stc_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.f |= CY;
 
-    fn stc_opcode(&mut self) {
-        self.flags.cy = true;
+        Ok(false)
     }
 
-    fn jmp_opcode(&mut self, low_byte: u8, high_byte: u8) {
-        let address = bytes_to_word(low_byte, high_byte);
+    pub(crate) fn jmp_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, address: u16) -> Result<bool> {
         self.pc = address;
+
+        Ok(false)
     }
 
-    fn jccc_opcode(&mut self, opcode: u8, low_byte: u8, high_byte: u8) {
-        if self.get_conditional(opcode) {
-            let address = bytes_to_word(low_byte, high_byte);
+    pub(crate) fn jccc_opcode<const C: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        address: u16,
+    ) -> Result<bool> {
+        let taken = self.condition::<C>();
+        if taken {
             self.pc = address;
-        } else {
-            self.pc += 3;
         }
+
+        Ok(taken)
     }
 
-    fn call_opcode(&mut self, low_byte: u8, high_byte: u8) -> Result<()> {
-        self.pc += 3;
+    pub(crate) fn call_opcode<Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        address: u16,
+    ) -> Result<bool> {
         let (low_return, high_return) = word_to_bytes(self.pc);
         self.push_16bit(low_return, high_return)?;
-
-        let address = bytes_to_word(low_byte, high_byte);
         self.pc = address;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn cccc_opcode(&mut self, opcode: u8, low_byte: u8, high_byte: u8) -> Result<u32> {
-        if self.get_conditional(opcode) {
-            self.call_opcode(low_byte, high_byte)?;
-
-            Ok(17)
-        } else {
-            self.pc += 3;
-
-            Ok(11)
+    pub(crate) fn cccc_opcode<const C: u8, Pt: Port>(
+        &mut self,
+        port: &mut Pt,
+        opcode: u8,
+        address: u16,
+    ) -> Result<bool> {
+        if !self.condition::<C>() {
+            return Ok(false);
         }
+        self.call_opcode(port, opcode, address)?;
+
+        Ok(true)
     }
 
-    fn ret_opcode(&mut self) -> Result<()> {
+    pub(crate) fn ret_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
         let (low_byte, high_byte) = self.pop_16bit()?;
         self.pc = bytes_to_word(low_byte, high_byte);
 
-        Ok(())
+        Ok(false)
     }
 
-    fn rccc_opcode(&mut self, opcode: u8) -> Result<u32> {
-        if self.get_conditional(opcode) {
-            self.ret_opcode()?;
-
-            Ok(11)
-        } else {
-            self.pc += 1;
-
-            Ok(5)
+    pub(crate) fn rccc_opcode<const C: u8, Pt: Port>(
+        &mut self,
+        port: &mut Pt,
+        opcode: u8,
+        data: u16,
+    ) -> Result<bool> {
+        if !self.condition::<C>() {
+            return Ok(false);
         }
-    }
-
-    fn rst_opcode(&mut self, opcode: u8) -> Result<()> {
-        let (low_byte, high_byte) = word_to_bytes(self.pc);
-        self.push_16bit(low_byte, high_byte)?;
+        self.ret_opcode(port, opcode, data)?;
 
-        let address = (opcode & 0b111000) as u16;
-        self.pc = address;
+        Ok(true)
+    }
 
-        Ok(())
+    pub(crate) fn rst_opcode<const N: u8, Pt: Port>(
+        &mut self,
+        port: &mut Pt,
+        opcode: u8,
+        _: u16,
+    ) -> Result<bool> {
+        self.call_opcode(port, opcode, N as u16 * 8)
     }
 
-    fn pchl(&mut self) {
-        let address = self.get_hl();
-        self.pc = address;
+    pub(crate) fn pchl_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.pc = self.get_hl();
+
+        Ok(false)
     }
 
-    fn push_opcode(&mut self, opcode: u8) -> Result<()> {
-        let (high_byte, low_byte) = match (opcode >> 4) & 0b11 {
-            0b00 => (self.b, self.c),
-            0b01 => (self.d, self.e),
-            0b10 => (self.h, self.l),
-            0b11 => {
-                let flag_register = self.flags_to_byte();
-                (flag_register, self.a)
-            }
-            _ => panic!("Failed to register pair from opcode: {:#x}", opcode),
+    /// Pushes BC, DE, HL or PSW
+    pub(crate) fn push_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
+        let (high_byte, low_byte) = match RP {
+            0 => (self.b, self.c),
+            1 => (self.d, self.e),
+            2 => (self.h, self.l),
+            _ => (self.a, self.flags_to_byte()),
         };
-
         self.push_16bit(low_byte, high_byte)?;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn pop_opcode(&mut self, opcode: u8) -> Result<()> {
+    /// Pops BC, DE, HL or PSW
+    pub(crate) fn pop_opcode<const RP: u8, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        _: u16,
+    ) -> Result<bool> {
         let (low_byte, high_byte) = self.pop_16bit()?;
 
-        match (opcode >> 4) & 0b11 {
-            0b00 => {
-                self.b = high_byte;
-                self.c = low_byte;
-            }
-            0b01 => {
-                self.d = high_byte;
-                self.e = low_byte;
-            }
-            0b10 => {
-                self.h = high_byte;
-                self.l = low_byte;
+        match RP {
+            0 => (self.b, self.c) = (high_byte, low_byte),
+            1 => (self.d, self.e) = (high_byte, low_byte),
+            2 => (self.h, self.l) = (high_byte, low_byte),
+            _ => {
+                self.a = high_byte;
+                self.byte_to_flag(low_byte);
             }
-            0b11 => {
-                self.byte_to_flag(high_byte);
-                self.a = low_byte;
-            }
-            _ => panic!("Failed to register pair from opcode: {:#x}", opcode),
         }
 
-        Ok(())
+        Ok(false)
     }
 
-    fn xthl_opcode(&mut self) -> Result<()> {
+    pub(crate) fn xthl_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
         let low_byte = self.l;
         let high_byte = self.h;
 
-        self.l = self.ram.read(self.sp)?;
-        self.h = self.ram.read(self.sp + 1)?;
+        self.l = self.read_data(self.sp)?;
+        self.h = self.read_data(self.sp.wrapping_add(1))?;
 
-        self.ram.write(self.sp, low_byte)?;
-        self.ram.write(self.sp + 1, high_byte)?;
+        self.write_data(self.sp, low_byte)?;
+        self.write_data(self.sp.wrapping_add(1), high_byte)?;
 
-        Ok(())
+        Ok(false)
     }
 
-    fn sphl(&mut self) {
+    pub(crate) fn sphl_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
         self.sp = self.get_hl();
+
+        Ok(false)
     }
 
-    fn in_opcode(&mut self, num: u8, port: &mut impl Port) {
-        self.a = port.read_in(num);
+    pub(crate) fn in_opcode<Pt: Port>(&mut self, port: &mut Pt, _: u8, num: u16) -> Result<bool> {
+        self.a = port.read_in(num as u8);
+
+        Ok(false)
     }
 
-    fn out_opcode(&self, num: u8, port: &mut impl Port) {
-        port.write_out(num, self.a);
+    pub(crate) fn out_opcode<Pt: Port>(&mut self, port: &mut Pt, _: u8, num: u16) -> Result<bool> {
+        port.write_out(num as u8, self.a);
+
+        Ok(false)
     }
 
-    fn ei_opcode(&mut self) {
+    pub(crate) fn ei_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
         self.interrupts_enabled = true;
+        self.ei_pending = true;
+
+        Ok(false)
     }
 
-    fn di_opcode(&mut self) {
+    pub(crate) fn di_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
         self.interrupts_enabled = false;
+        self.ei_pending = false;
+
+        Ok(false)
+    }
+
+    // =====================================================================
+    //                            8085 OPCODES
+    // =====================================================================
+
+    pub(crate) fn rim_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.a = self.pins.rim(self.interrupts_enabled);
+
+        Ok(false)
+    }
+
+    pub(crate) fn sim_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.pins.sim(self.a);
+        self.update_running();
+
+        Ok(false)
+    }
+
+    /// HL = HL - BC, computed as SUB L,C then SBB H,B with Z set from all
+    /// 16 bits
+    pub(crate) fn dsub_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.l = self.sub_flags(self.l, self.c, false);
+        let borrow = self.f & CY != 0;
+        self.h = self.sub_flags(self.h, self.b, borrow);
+        if self.l != 0 {
+            self.f &= !Z;
+        }
+
+        Ok(false)
+    }
+
+    /// Shifts HL right, keeping the sign bit
+    pub(crate) fn arhl_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        self.f = self.l & 1;
+        (self.l, self.h) = word_to_bytes(((self.get_hl() as i16) >> 1) as u16);
+
+        Ok(false)
+    }
+
+    /// Rotates DE left through the carry, V is set when the sign changes
+    pub(crate) fn rdel_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        let de = self.get_de();
+        let result = (de << 1) | (self.f & CY) as u16;
+        (self.e, self.d) = word_to_bytes(result);
+        self.f = (de >> 15) as u8 | if (de ^ result) & 0x8000 != 0 { V } else { 0 };
+
+        Ok(false)
+    }
+
+    pub(crate) fn ldhi_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, data: u16) -> Result<bool> {
+        (self.e, self.d) = word_to_bytes(self.get_hl().wrapping_add(data));
+
+        Ok(false)
+    }
+
+    pub(crate) fn ldsi_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, data: u16) -> Result<bool> {
+        (self.e, self.d) = word_to_bytes(self.sp.wrapping_add(data));
+
+        Ok(false)
+    }
+
+    /// Restarts at 0x40 if V is set
+    pub(crate) fn rstv_opcode<Pt: Port>(
+        &mut self,
+        port: &mut Pt,
+        opcode: u8,
+        _: u16,
+    ) -> Result<bool> {
+        if self.f & V == 0 {
+            return Ok(false);
+        }
+        self.call_opcode(port, opcode, 0x40)?;
+
+        Ok(true)
+    }
+
+    pub(crate) fn shlx_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        let address = self.get_de();
+        self.write_data(address, self.l)?;
+        self.write_data(address.wrapping_add(1), self.h)?;
+
+        Ok(false)
+    }
+
+    pub(crate) fn lhlx_opcode<Pt: Port>(&mut self, _: &mut Pt, _: u8, _: u16) -> Result<bool> {
+        let address = self.get_de();
+        self.l = self.read_data(address)?;
+        self.h = self.read_data(address.wrapping_add(1))?;
+
+        Ok(false)
+    }
+
+    /// JK when `SET` is true, JNK otherwise
+    pub(crate) fn jk_opcode<const SET: bool, Pt: Port>(
+        &mut self,
+        _: &mut Pt,
+        _: u8,
+        address: u16,
+    ) -> Result<bool> {
+        let taken = (self.f & K != 0) == SET;
+        if taken {
+            self.pc = address;
+        }
+
+        Ok(taken)
     }
 }
@@ -0,0 +1,337 @@
+use std::{collections::BTreeMap, fmt::Write as _, io::Write};
+
+use crate::{
+    disassembler::{Flow, variant_opcode_info},
+    errors::Result,
+    i8085::Variant,
+};
+
+/// Executions of an address or opcode and the cycles they took
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counter {
+    pub count: u64,
+    pub cycles: u64,
+}
+
+/// Cycles attributed to a subroutine entered through CALL, RST or an
+/// interrupt
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Subroutine {
+    pub calls: u64,
+    /// Cycles of the subroutine's own instructions
+    pub self_cycles: u64,
+    /// Cycles from entry to return, including the subroutines it called.
+    /// Only counts calls that have returned.
+    pub total_cycles: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    entry: u16,
+    sp: u16,
+    start_cycles: u64,
+}
+
+/// Counts executions and cycles per address and per opcode, and attributes
+/// cycles to subroutines by following CALL, RST, interrupts and returns.
+/// Install it with `Processor::set_profiler`.
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    addresses: Vec<Counter>,
+    opcodes: Vec<Counter>,
+    // Code outside any tracked subroutine is keyed by None
+    subroutines: BTreeMap<Option<u16>, Subroutine>,
+    frames: Vec<Frame>,
+    instructions: u64,
+    cycles: u64,
+    // Processor model, for opcode names and flow
+    variant: Variant,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            addresses: vec![Counter::default(); 0x10000],
+            opcodes: vec![Counter::default(); 0x100],
+            subroutines: BTreeMap::new(),
+            frames: Vec::new(),
+            instructions: 0,
+            cycles: 0,
+            variant: Variant::default(),
+        }
+    }
+
+    /// Instructions executed, not counting interrupt acknowledges
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// All cycles spent, including interrupt acknowledges and halted idling
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn address(&self, address: u16) -> Counter {
+        self.addresses[address as usize]
+    }
+
+    /// Counts of an opcode, including instructions supplied by interrupts
+    pub fn opcode(&self, opcode: u8) -> Counter {
+        self.opcodes[opcode as usize]
+    }
+
+    /// Stats of the subroutine at `entry`, None for code outside any
+    /// subroutine
+    pub fn subroutine(&self, entry: Option<u16>) -> Subroutine {
+        self.subroutines.get(&entry).copied().unwrap_or_default()
+    }
+
+    /// Executed addresses, most cycles first
+    pub fn hot_addresses(&self) -> Vec<(u16, Counter)> {
+        hottest(&self.addresses)
+            .map(|(address, counter)| (address as u16, counter))
+            .collect()
+    }
+
+    /// Executed opcodes, most cycles first
+    pub fn hot_opcodes(&self) -> Vec<(u8, Counter)> {
+        hottest(&self.opcodes)
+            .map(|(opcode, counter)| (opcode as u8, counter))
+            .collect()
+    }
+
+    /// Subroutines by entry address, most inclusive cycles first
+    pub fn hot_subroutines(&self) -> Vec<(Option<u16>, Subroutine)> {
+        let mut subroutines: Vec<_> = self
+            .subroutines
+            .iter()
+            .map(|(&entry, &subroutine)| (entry, subroutine))
+            .collect();
+        subroutines.sort_by_key(|(_, subroutine)| {
+            std::cmp::Reverse((subroutine.total_cycles, subroutine.self_cycles))
+        });
+
+        subroutines
+    }
+
+    /// Human readable report listing at most `limit` rows per section
+    pub fn report(&self, limit: usize) -> String {
+        let mut report = format!(
+            "{} instructions, {} cycles\n\nSubroutines\n{:<10}{:>10}{:>14}{:>8}{:>14}{:>8}\n",
+            self.instructions, self.cycles, "entry", "calls", "self", "%", "total", "%"
+        );
+        for (entry, subroutine) in self.hot_subroutines().into_iter().take(limit) {
+            let _ = writeln!(
+                report,
+                "{:<10}{:>10}{:>14}{:>8.2}{:>14}{:>8.2}",
+                entry_name(entry),
+                subroutine.calls,
+                subroutine.self_cycles,
+                self.percent(subroutine.self_cycles),
+                subroutine.total_cycles,
+                self.percent(subroutine.total_cycles),
+            );
+        }
+
+        let _ = write!(
+            report,
+            "\nAddresses\n{:<10}{:>10}{:>14}{:>8}\n",
+            "address", "count", "cycles", "%"
+        );
+        for (address, counter) in self.hot_addresses().into_iter().take(limit) {
+            let _ = writeln!(
+                report,
+                "{:<10}{:>10}{:>14}{:>8.2}",
+                format!("{address:04X}"),
+                counter.count,
+                counter.cycles,
+                self.percent(counter.cycles),
+            );
+        }
+
+        let _ = write!(
+            report,
+            "\nOpcodes\n{:<10}{:>10}{:>14}{:>8}\n",
+            "opcode", "count", "cycles", "%"
+        );
+        for (opcode, counter) in self.hot_opcodes().into_iter().take(limit) {
+            let _ = writeln!(
+                report,
+                "{:<10}{:>10}{:>14}{:>8.2}",
+                format!(
+                    "{opcode:02X} {}",
+                    variant_opcode_info(self.variant, opcode).mnemonic
+                ),
+                counter.count,
+                counter.cycles,
+                self.percent(counter.cycles),
+            );
+        }
+
+        report
+    }
+
+    /// Writes every row as `kind,key,name,count,cycles,total_cycles,percent`.
+    /// Subroutines come first, then addresses and opcodes, each sorted by
+    /// cycles. Count is the number of calls for subroutines and `cycles`
+    /// their self cycles.
+    pub fn write_csv(&self, mut writer: impl Write) -> Result<()> {
+        writeln!(writer, "kind,key,name,count,cycles,total_cycles,percent")?;
+
+        for (entry, subroutine) in self.hot_subroutines() {
+            writeln!(
+                writer,
+                "subroutine,{},,{},{},{},{:.4}",
+                entry_name(entry),
+                subroutine.calls,
+                subroutine.self_cycles,
+                subroutine.total_cycles,
+                self.percent(subroutine.total_cycles),
+            )?;
+        }
+        for (address, counter) in self.hot_addresses() {
+            writeln!(
+                writer,
+                "address,{address:04X},,{},{},,{:.4}",
+                counter.count,
+                counter.cycles,
+                self.percent(counter.cycles),
+            )?;
+        }
+        for (opcode, counter) in self.hot_opcodes() {
+            writeln!(
+                writer,
+                "opcode,{opcode:02X},{},{},{},,{:.4}",
+                variant_opcode_info(self.variant, opcode).mnemonic,
+                counter.count,
+                counter.cycles,
+                self.percent(counter.cycles),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the processor model whose opcodes are profiled
+    pub(crate) fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Records an instruction fetched from `pc`. `previous_sp` is SP before
+    /// it ran, `sp` and `next_pc` are SP and PC after.
+    pub(crate) fn record(
+        &mut self,
+        pc: u16,
+        opcode: u8,
+        cycles: u32,
+        previous_sp: u16,
+        sp: u16,
+        next_pc: u16,
+    ) {
+        self.instructions += 1;
+        add(&mut self.addresses[pc as usize], cycles);
+
+        let flow = variant_opcode_info(self.variant, opcode).flow;
+        let call = matches!(flow, Flow::Call | Flow::Restart);
+        self.attribute(Some(opcode), cycles, call, previous_sp, sp, next_pc);
+    }
+
+    /// Records an interrupt acknowledge, with the instruction supplied on
+    /// the bus if there was one
+    pub(crate) fn record_interrupt(
+        &mut self,
+        opcode: Option<u8>,
+        cycles: u32,
+        previous_sp: u16,
+        sp: u16,
+        next_pc: u16,
+    ) {
+        self.attribute(opcode, cycles, true, previous_sp, sp, next_pc);
+    }
+
+    /// Records cycles spent halted, they go to the current subroutine
+    pub(crate) fn record_idle(&mut self, cycles: u32) {
+        self.cycles += cycles as u64;
+        self.current_subroutine().self_cycles += cycles as u64;
+    }
+
+    fn attribute(
+        &mut self,
+        opcode: Option<u8>,
+        cycles: u32,
+        call: bool,
+        previous_sp: u16,
+        sp: u16,
+        next_pc: u16,
+    ) {
+        if let Some(opcode) = opcode {
+            add(&mut self.opcodes[opcode as usize], cycles);
+        }
+        self.cycles += cycles as u64;
+
+        // A CALL's own cycles belong to the caller and a RET's to the callee
+        self.current_subroutine().self_cycles += cycles as u64;
+
+        // Returns and stack adjustments end the frames they popped, SP is
+        // compared as an offset so that stacks at 0x0000 wrap
+        while let Some(frame) = self.frames.last().copied()
+            && sp.wrapping_sub(frame.sp) as i16 > 0
+        {
+            self.frames.pop();
+            self.subroutines
+                .entry(Some(frame.entry))
+                .or_default()
+                .total_cycles += self.cycles - frame.start_cycles;
+        }
+
+        if call && sp == previous_sp.wrapping_sub(2) {
+            self.frames.push(Frame {
+                entry: next_pc,
+                sp,
+                start_cycles: self.cycles,
+            });
+            self.subroutines.entry(Some(next_pc)).or_default().calls += 1;
+        }
+    }
+
+    fn current_subroutine(&mut self) -> &mut Subroutine {
+        let entry = self.frames.last().map(|frame| frame.entry);
+        self.subroutines.entry(entry).or_default()
+    }
+
+    fn percent(&self, cycles: u64) -> f64 {
+        if self.cycles == 0 {
+            return 0.0;
+        }
+
+        cycles as f64 * 100.0 / self.cycles as f64
+    }
+}
+
+fn add(counter: &mut Counter, cycles: u32) {
+    counter.count += 1;
+    counter.cycles += cycles as u64;
+}
+
+/// Indices of the used counters, most cycles first
+fn hottest(counters: &[Counter]) -> impl Iterator<Item = (usize, Counter)> {
+    let mut used: Vec<_> = counters
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, counter)| counter.count > 0)
+        .collect();
+    used.sort_by_key(|(_, counter)| std::cmp::Reverse(counter.cycles));
+
+    used.into_iter()
+}
+
+fn entry_name(entry: Option<u16>) -> String {
+    entry.map_or_else(|| "top".to_string(), |entry| format!("{entry:04X}"))
+}
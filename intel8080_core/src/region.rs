@@ -0,0 +1,235 @@
+use std::ops::RangeInclusive;
+
+use crate::errors::{Error, Result};
+
+const ADDRESSES: usize = 0x10000;
+
+/// How a region answers the bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegionKind {
+    Rom,
+    Ram,
+    /// Repeats other regions at `offset + (address & mask)`
+    Mirror,
+    /// Forwarded to the device installed with `Memory::set_mmio`, which
+    /// sees `offset + (address & mask)` as the address
+    Mmio,
+    /// Named hole in the address space, accesses are faults
+    Unmapped,
+}
+
+/// Named address range. ROM and RAM regions are stored at
+/// `offset + (address & mask)` in the backing memory, the mask drops address
+/// lines a board leaves unconnected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+    pub range: RangeInclusive<u16>,
+    pub kind: RegionKind,
+    pub mask: u16,
+    pub offset: usize,
+}
+
+impl Region {
+    /// Region with every address line connected and no offset, so ROM and
+    /// RAM are stored at their own address
+    pub fn new(name: impl Into<String>, range: RangeInclusive<u16>, kind: RegionKind) -> Self {
+        Self {
+            name: name.into(),
+            range,
+            kind,
+            mask: 0xFFFF,
+            offset: 0,
+        }
+    }
+
+    pub fn with_mask(mut self, mask: u16) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    fn target(&self, address: u16) -> usize {
+        self.offset + (address & self.mask) as usize
+    }
+
+    /// Mirrors and MMIO regions target addresses, ROM and RAM index backing
+    /// memory. Fails if the offset pushes either past what it can reach
+    /// rather than wrapping.
+    fn check_targets(&self) -> Result<()> {
+        let limit = match self.kind {
+            RegionKind::Mirror | RegionKind::Mmio => u16::MAX as usize,
+            RegionKind::Rom | RegionKind::Ram => u32::MAX as usize,
+            RegionKind::Unmapped => return Ok(()),
+        };
+        let Some(highest) = self.range.clone().map(|address| address & self.mask).max() else {
+            return Ok(());
+        };
+
+        match self.offset.checked_add(highest as usize) {
+            Some(end) if end <= limit => Ok(()),
+            _ => Err(Error::RegionMap(format!(
+                "region '{}' reaches past {limit:#X}",
+                self.name
+            ))),
+        }
+    }
+}
+
+/// Where an address ends up after decoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Target {
+    Rom(usize),
+    Ram(usize),
+    Mmio(u16),
+    Unmapped,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    // Index into the regions, NO_REGION where none covers the address
+    region: u16,
+    target: u32,
+}
+
+const NO_REGION: u16 = u16::MAX;
+
+/// Address map compiled from regions into a table with one slot per
+/// address. Later regions take precedence where ranges overlap.
+#[derive(Clone, Debug)]
+pub struct RegionMap {
+    regions: Vec<Region>,
+    slots: Vec<Slot>,
+    backing_size: usize,
+}
+
+impl RegionMap {
+    pub fn builder() -> RegionMapBuilder {
+        RegionMapBuilder::default()
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Region decoding `address`. Mirrors are resolved, so this is the ROM,
+    /// RAM or MMIO region the access reaches.
+    pub fn region(&self, address: u16) -> Option<&Region> {
+        self.regions
+            .get(self.slots[address as usize].region as usize)
+    }
+
+    /// Bytes of backing memory the ROM and RAM regions need
+    pub fn backing_size(&self) -> usize {
+        self.backing_size
+    }
+
+    pub(crate) fn decode(&self, address: u16) -> Target {
+        let slot = self.slots[address as usize];
+        let Some(region) = self.regions.get(slot.region as usize) else {
+            return Target::Unmapped;
+        };
+
+        match region.kind {
+            RegionKind::Rom => Target::Rom(slot.target as usize),
+            RegionKind::Ram => Target::Ram(slot.target as usize),
+            RegionKind::Mmio => Target::Mmio(slot.target as u16),
+            RegionKind::Mirror | RegionKind::Unmapped => Target::Unmapped,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RegionMapBuilder {
+    regions: Vec<Region>,
+}
+
+impl RegionMapBuilder {
+    pub fn region(mut self, region: Region) -> Self {
+        self.regions.push(region);
+        self
+    }
+
+    pub fn rom(self, name: impl Into<String>, range: RangeInclusive<u16>) -> Self {
+        self.region(Region::new(name, range, RegionKind::Rom))
+    }
+
+    pub fn ram(self, name: impl Into<String>, range: RangeInclusive<u16>) -> Self {
+        self.region(Region::new(name, range, RegionKind::Ram))
+    }
+
+    /// Repeats the addresses selected by `mask` across `range`
+    pub fn mirror(self, name: impl Into<String>, range: RangeInclusive<u16>, mask: u16) -> Self {
+        self.region(Region::new(name, range, RegionKind::Mirror).with_mask(mask))
+    }
+
+    /// Compiles the map. Mirrors fold onto the regions beneath them, never
+    /// onto other mirrors.
+    pub fn build(self) -> Result<RegionMap> {
+        if self.regions.len() >= NO_REGION as usize {
+            return Err(Error::RegionMap(format!(
+                "{} regions is more than the {} supported",
+                self.regions.len(),
+                NO_REGION
+            )));
+        }
+
+        for region in &self.regions {
+            region.check_targets()?;
+        }
+
+        // Last region covering each address, and last region that is not a
+        // mirror
+        let mut owners = vec![NO_REGION; ADDRESSES];
+        let mut plain = vec![
+            Slot {
+                region: NO_REGION,
+                target: 0,
+            };
+            ADDRESSES
+        ];
+        for (index, region) in self.regions.iter().enumerate() {
+            for address in region.range.clone() {
+                owners[address as usize] = index as u16;
+                if region.kind != RegionKind::Mirror {
+                    plain[address as usize] = Slot {
+                        region: index as u16,
+                        target: region.target(address) as u32,
+                    };
+                }
+            }
+        }
+
+        let slots = (0..ADDRESSES)
+            .map(|address| match self.regions.get(owners[address] as usize) {
+                Some(region) if region.kind == RegionKind::Mirror => {
+                    plain[region.target(address as u16)]
+                }
+                _ => plain[address],
+            })
+            .collect();
+
+        let backing_size = self
+            .regions
+            .iter()
+            .filter(|region| matches!(region.kind, RegionKind::Rom | RegionKind::Ram))
+            .flat_map(|region| {
+                region
+                    .range
+                    .clone()
+                    .map(|address| region.target(address) + 1)
+            })
+            .max()
+            .unwrap_or(0);
+
+        Ok(RegionMap {
+            regions: self.regions,
+            slots,
+            backing_size,
+        })
+    }
+}
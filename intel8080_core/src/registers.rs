@@ -0,0 +1,160 @@
+use std::{fmt, str::FromStr};
+
+use crate::errors::{Error, Result};
+
+/// Condition flags, packed into the F register as `S Z 0 AC 0 P 1 CY`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub s: bool,
+    pub z: bool,
+    pub p: bool,
+    pub cy: bool,
+    pub ac: bool,
+    /// Undocumented 8085 overflow flag
+    pub v: bool,
+    /// Undocumented 8085 underflow indicator
+    pub k: bool,
+}
+
+impl Flags {
+    pub fn to_byte(&self) -> u8 {
+        let mut byte = 2;
+
+        byte |= (self.s as u8) << 7;
+        byte |= (self.z as u8) << 6;
+        byte |= (self.ac as u8) << 4;
+        byte |= (self.p as u8) << 2;
+        byte |= self.cy as u8;
+
+        byte
+    }
+
+    pub fn from_byte(flag_register: u8) -> Self {
+        Self {
+            s: flag_register & 0x80 != 0,
+            z: flag_register & 0x40 != 0,
+            ac: flag_register & 0x10 != 0,
+            p: flag_register & 0x4 != 0,
+            cy: flag_register & 0x1 != 0,
+            v: false,
+            k: false,
+        }
+    }
+
+    /// Packs the flags the way an 8085 running undocumented opcodes does,
+    /// as `S Z K AC 0 P V CY`
+    pub fn to_byte_8085(&self) -> u8 {
+        (self.to_byte() & !0x02) | (self.k as u8) << 5 | (self.v as u8) << 1
+    }
+
+    pub fn from_byte_8085(flag_register: u8) -> Self {
+        Self {
+            v: flag_register & 0x02 != 0,
+            k: flag_register & 0x20 != 0,
+            ..Self::from_byte(flag_register)
+        }
+    }
+}
+
+/// Registers and register pairs addressable by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Register {
+    A,
+    F,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    BC,
+    DE,
+    HL,
+    SP,
+    PC,
+    PSW,
+}
+
+impl Register {
+    pub const ALL: [Register; 14] = [
+        Register::A,
+        Register::F,
+        Register::B,
+        Register::C,
+        Register::D,
+        Register::E,
+        Register::H,
+        Register::L,
+        Register::BC,
+        Register::DE,
+        Register::HL,
+        Register::SP,
+        Register::PC,
+        Register::PSW,
+    ];
+
+    pub fn is_16bit(&self) -> bool {
+        matches!(
+            self,
+            Register::BC
+                | Register::DE
+                | Register::HL
+                | Register::SP
+                | Register::PC
+                | Register::PSW
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Register::A => "A",
+            Register::F => "F",
+            Register::B => "B",
+            Register::C => "C",
+            Register::D => "D",
+            Register::E => "E",
+            Register::H => "H",
+            Register::L => "L",
+            Register::BC => "BC",
+            Register::DE => "DE",
+            Register::HL => "HL",
+            Register::SP => "SP",
+            Register::PC => "PC",
+            Register::PSW => "PSW",
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Register {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Register::ALL
+            .into_iter()
+            .find(|register| register.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::UnknownRegister(name.to_string()))
+    }
+}
+
+/// Snapshot of the programmer-visible processor state
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuState {
+    pub a: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub flags: Flags,
+    pub interrupts_enabled: bool,
+    pub halted: bool,
+}
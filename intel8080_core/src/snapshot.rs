@@ -0,0 +1,200 @@
+use crate::{
+    errors::{Error, Result},
+    helpers::{bytes_to_word, word_to_bytes},
+    i8085::{Pins, Variant},
+    processor::UndocumentedOpcodes,
+    registers::{CpuState, Flags},
+};
+
+/// Version of the save-state format written by `Processor::save_state`
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+const MAGIC: &[u8; 4] = b"I80S";
+
+// Status bits
+const INTERRUPTS_ENABLED: u8 = 1 << 0;
+const EI_PENDING: u8 = 1 << 1;
+const HALTED: u8 = 1 << 2;
+const ROM_LOADED: u8 = 1 << 3;
+const INTR: u8 = 1 << 4;
+const INTA: u8 = 1 << 5;
+
+/// Complete processor state as stored in a save-state.
+///
+/// Layout (multi-byte values little-endian):
+/// magic "I80S", version, A B C D E H L F, SP, PC, status bits,
+/// INTR instruction (3 bytes), cycles (u64), variant, undocumented opcode
+/// policy, 8085 pins (3 bytes), RAM size (u32), RAM contents.
+///
+/// F is stored with V and K in bits 1 and 5.
+#[derive(Clone, Debug)]
+pub(crate) struct Snapshot {
+    pub state: CpuState,
+    pub ei_pending: bool,
+    pub rom_loaded: bool,
+    pub intr: Option<[u8; 3]>,
+    pub inta: bool,
+    pub cycles: u64,
+    pub variant: Variant,
+    /// None for a callback, which cannot be saved
+    pub undocumented: Option<UndocumentedOpcodes>,
+    pub pins: Pins,
+    pub ram: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let state = &self.state;
+        let mut bytes = Vec::with_capacity(38 + self.ram.len());
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(SNAPSHOT_VERSION);
+        bytes.extend_from_slice(&[
+            state.a,
+            state.b,
+            state.c,
+            state.d,
+            state.e,
+            state.h,
+            state.l,
+            state.flags.to_byte_8085(),
+        ]);
+        push_word(&mut bytes, state.sp);
+        push_word(&mut bytes, state.pc);
+
+        let mut status = 0;
+        status |= state.interrupts_enabled as u8 * INTERRUPTS_ENABLED;
+        status |= self.ei_pending as u8 * EI_PENDING;
+        status |= state.halted as u8 * HALTED;
+        status |= self.rom_loaded as u8 * ROM_LOADED;
+        status |= self.intr.is_some() as u8 * INTR;
+        status |= self.inta as u8 * INTA;
+        bytes.push(status);
+        bytes.extend_from_slice(&self.intr.unwrap_or_default());
+        bytes.extend_from_slice(&self.cycles.to_le_bytes());
+        bytes.push(match self.variant {
+            Variant::Intel8080 => 0,
+            Variant::Intel8085 => 1,
+            Variant::Intel8085Undocumented => 2,
+        });
+        bytes.push(match self.undocumented {
+            Some(UndocumentedOpcodes::Strict) => 0,
+            Some(UndocumentedOpcodes::Alias) => 1,
+            Some(UndocumentedOpcodes::Callback(_)) | None => 2,
+        });
+        bytes.extend_from_slice(&self.pins.to_bytes());
+
+        bytes.extend_from_slice(&(self.ram.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.ram);
+
+        bytes
+    }
+
+    /// Parses a save-state, rejecting it unless its RAM is `ram_size` bytes
+    pub fn from_bytes(bytes: &[u8], ram_size: usize) -> Result<Self> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(Error::SnapshotFormat);
+        }
+
+        let version = reader.byte()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(Error::SnapshotVersion {
+                found: version,
+                supported: SNAPSHOT_VERSION,
+            });
+        }
+
+        let registers = reader.take(8)?;
+        let sp = reader.word()?;
+        let pc = reader.word()?;
+        let status = reader.byte()?;
+        let intr: [u8; 3] = reader.take(3)?.try_into().unwrap();
+        let cycles = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let variant = match reader.byte()? {
+            0 => Variant::Intel8080,
+            1 => Variant::Intel8085,
+            2 => Variant::Intel8085Undocumented,
+            _ => return Err(Error::SnapshotFormat),
+        };
+        let undocumented = match reader.byte()? {
+            0 => Some(UndocumentedOpcodes::Strict),
+            1 => Some(UndocumentedOpcodes::Alias),
+            2 => None,
+            _ => return Err(Error::SnapshotFormat),
+        };
+        let pins =
+            Pins::from_bytes(reader.take(3)?.try_into().unwrap()).ok_or(Error::SnapshotFormat)?;
+
+        let snapshot_ram_size = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+        if snapshot_ram_size != ram_size {
+            return Err(Error::SnapshotRamSize {
+                snapshot_size: snapshot_ram_size,
+                ram_size,
+            });
+        }
+        let ram = reader.take(ram_size)?.to_vec();
+
+        if !reader.bytes.is_empty() {
+            return Err(Error::SnapshotFormat);
+        }
+
+        Ok(Self {
+            state: CpuState {
+                a: registers[0],
+                b: registers[1],
+                c: registers[2],
+                d: registers[3],
+                e: registers[4],
+                h: registers[5],
+                l: registers[6],
+                sp,
+                pc,
+                flags: Flags::from_byte_8085(registers[7]),
+                interrupts_enabled: status & INTERRUPTS_ENABLED != 0,
+                halted: status & HALTED != 0,
+            },
+            ei_pending: status & EI_PENDING != 0,
+            rom_loaded: status & ROM_LOADED != 0,
+            intr: (status & INTR != 0).then_some(intr),
+            inta: status & INTA != 0,
+            cycles,
+            variant,
+            undocumented,
+            pins,
+            ram,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < length {
+            return Err(Error::SnapshotFormat);
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(bytes_to_word(bytes[0], bytes[1]))
+    }
+}
+
+fn push_word(bytes: &mut Vec<u8>, word: u16) {
+    let (low_byte, high_byte) = word_to_bytes(word);
+    bytes.push(low_byte);
+    bytes.push(high_byte);
+}
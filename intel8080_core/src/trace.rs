@@ -0,0 +1,231 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    ops::{Range, RangeInclusive},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{disassembler::disassemble_for, errors::Result, i8085::Variant, registers::CpuState};
+
+/// Layout of each trace line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// `PC: 0100, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 0\t(31 00 24 06)`,
+    /// the layout printed by common reference 8080 emulators
+    #[default]
+    Reference,
+    /// Address, instruction bytes, disassembly, registers, flags and cycles
+    Detailed,
+}
+
+#[derive(Clone)]
+enum TraceSink {
+    Writer(Arc<Mutex<dyn Write + Send>>),
+    Ring {
+        lines: VecDeque<String>,
+        capacity: usize,
+    },
+}
+
+impl fmt::Debug for TraceSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceSink::Writer(_) => f.write_str("Writer"),
+            TraceSink::Ring { lines, capacity } => f
+                .debug_struct("Ring")
+                .field("lines", &lines.len())
+                .field("capacity", capacity)
+                .finish(),
+        }
+    }
+}
+
+/// Logs each instruction the processor executes. Install it with
+/// `Processor::set_tracer`. Lines are written before the instruction runs,
+/// with the cycles executed so far.
+#[derive(Clone, Debug)]
+pub struct Tracer {
+    sink: TraceSink,
+    format: TraceFormat,
+    addresses: RangeInclusive<u16>,
+    window: Range<u64>,
+    instructions: u64,
+    cycles: u64,
+    // Processor model, for disassembly
+    variant: Variant,
+}
+
+impl Tracer {
+    /// Writes the trace to a new file at `path`
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::to_writer(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::with_sink(TraceSink::Writer(Arc::new(Mutex::new(writer))))
+    }
+
+    /// Keeps only the last `capacity` lines in memory
+    pub fn ring_buffer(capacity: usize) -> Self {
+        Self::with_sink(TraceSink::Ring {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        })
+    }
+
+    fn with_sink(sink: TraceSink) -> Self {
+        Self {
+            sink,
+            format: TraceFormat::default(),
+            addresses: 0..=u16::MAX,
+            window: 0..u64::MAX,
+            instructions: 0,
+            cycles: 0,
+            variant: Variant::default(),
+        }
+    }
+
+    pub fn with_format(mut self, format: TraceFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Only logs instructions whose address is in `addresses`
+    pub fn with_addresses(mut self, addresses: RangeInclusive<u16>) -> Self {
+        self.addresses = addresses;
+        self
+    }
+
+    /// Only logs the instructions numbered `window`, counting from 0 at the
+    /// first instruction executed with the tracer installed
+    pub fn with_window(mut self, window: Range<u64>) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Instructions executed since the tracer was installed
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Cycles executed since the tracer was installed
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Lines held by a ring buffer tracer, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        let lines = match &self.sink {
+            TraceSink::Ring { lines, .. } => Some(lines.iter().map(String::as_str)),
+            TraceSink::Writer(_) => None,
+        };
+
+        lines.into_iter().flatten()
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        if let TraceSink::Writer(writer) = &self.sink {
+            writer.lock().unwrap().flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Sets the processor model whose opcodes are disassembled
+    pub(crate) fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    pub(crate) fn add_cycles(&mut self, cycles: u32) {
+        self.cycles += cycles as u64;
+    }
+
+    /// Logs the instruction at `state.pc`, `bytes` are the 4 bytes there
+    pub(crate) fn record(&mut self, state: &CpuState, bytes: [u8; 4]) -> Result<()> {
+        let number = self.instructions;
+        self.instructions += 1;
+        if !self.window.contains(&number) || !self.addresses.contains(&state.pc) {
+            return Ok(());
+        }
+
+        let line = match self.format {
+            TraceFormat::Reference => reference_line(state, bytes, self.cycles),
+            TraceFormat::Detailed => detailed_line(state, bytes, self.cycles, self.variant),
+        };
+
+        match &mut self.sink {
+            TraceSink::Writer(writer) => writeln!(writer.lock().unwrap(), "{line}")?,
+            TraceSink::Ring { lines, capacity } => {
+                if lines.len() == *capacity {
+                    lines.pop_front();
+                }
+                if *capacity > 0 {
+                    lines.push_back(line);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn reference_line(state: &CpuState, bytes: [u8; 4], cycles: u64) -> String {
+    format!(
+        "PC: {:04X}, AF: {:02X}{:02X}, BC: {:02X}{:02X}, DE: {:02X}{:02X}, HL: {:02X}{:02X}, SP: {:04X}, CYC: {}\t({:02X} {:02X} {:02X} {:02X})",
+        state.pc,
+        state.a,
+        state.flags.to_byte(),
+        state.b,
+        state.c,
+        state.d,
+        state.e,
+        state.h,
+        state.l,
+        state.sp,
+        cycles,
+        bytes[0],
+        bytes[1],
+        bytes[2],
+        bytes[3],
+    )
+}
+
+fn detailed_line(state: &CpuState, bytes: [u8; 4], cycles: u64, variant: Variant) -> String {
+    let instruction =
+        disassemble_for(&bytes[..3], state.pc, variant).expect("3 bytes always decode");
+    let instruction_bytes = instruction
+        .bytes()
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let flags = &state.flags;
+    let flag_chars: String = [
+        (flags.s, 'S'),
+        (flags.z, 'Z'),
+        (flags.ac, 'A'),
+        (flags.p, 'P'),
+        (flags.cy, 'C'),
+    ]
+    .iter()
+    .map(|&(set, name)| if set { name } else { '.' })
+    .collect();
+
+    format!(
+        "{:04X}  {instruction_bytes:<8}  {:<14}  A:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} F:{flag_chars} CYC:{cycles}",
+        state.pc,
+        instruction.to_string(),
+        state.a,
+        state.b,
+        state.c,
+        state.d,
+        state.e,
+        state.h,
+        state.l,
+        state.sp,
+    )
+}
@@ -0,0 +1,93 @@
+use intel8080_core::{assembler::assemble, errors::Error};
+
+/// Line number and message of a failed assembly
+fn error(source: &str) -> (usize, String) {
+    match assemble(source) {
+        Err(Error::Assembly { line_num, message }) => (line_num, message),
+        result => panic!("expected an assembly error, got {result:?}"),
+    }
+}
+
+#[test]
+fn lays_out_directives_and_labels() {
+    let assembly = assemble(
+        "
+        ORG 100H
+COUNT   EQU 3
+START:  MVI B,COUNT
+        JMP NEXT        ; forward reference
+MSG:    DB 'Hi',0,COUNT*2
+        DW START,1234H
+        DS 2
+NEXT:   HLT
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(assembly.origin(), 0x100);
+    assert_eq!(assembly.symbols["COUNT"], 3);
+    assert_eq!(assembly.symbols["START"], 0x100);
+    assert_eq!(assembly.symbols["MSG"], 0x105);
+    assert_eq!(assembly.symbols["NEXT"], 0x10F);
+    assert_eq!(assembly.lines[&4], 0x100);
+    assert_eq!(assembly.lines[&9], 0x10F);
+
+    // DS leaves a gap, which the binary image fills with zeros
+    assert_eq!(assembly.segments.len(), 2);
+    assert_eq!(
+        assembly.to_binary(),
+        [
+            0x06, 0x03, 0xC3, 0x0F, 0x01, b'H', b'i', 0x00, 0x06, 0x00, 0x01, 0x34, 0x12, 0x00,
+            0x00, 0x76
+        ]
+    );
+}
+
+#[test]
+fn evaluates_expressions() {
+    let assembly = assemble(
+        "
+        ORG 200H
+        DB HIGH 1234H, LOW 1234H, 2+3*4, (2+3)*4, 10 MOD 3
+        DB 1 SHL 4, NOT 0 AND 0FFH, 'A'+1, -1, 0x7F, 101B, 17Q
+        DW $, 'AB'
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(
+        assembly.to_binary(),
+        [
+            0x12, 0x34, 0x0E, 0x14, 0x01, 0x10, 0xFF, 0x42, 0xFF, 0x7F, 0x05, 0x0F, 0x0C, 0x02,
+            0x42, 0x41
+        ]
+    );
+}
+
+#[test]
+fn reports_errors_with_line_numbers() {
+    assert_eq!(error(" NOP\n FOO A").0, 2);
+    assert_eq!(
+        error(" JMP NOWHERE"),
+        (1, "undefined symbol 'NOWHERE'".into())
+    );
+    assert_eq!(error(" MVI A,256").1, "value 256 does not fit in a byte");
+    assert_eq!(
+        error("X: NOP\nX: NOP"),
+        (2, "symbol 'X' is defined more than once".into())
+    );
+    assert_eq!(error(" DB 1/0").1, "division by zero");
+
+    // Overflowing arithmetic is an error rather than a panic
+    for expression in [
+        "9223372036854775807+1",
+        "-9223372036854775807-2",
+        "4294967296*4294967296",
+        "-(-9223372036854775807-1)",
+        "(-9223372036854775807-1)/-1",
+    ] {
+        let (line_num, message) = error(&format!(" NOP\n DW {expression}"));
+        assert_eq!(line_num, 2);
+        assert_eq!(message, "expression overflows 64 bits", "{expression}");
+    }
+}
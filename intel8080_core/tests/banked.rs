@@ -0,0 +1,118 @@
+use intel8080_core::{
+    assembler::assemble,
+    banked::{BankPorts, BankedMemory},
+    bus::Bus,
+    memory::{Fault, FaultPolicy},
+    processor::Processor,
+    registers::Register,
+};
+
+mod common;
+
+use common::TestPort;
+
+#[test]
+fn ports_switch_banks() {
+    // CP/M 3 style: the lowest 16 KiB is banked, the code runs from common
+    // memory at the top
+    let assembly = assemble(
+        "
+        ORG 0C000H
+        MVI A,11H
+        STA 0
+        MVI A,4
+        OUT 10H
+        MVI A,44H
+        STA 0
+        XRA A
+        OUT 10H
+        LDA 0
+        HLT
+        ",
+    )
+    .unwrap();
+    let memory = BankedMemory::new(0x4000, 6).unwrap();
+    let mut port = BankPorts::new(TestPort, memory.banks())
+        .with_port(0x10, 0)
+        .unwrap();
+    let mut processor = Processor::with_bus(memory);
+    processor
+        .load_rom(&assembly.to_binary(), assembly.origin())
+        .unwrap();
+    processor.set_register(Register::PC, 0xC000);
+
+    for _ in 0..9 {
+        processor.execute(&mut port).unwrap();
+    }
+    assert_eq!(processor.register(Register::A), 0x11);
+
+    let banks = processor.bus().banks();
+    assert_eq!(banks.selected(0), 0);
+    banks.select(0, 4).unwrap();
+    assert_eq!(processor.bus().peek(0), Some(0x44));
+}
+
+#[test]
+fn latches_switch_cartridge_roms() {
+    // 8 KiB pages, with a latch at 0x6000 choosing the ROM seen at 0x4000
+    let mut memory = BankedMemory::new(0x2000, 12).unwrap();
+    for page in 8..12 {
+        memory.set_rom(page, true).unwrap();
+        memory.load_pages(page, &[page as u8 * 0x10]).unwrap();
+    }
+    memory.add_latch(0x6000, 2).unwrap();
+
+    memory.write(0x6000, 9).unwrap();
+    assert_eq!(memory.read(0x4000).unwrap(), 0x90);
+    assert_eq!(memory.read(0x6000).unwrap(), 0x00);
+
+    let error = memory.write(0x4000, 0).unwrap_err();
+    assert_eq!(error.to_string(), "Write to ROM 'page 9' at 0x4000");
+
+    // ROM writes follow the fault policy like they do on `Memory`
+    memory.set_rom_write_policy(FaultPolicy::Log);
+    memory.write(0x4001, 0x12).unwrap();
+    assert_eq!(memory.read(0x4001).unwrap(), 0x00);
+    assert_eq!(
+        memory.take_faults(),
+        [Fault::RomWrite {
+            address: 0x4001,
+            value: 0x12
+        }]
+    );
+
+    // Page numbers wrap at the number of pages
+    memory.write(0x6000, 20).unwrap();
+    assert_eq!(memory.banks().selected(2), 8);
+    assert_eq!(memory.read(0x4000).unwrap(), 0x80);
+}
+
+#[test]
+fn rejects_invalid_layouts() {
+    assert!(BankedMemory::new(3000, 4).is_err());
+    assert!(BankedMemory::new(0x20000, 1).is_err());
+    assert!(BankedMemory::new(0x4000, 0).is_err());
+
+    let mut memory = BankedMemory::new(0x4000, 4).unwrap();
+    assert!(memory.load_pages(3, &[0; 0x4000]).is_ok());
+    assert!(memory.load_pages(3, &[0; 0x4001]).is_err());
+
+    // Windows and pages are checked when configured, not on first use
+    let error = memory.set_rom(4, true).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid bank layout: page 4 is out of range, there are 4 pages"
+    );
+    assert!(memory.add_latch(0x1000, 4).is_err());
+    assert!(
+        BankPorts::new(TestPort, memory.banks())
+            .with_port(0x10, 4)
+            .is_err()
+    );
+    assert!(memory.add_latch(0x1000, 3).is_ok());
+
+    let banks = memory.banks();
+    assert!(banks.select(4, 0).is_err());
+    assert!(banks.select(0, 4).is_err());
+    assert_eq!(banks.selected(0), 0);
+}
@@ -0,0 +1,13 @@
+use intel8080_core::port::Port;
+
+/// Answers IN with the port number, so tests can tell which port was read,
+/// and drops OUT
+pub struct TestPort;
+
+impl Port for TestPort {
+    fn read_in(&self, port_num: u8) -> u8 {
+        port_num
+    }
+
+    fn write_out(&mut self, _port_num: u8, _value: u8) {}
+}
@@ -0,0 +1,109 @@
+use intel8080_core::{
+    assembler::assemble,
+    coverage::{Coverage, Usage},
+    disassembler::disassemble_covered,
+    processor::Processor,
+};
+
+mod common;
+
+use common::TestPort;
+
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,2400H
+        LDA VALUE
+        ORA A
+        JZ SKIP
+        STA 2000H
+SKIP:   PUSH PSW
+        HLT
+        MVI A,1
+VALUE:  DB 7
+";
+
+fn run(value: u8) -> Coverage {
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut rom = assembly.to_binary();
+    *rom.last_mut().unwrap() = value;
+
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&rom, 0).unwrap();
+    processor.set_coverage(Some(Coverage::new()));
+    while !processor.is_halted() {
+        processor.execute(&mut TestPort).unwrap();
+    }
+
+    processor.coverage().unwrap().clone()
+}
+
+#[test]
+fn distinguishes_code_and_data() {
+    let coverage = run(7);
+
+    assert!(coverage.is(0x0000, Usage::Opcode));
+    assert!(coverage.is(0x0001, Usage::Operand));
+    assert!(!coverage.is(0x0001, Usage::Opcode));
+    // LDA VALUE reads the DB byte
+    assert!(coverage.is(0x0011, Usage::Read));
+    assert!(!coverage.is_code(0x0011));
+    assert!(coverage.is(0x2000, Usage::Write));
+    // PUSH PSW writes below the stack pointer
+    assert_eq!(coverage.count(Usage::Write, 0x23FE..=0x23FF), 2);
+    // Never reached
+    assert!(coverage.is_untouched(0x000F));
+    assert_eq!(coverage.count(Usage::Opcode, 0x0000..=0x0011), 7);
+}
+
+#[test]
+fn merges_runs_and_round_trips_bitmaps() {
+    let mut coverage = run(0);
+    assert!(coverage.is_untouched(0x000A));
+    assert!(!coverage.is(0x2000, Usage::Write));
+
+    coverage.merge(&run(7));
+    assert!(coverage.is(0x000A, Usage::Opcode));
+
+    let bitmap = coverage.to_bitmap();
+    assert_eq!(bitmap.len(), 4 * 8192);
+    // Bit 0 of the opcode map is address 0
+    assert_eq!(bitmap[0] & 1, 1);
+    assert_eq!(Coverage::from_bitmap(&bitmap).unwrap(), coverage);
+    assert!(Coverage::from_bitmap(&bitmap[1..]).is_err());
+
+    let report = coverage.report(&[("rom", 0x0000..=0x0011), ("ram", 0x2000..=0x23FF)]);
+    let rom_line = report.lines().nth(1).unwrap();
+    assert!(rom_line.starts_with("rom"), "{report}");
+    assert!(rom_line.contains("0000-0011"), "{report}");
+}
+
+#[test]
+fn feeds_the_disassembler() {
+    let rom = assemble(PROGRAM).unwrap().to_binary();
+    let listing: Vec<String> = disassemble_covered(&rom, 0, &run(7))
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    // The value LDA reads is data, the MVI after HLT was never reached
+    assert_eq!(
+        listing,
+        [
+            "LXI SP,2400H",
+            "LDA 0011H",
+            "ORA A",
+            "JZ 000DH",
+            "STA 2000H",
+            "PUSH PSW",
+            "HLT",
+            "MVI A,01H",
+            "DB 07H",
+        ]
+    );
+
+    // Empty ranges report 0% rather than NaN
+    let (start, end) = (0x0010, 0x000F);
+    let report = run(7).report(&[("empty", start..=end)]);
+    assert!(report.contains("0   0.0%"), "{report}");
+    assert!(!report.contains("NaN"), "{report}");
+}
@@ -0,0 +1,305 @@
+use intel8080_core::{
+    assembler::assemble,
+    banked::BankedMemory,
+    bus::Bus,
+    debugger::{Access, Debugger, StopReason},
+    i8085::{InterruptPin, Variant},
+    processor::Processor,
+};
+
+mod common;
+
+use common::TestPort;
+
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,1000H
+        MVI A,5
+        CALL DOUBLE
+AFTER:  STA 2000H
+        IN 10H
+        OUT 11H
+        HLT
+DOUBLE: ADD A
+        CALL NOTHING
+        RET
+NOTHING: RET
+";
+
+fn debugger() -> (Debugger, u16, u16, u16) {
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+
+    (
+        Debugger::new(processor),
+        assembly.symbols["AFTER"],
+        assembly.symbols["DOUBLE"],
+        assembly.symbols["NOTHING"],
+    )
+}
+
+#[test]
+fn breakpoints_stop_before_the_instruction() {
+    let (mut debugger, after, double, _) = debugger();
+    debugger.add_breakpoint(double);
+
+    assert_eq!(
+        debugger.run(&mut TestPort).unwrap(),
+        StopReason::Breakpoint(double)
+    );
+    assert_eq!(debugger.processor().pc(), double);
+    assert_eq!(debugger.processor().state().a, 5);
+
+    let frames = debugger.call_stack();
+    assert_eq!(frames.len(), 1);
+    assert_eq!((frames[0].entry, frames[0].return_address), (double, after));
+
+    // Resuming steps off the breakpoint
+    assert_eq!(debugger.step_out(&mut TestPort).unwrap(), StopReason::Step);
+    assert_eq!(debugger.processor().pc(), after);
+    assert_eq!(debugger.processor().state().a, 10);
+    assert!(debugger.call_stack().is_empty());
+}
+
+#[test]
+fn step_over_runs_calls_to_completion() {
+    let (mut debugger, after, _, nothing) = debugger();
+    debugger.step(&mut TestPort).unwrap();
+    debugger.step(&mut TestPort).unwrap();
+
+    assert_eq!(debugger.step_over(&mut TestPort).unwrap(), StopReason::Step);
+    assert_eq!(debugger.processor().pc(), after);
+
+    // Breakpoints inside the call still stop a step over
+    let (mut debugger, _, _, _) = self::debugger();
+    debugger.add_breakpoint(nothing);
+    debugger.run_to(after - 3, &mut TestPort).unwrap();
+    assert_eq!(
+        debugger.step_over(&mut TestPort).unwrap(),
+        StopReason::Breakpoint(nothing)
+    );
+}
+
+#[test]
+fn watchpoints_ignore_opcode_fetches() {
+    let (mut debugger, after, double, _) = debugger();
+    debugger.add_watchpoint(double, Access::Read);
+    debugger.add_watchpoint(0x2000, Access::Write);
+
+    assert_eq!(
+        debugger.run(&mut TestPort).unwrap(),
+        StopReason::Watchpoint {
+            address: 0x2000,
+            value: 10,
+            access: Access::Write
+        }
+    );
+    assert_eq!(debugger.processor().pc(), after + 3);
+}
+
+#[test]
+fn port_breakpoints_and_halt() {
+    let (mut debugger, _, _, _) = debugger();
+    debugger.add_port_breakpoint(0x10, Access::Read);
+    debugger.add_port_breakpoint(0x11, Access::Write);
+
+    assert_eq!(
+        debugger.run(&mut TestPort).unwrap(),
+        StopReason::PortBreakpoint {
+            port_num: 0x10,
+            value: 0x10,
+            access: Access::Read
+        }
+    );
+    assert_eq!(
+        debugger.run(&mut TestPort).unwrap(),
+        StopReason::PortBreakpoint {
+            port_num: 0x11,
+            value: 0x10,
+            access: Access::Write
+        }
+    );
+    assert_eq!(debugger.run(&mut TestPort).unwrap(), StopReason::Halted);
+    assert!(debugger.into_processor().is_halted());
+}
+
+#[test]
+fn run_to_and_cycle_limit() {
+    let (mut debugger, after, _, _) = debugger();
+
+    assert_eq!(
+        debugger.run_to(after, &mut TestPort).unwrap(),
+        StopReason::Reached(after)
+    );
+    // LXI, MVI, CALL, ADD, CALL, RET, RET
+    assert_eq!(debugger.cycles(), 10 + 7 + 17 + 4 + 17 + 10 + 10);
+
+    assert_eq!(
+        debugger.run_for(1, &mut TestPort).unwrap(),
+        StopReason::CycleLimit
+    );
+    assert_eq!(debugger.processor().pc(), after + 3);
+}
+
+#[test]
+fn reverse_execution_finds_the_write() {
+    let (mut debugger, after, double, _) = debugger();
+    debugger.set_history_capacity(100);
+    debugger.add_watchpoint(0x2000, Access::Write);
+    debugger.run(&mut TestPort).unwrap();
+
+    // Reverse continue stops before the STA that wrote the byte
+    assert_eq!(
+        debugger.run_back_to_breakpoint().unwrap(),
+        StopReason::Watchpoint {
+            address: 0x2000,
+            value: 10,
+            access: Access::Write
+        }
+    );
+    assert_eq!(debugger.processor().pc(), after);
+    assert_eq!(debugger.processor().bus().peek(0x2000), Some(0));
+    assert_eq!(debugger.cycles(), 10 + 7 + 17 + 4 + 17 + 10 + 10);
+
+    // Undoing the RET puts DOUBLE's frame back
+    assert_eq!(debugger.step_back().unwrap(), StopReason::Step);
+    assert_eq!(debugger.call_stack().len(), 1);
+    assert_eq!(debugger.call_stack()[0].entry, double);
+    assert_eq!(debugger.processor().state().a, 10);
+
+    // Undoes the inner RET and CALL
+    assert_eq!(debugger.rewind(20).unwrap(), StopReason::CycleLimit);
+    assert_eq!(debugger.cycles(), 10 + 7 + 17 + 4);
+    assert_eq!(debugger.call_stack().len(), 1);
+
+    assert_eq!(debugger.rewind(1000).unwrap(), StopReason::HistoryStart);
+    assert_eq!(debugger.processor().pc(), 0);
+    assert_eq!(debugger.processor().state().a, 0);
+    assert!(debugger.call_stack().is_empty());
+
+    // Replaying gives the same result
+    assert!(matches!(
+        debugger.run(&mut TestPort).unwrap(),
+        StopReason::Watchpoint { value: 10, .. }
+    ));
+
+    debugger.set_history_capacity(2);
+    assert_eq!(debugger.history_len(), 2);
+}
+
+#[test]
+fn interrupts_serviced_by_execute_get_their_own_frames() {
+    let assembly = assemble(
+        "
+        ORG 0
+        LXI SP,1000H
+        EI
+        NOP
+        CALL SUB
+        HLT
+SUB:    RET
+        ORG 24H
+        RET
+        ORG 38H
+        RET
+        ",
+    )
+    .unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+    let mut debugger = Debugger::new(processor);
+    for _ in 0..3 {
+        debugger.step(&mut TestPort).unwrap();
+    }
+    assert_eq!(debugger.processor().pc(), 5);
+
+    // The held INTR is serviced instead of the CALL at PC
+    debugger.processor_mut().set_intr(&[0xFF]);
+    debugger.step(&mut TestPort).unwrap();
+    debugger.processor_mut().clear_intr();
+    let frame = debugger.call_stack()[0];
+    assert!(frame.interrupt);
+    assert_eq!((frame.entry, frame.return_address), (0x38, 5));
+
+    debugger.step_out(&mut TestPort).unwrap();
+    assert_eq!(debugger.processor().pc(), 5);
+    assert!(debugger.call_stack().is_empty());
+
+    // Same for the 8085 TRAP input, which ignores the interrupt enable
+    debugger.processor_mut().set_variant(Variant::Intel8085);
+    debugger
+        .processor_mut()
+        .set_interrupt_pin(InterruptPin::Trap, true);
+    debugger.step(&mut TestPort).unwrap();
+    let frame = debugger.call_stack()[0];
+    assert!(frame.interrupt);
+    assert_eq!((frame.entry, frame.return_address), (0x24, 5));
+
+    debugger.step(&mut TestPort).unwrap();
+    assert!(debugger.call_stack().is_empty());
+    assert_eq!(debugger.step_over(&mut TestPort).unwrap(), StopReason::Step);
+    assert_eq!(debugger.processor().pc(), 8);
+}
+
+#[test]
+fn halted_8085_waits_for_its_inputs() {
+    // Unmasks RST 7.5 and halts with interrupts enabled
+    let assembly = assemble(
+        "
+        ORG 0
+        MVI A,0BH
+        DB 30H
+        EI
+        HLT
+        ORG 3CH
+        MVI A,3CH
+        HLT
+        ",
+    )
+    .unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+    processor.set_variant(Variant::Intel8085);
+    let mut debugger = Debugger::new(processor);
+    debugger.step(&mut TestPort).unwrap();
+    debugger.step(&mut TestPort).unwrap();
+
+    // The request arrives while interrupts are still disabled, and is
+    // serviced once the processor has halted
+    debugger
+        .processor_mut()
+        .set_interrupt_pin(InterruptPin::Rst75, true);
+    debugger.add_breakpoint(0x3E);
+    assert_eq!(
+        debugger.run(&mut TestPort).unwrap(),
+        StopReason::Breakpoint(0x3E)
+    );
+    assert_eq!(debugger.processor().state().a, 0x3C);
+    assert_eq!(debugger.run(&mut TestPort).unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn rewinding_banked_memory_skips_latches() {
+    // Window 1 is switched by a latch at its first address
+    let mut memory = BankedMemory::new(0x4000, 4).unwrap();
+    memory.load_pages(1, &[0x11]).unwrap();
+    memory.load_pages(2, &[0x22]).unwrap();
+    memory.add_latch(0x4000, 1).unwrap();
+    let assembly = assemble(" ORG 0\n MVI A,2\n STA 4000H\n STA 0C000H\n HLT\n").unwrap();
+    let mut processor = Processor::with_bus(memory);
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+
+    let mut debugger = Debugger::new(processor);
+    debugger.set_history_capacity(10);
+    assert_eq!(debugger.run(&mut TestPort).unwrap(), StopReason::Halted);
+    assert_eq!(debugger.processor().bus().peek(0xC000), Some(2));
+
+    assert_eq!(debugger.rewind(u64::MAX).unwrap(), StopReason::HistoryStart);
+    assert_eq!(debugger.processor().pc(), 0);
+    let bus = debugger.processor().bus().inner();
+    assert_eq!(bus.peek(0xC000), Some(0));
+    assert_eq!((bus.banks().selected(1), bus.peek(0x4000)), (2, Some(0x22)));
+    bus.banks().select(1, 1).unwrap();
+    assert_eq!(bus.peek(0x4000), Some(0x11));
+}
@@ -0,0 +1,74 @@
+use intel8080_core::{
+    disassembler::{Flow, disassemble, disassemble_all, disassemble_bus},
+    memory::Memory,
+};
+
+#[test]
+fn decodes_intel_syntax() {
+    let instruction = disassemble(&[0x7E], 0).unwrap();
+    assert_eq!(instruction.to_string(), "MOV A,M");
+    assert_eq!((instruction.length, instruction.cycles), (1, 7));
+
+    let instruction = disassemble(&[0x21, 0x00, 0x24], 0x100).unwrap();
+    assert_eq!(instruction.to_string(), "LXI H,2400H");
+    assert_eq!(instruction.bytes(), [0x21, 0x00, 0x24]);
+    assert_eq!((instruction.length, instruction.cycles), (3, 10));
+    assert_eq!(instruction.next_address(), 0x103);
+
+    // Literals starting with a letter get a leading zero
+    let instruction = disassemble(&[0x3E, 0xFF], 0).unwrap();
+    assert_eq!(instruction.to_string(), "MVI A,0FFH");
+    assert_eq!(
+        disassemble(&[0xD3, 0x03], 0).unwrap().to_string(),
+        "OUT 03H"
+    );
+    assert_eq!(disassemble(&[0xF5], 0).unwrap().to_string(), "PUSH PSW");
+    assert_eq!(disassemble(&[0xEF], 0).unwrap().to_string(), "RST 5");
+}
+
+#[test]
+fn decodes_flow_and_conditional_cycles() {
+    let instruction = disassemble(&[0xC2, 0x34, 0x12], 0).unwrap();
+    assert_eq!(instruction.to_string(), "JNZ 1234H");
+    assert_eq!(instruction.flow, Flow::Jump);
+    assert_eq!(instruction.target(), Some(0x1234));
+
+    let instruction = disassemble(&[0xC4, 0x00, 0x20], 0).unwrap();
+    assert_eq!(instruction.flow, Flow::Call);
+    assert_eq!(
+        (instruction.cycles, instruction.cycles_taken),
+        (11, Some(17))
+    );
+
+    let instruction = disassemble(&[0xD8], 0).unwrap();
+    assert_eq!(instruction.flow, Flow::Return);
+    assert_eq!(
+        (instruction.cycles, instruction.cycles_taken),
+        (5, Some(11))
+    );
+
+    let instruction = disassemble(&[0xDF], 0).unwrap();
+    assert_eq!(instruction.target(), Some(0x18));
+
+    let instruction = disassemble(&[0xDD, 0x00, 0x10], 0).unwrap();
+    assert!(instruction.undocumented);
+    assert_eq!(instruction.to_string(), "CALL 1000H");
+}
+
+#[test]
+fn stops_at_truncated_instructions() {
+    assert_eq!(disassemble(&[0x21, 0x00], 0), None);
+    assert_eq!(disassemble(&[], 0), None);
+
+    let instructions = disassemble_all(&[0x00, 0x3E, 0x01, 0xC3, 0x00], 0x100);
+    let text: Vec<String> = instructions.iter().map(ToString::to_string).collect();
+    assert_eq!(text, ["NOP", "MVI A,01H"]);
+    assert_eq!(instructions[1].address, 0x101);
+
+    // A JMP in the last byte of memory has operands past the end
+    let mut memory = Memory::new(0x4000, |address| (address as usize, false));
+    memory.write(0x3FFF, 0xC3).unwrap();
+    memory.write(0x3FFE, 0x00).unwrap();
+    assert_eq!(disassemble_bus(&memory, 0x3FFF), None);
+    assert_eq!(disassemble_bus(&memory, 0x3FFE).unwrap().to_string(), "NOP");
+}
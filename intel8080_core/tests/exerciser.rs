@@ -0,0 +1,115 @@
+use std::{env, fs, path::PathBuf};
+
+use intel8080_core::cpm::CpmMachine;
+
+/// CRC of each 8080EXM test group on real silicon, in the order they run
+const EXERCISER_CRCS: [(&str, &str); 25] = [
+    ("dad <b,d,h,sp>", "14474ba6"),
+    ("aluop nn", "9e922f9e"),
+    ("aluop <b,c,d,e,h,l,m,a>", "cf762c86"),
+    ("<daa,cma,stc,cmc>", "bb3f030c"),
+    ("<inr,dcr> a", "adb6460e"),
+    ("<inr,dcr> b", "83ed1345"),
+    ("<inx,dcx> b", "f79287cd"),
+    ("<inr,dcr> c", "e5f6721b"),
+    ("<inr,dcr> d", "15b5579a"),
+    ("<inx,dcx> d", "7f4e2501"),
+    ("<inr,dcr> e", "cf2ab396"),
+    ("<inr,dcr> h", "12b2952c"),
+    ("<inx,dcx> h", "9f2b23c0"),
+    ("<inr,dcr> l", "ff57d356"),
+    ("<inr,dcr> m", "92e963bd"),
+    ("<inx,dcx> sp", "d5702fab"),
+    ("lhld nnnn", "a9c3d5cb"),
+    ("shld nnnn", "e8864f26"),
+    ("lxi <b,d,h,sp>,nnnn", "fcf46e12"),
+    ("ldax <b,d>", "2b821d5f"),
+    ("mvi <b,c,d,e,h,l,m,a>,nn", "eaa72044"),
+    ("mov <bcdehla>,<bcdehla>", "10b58cee"),
+    ("sta nnnn / lda nnnn", "ed57af72"),
+    ("<rlc,rrc,ral,rar>", "e0d89235"),
+    ("stax <b,d>", "2b0471e9"),
+];
+
+/// Runs a diagnostic program and returns its console output. The programs
+/// are not distributed with the repository, see tests/roms/README.md. A
+/// missing program skips the test unless `I8080_ROM_DIR` points at the
+/// directory it should be in.
+fn run_program(file_name: &str) -> Option<String> {
+    let rom_dir = env::var_os("I8080_ROM_DIR").map(PathBuf::from);
+    let required = rom_dir.is_some();
+    let path = rom_dir
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms"))
+        .join(file_name);
+
+    let program = match fs::read(&path) {
+        Ok(program) => program,
+        Err(error) if required => panic!("{file_name} not found at {}: {error}", path.display()),
+        Err(_) => {
+            eprintln!(
+                "skipping, {file_name} not found at {}, see tests/roms/README.md",
+                path.display()
+            );
+            return None;
+        }
+    };
+
+    let mut machine = CpmMachine::new(&program, Vec::new()).unwrap();
+    machine.run().unwrap();
+
+    Some(String::from_utf8_lossy(&machine.into_output()).into_owned())
+}
+
+#[test]
+fn preliminary_tests() {
+    let Some(output) = run_program("8080PRE.COM") else {
+        return;
+    };
+
+    assert!(
+        output.contains("8080 Preliminary tests complete"),
+        "{output}"
+    );
+}
+
+#[test]
+fn microcosm_diagnostic() {
+    let Some(output) = run_program("TST8080.COM") else {
+        return;
+    };
+
+    assert!(output.contains("CPU IS OPERATIONAL"), "{output}");
+}
+
+#[test]
+fn supersoft_cpu_test() {
+    let Some(output) = run_program("CPUTEST.COM") else {
+        return;
+    };
+
+    assert!(output.contains("CPU TESTS OK"), "{output}");
+}
+
+#[test]
+#[ignore = "runs for several minutes, use --release"]
+fn instruction_exerciser() {
+    let Some(output) = run_program("8080EXM.COM") else {
+        return;
+    };
+
+    // Every test group prints its CRC and whether it matched real silicon
+    let results: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.contains("crc"))
+        .collect();
+    assert_eq!(results.len(), EXERCISER_CRCS.len(), "{output}");
+
+    for (line, (name, crc)) in results.iter().zip(EXERCISER_CRCS) {
+        assert!(line.starts_with(name), "expected {name}, got {line}");
+        assert!(
+            line.contains("PASS!") && line.ends_with(&format!("crc is:{crc}")),
+            "{name} should have crc {crc}: {line}"
+        );
+    }
+}
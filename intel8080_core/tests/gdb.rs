@@ -0,0 +1,136 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+};
+
+use intel8080_core::{assembler::assemble, debugger::Debugger, gdb::GdbStub, processor::Processor};
+
+mod common;
+
+use common::TestPort;
+
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,1000H
+        MVI A,12H
+        STA 2000H
+LOOP:   INR B
+        JMP LOOP
+";
+
+/// Minimal client that sends packets and returns the stub's replies
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn send(&mut self, data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        write!(self.stream, "${data}#{checksum:02x}").unwrap();
+        self.receive()
+    }
+
+    fn receive(&mut self) -> String {
+        let mut reply = Vec::new();
+        let mut byte = [0];
+        loop {
+            self.stream.read_exact(&mut byte).unwrap();
+            match byte[0] {
+                b'+' if reply.is_empty() => {}
+                b'#' => break,
+                other => reply.push(other),
+            }
+        }
+
+        let mut checksum = [0; 2];
+        self.stream.read_exact(&mut checksum).unwrap();
+        self.stream.write_all(b"+").unwrap();
+
+        String::from_utf8(reply)
+            .unwrap()
+            .trim_start_matches('$')
+            .to_string()
+    }
+}
+
+fn start() -> (Client, JoinHandle<Debugger>) {
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut debugger = Debugger::new(processor);
+        let (stream, _) = listener.accept().unwrap();
+        GdbStub::new(stream)
+            .serve(&mut debugger, &mut TestPort)
+            .unwrap();
+        debugger
+    });
+
+    let client = Client {
+        stream: TcpStream::connect(address).unwrap(),
+    };
+    (client, server)
+}
+
+#[test]
+fn registers_memory_and_stepping() {
+    let (mut client, server) = start();
+
+    let supported = client.send("qSupported:xmlRegisters=i386");
+    assert!(supported.contains("PacketSize=1000;"));
+    assert!(supported.contains("qXfer:features:read+"));
+    assert!(
+        client
+            .send("qXfer:features:read:target.xml:0,1000")
+            .starts_with('l')
+    );
+    assert_eq!(client.send("?"), "S05");
+    assert_eq!(client.send("g"), "000200000000000000000000");
+
+    // LXI SP then MVI A
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("s"), "S05");
+    assert_eq!(client.send("p0"), "12");
+    assert_eq!(client.send("p8"), "0010");
+    assert_eq!(client.send("p9"), "0500");
+
+    assert_eq!(client.send("P2=34"), "OK");
+    assert_eq!(client.send("M3000,2:abcd"), "OK");
+    assert_eq!(client.send("m3000,2"), "abcd");
+    assert_eq!(client.send("m0000,3"), "310010");
+
+    // Replies are capped at the advertised packet size
+    assert_eq!(client.send("m0000,800").len(), 0x1000);
+    assert_eq!(client.send("m0000,801"), "E01");
+
+    assert_eq!(client.send("D"), "OK");
+    let debugger = server.join().unwrap();
+    assert_eq!(debugger.processor().state().b, 0x34);
+}
+
+#[test]
+fn breakpoints_watchpoints_and_interrupt() {
+    let (mut client, server) = start();
+
+    assert_eq!(client.send("Z2,2000,1"), "OK");
+    assert_eq!(client.send("c"), "T05watch:2000;");
+    assert_eq!(client.send("z2,2000,1"), "OK");
+
+    assert_eq!(client.send("Z0,8,1"), "OK");
+    assert_eq!(client.send("c"), "S05");
+    assert_eq!(client.send("p9"), "0800");
+    assert_eq!(client.send("z0,8,1"), "OK");
+
+    // The loop never stops on its own
+    client.stream.write_all(b"$c#63").unwrap();
+    client.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(client.receive(), "S02");
+
+    client.stream.write_all(b"$k#6b").unwrap();
+    server.join().unwrap();
+}
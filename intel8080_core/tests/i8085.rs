@@ -0,0 +1,197 @@
+use intel8080_core::{
+    assembler::assemble,
+    debugger::Debugger,
+    disassembler::{Flow, variant_opcode_info},
+    i8085::{InterruptPin, Variant},
+    processor::Processor,
+    profile::Profiler,
+    registers::Register,
+};
+
+mod common;
+
+use common::TestPort;
+
+// The assembler only knows 8080 mnemonics
+const RIM: &str = "DB 20H";
+const SIM: &str = "DB 30H";
+
+fn load(source: &str, variant: Variant) -> Processor {
+    let assembly = assemble(source).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor
+        .load_rom(&assembly.to_binary(), assembly.origin())
+        .unwrap();
+    processor.set_register(Register::PC, 0x100);
+    processor.set_variant(variant);
+
+    processor
+}
+
+#[test]
+fn services_8085_interrupts_by_priority_and_mask() {
+    let source = format!(
+        "
+        ORG 24H
+        HLT
+        ORG 34H
+        HLT
+        ORG 3CH
+        HLT
+        ORG 100H
+        LXI SP,2000H
+        MVI A,0CDH      ; SOD high, unmask 6.5 only
+        {SIM}
+        EI
+        NOP
+        NOP
+        {RIM}
+        "
+    );
+    let mut processor = load(&source, Variant::Intel8085);
+    for _ in 0..5 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+    assert!(processor.sod());
+
+    // EI has taken effect after the first NOP. RST 5.5 is masked and RST 7.5
+    // stays latched, so 6.5 wins
+    processor.set_interrupt_pin(InterruptPin::Rst55, true);
+    processor.set_interrupt_pin(InterruptPin::Rst75, true);
+    processor.set_interrupt_pin(InterruptPin::Rst75, false);
+    processor.set_interrupt_pin(InterruptPin::Rst65, true);
+    assert_eq!(processor.execute(&mut TestPort).unwrap(), 12);
+    assert_eq!(processor.pc(), 0x34);
+    assert!(!processor.interrupts_enabled());
+
+    // TRAP ignores the interrupt enable and wakes HLT
+    processor.execute(&mut TestPort).unwrap();
+    assert!(processor.is_halted());
+    processor.set_interrupt_pin(InterruptPin::Trap, true);
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.pc(), 0x24);
+
+    // The latch is cleared once serviced, holding the pin high does nothing
+    processor.set_register(Register::PC, 0x109);
+    processor.set_interrupt_pin(InterruptPin::Rst65, false);
+    processor.set_interrupt_pin(InterruptPin::Rst55, false);
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.pc(), 0x10A);
+
+    // Pending 7.5, masks 7.5 and 5.5, and interrupts were off before TRAP
+    assert_eq!(processor.register(Register::A), 0b0100_0101);
+}
+
+#[test]
+fn runs_undocumented_8085_opcodes() {
+    let source = "
+        ORG 100H
+        LXI H,1234H
+        LXI B,0235H
+        DB 08H          ; DSUB
+        DB 28H,10H      ; LDHI 10H
+        DB 0D9H         ; SHLX
+        LXI H,0
+        DB 0EDH         ; LHLX
+        LXI D,0
+        DCX D
+        DB 0DDH         ; JNK 0
+        DW 0
+        LXI B,8000H
+        DB 10H          ; ARHL
+        ";
+    let mut processor = load(source, Variant::Intel8085Undocumented);
+
+    let cycles: Vec<u32> = (0..9)
+        .map(|_| processor.execute(&mut TestPort).unwrap())
+        .collect();
+    assert_eq!(cycles, [10, 10, 10, 10, 10, 10, 10, 10, 6]);
+    assert_eq!(processor.register(Register::HL), 0x0FFF);
+    assert_eq!(processor.register(Register::DE), 0xFFFF);
+
+    // DCX wrapping to FFFF sets K, so JNK falls through
+    assert!(processor.flags().k);
+    assert_eq!(processor.execute(&mut TestPort).unwrap(), 7);
+    assert_eq!(processor.pc(), 0x115);
+
+    processor.execute(&mut TestPort).unwrap();
+    processor.set_register(Register::HL, 0x8001);
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.register(Register::HL), 0xC000);
+    assert!(processor.flags().cy);
+
+    // The plain 8085 leaves the undocumented opcodes unknown
+    let mut processor = load(source, Variant::Intel8085);
+    processor.set_register(Register::PC, 0x106);
+    assert!(processor.execute(&mut TestPort).is_err());
+}
+
+#[test]
+fn rstv_is_followed_as_a_call() {
+    // ADI overflows into V, so RSTV calls 0x40
+    let source = "
+        ORG 40H
+        INR B
+        RET
+        ORG 100H
+        LXI SP,1000H
+        MVI A,7FH
+        ADI 1
+        DB 0CBH
+        HLT
+    ";
+    let info = variant_opcode_info(Variant::Intel8085Undocumented, 0xCB);
+    assert_eq!((info.mnemonic, info.flow), ("RSTV", Flow::Restart));
+    assert_eq!(
+        variant_opcode_info(Variant::Intel8080, 0xCB).flow,
+        Flow::Jump
+    );
+
+    let mut processor = load(source, Variant::Intel8085Undocumented);
+    processor.set_profiler(Some(Profiler::new()));
+    for _ in 0..6 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+    let subroutine = processor.profiler().unwrap().subroutine(Some(0x40));
+    assert_eq!(subroutine.calls, 1);
+    assert_eq!(subroutine.total_cycles, 4 + 10);
+
+    // Stepping over RSTV runs the handler
+    let mut debugger = Debugger::new(load(source, Variant::Intel8085Undocumented));
+    for _ in 0..3 {
+        debugger.step(&mut TestPort).unwrap();
+    }
+    debugger.step_over(&mut TestPort).unwrap();
+    assert_eq!(debugger.processor().pc(), 0x108);
+    assert_eq!(debugger.processor().register(Register::B), 1);
+}
+
+#[test]
+fn and_always_sets_auxiliary_carry() {
+    // Neither operand has bit 3 set, which leaves AC clear on the 8080
+    let source = "
+        ORG 100H
+        MVI A,0F0H
+        ANI 0F0H
+        MVI B,30H
+        ANA B
+        ";
+
+    for (variant, ac) in [
+        (Variant::Intel8080, false),
+        (Variant::Intel8085, true),
+        (Variant::Intel8085Undocumented, true),
+    ] {
+        let mut processor = load(source, variant);
+        processor.execute(&mut TestPort).unwrap();
+        processor.execute(&mut TestPort).unwrap();
+        assert_eq!(processor.flags().ac, ac, "ANI on {variant:?}");
+
+        processor.execute(&mut TestPort).unwrap();
+        processor.execute(&mut TestPort).unwrap();
+        let flags = processor.flags();
+        assert_eq!(processor.register(Register::A), 0x30);
+        assert_eq!(flags.ac, ac, "ANA on {variant:?}");
+        assert!(!flags.cy && !flags.z && !flags.s && flags.p);
+    }
+}
@@ -0,0 +1,126 @@
+use intel8080_core::{assembler::assemble, cpm::CpmMachine, registers::CpuState};
+
+/// Assembles a program fragment at 0x0100, runs it until it returns to CP/M
+/// and returns the final processor state
+fn run(source: &str) -> CpuState {
+    let source = format!(" ORG 100H\n LXI SP,0F000H\n{source}\n JMP 0\n");
+    let assembly = assemble(&source).unwrap();
+
+    let mut machine = CpmMachine::new(&assembly.to_binary(), Vec::new()).unwrap();
+    machine.run().unwrap();
+    machine.processor().state()
+}
+
+#[test]
+fn ana_sets_aux_carry_from_operands() {
+    let state = run(" MVI A,08H\n MVI B,00H\n ANA B");
+    assert_eq!(state.a, 0x00);
+    assert!(state.flags.z);
+    assert!(state.flags.ac);
+    assert!(!state.flags.cy);
+
+    let state = run(" MVI A,0F0H\n ANI 07H");
+    assert!(!state.flags.ac);
+}
+
+#[test]
+fn sub_aux_carry_is_inverted_borrow() {
+    let state = run(" MVI A,10H\n SUI 01H");
+    assert_eq!(state.a, 0x0F);
+    assert!(!state.flags.ac);
+    assert!(!state.flags.cy);
+
+    let state = run(" MVI A,15H\n MVI B,03H\n SUB B");
+    assert_eq!(state.a, 0x12);
+    assert!(state.flags.ac);
+
+    let state = run(" MVI A,02H\n CPI 03H");
+    assert_eq!(state.a, 0x02);
+    assert!(state.flags.cy);
+    assert!(state.flags.s);
+}
+
+#[test]
+fn adc_and_sbb_include_carry() {
+    let state = run(" STC\n MVI A,01H\n ACI 0FFH");
+    assert_eq!(state.a, 0x01);
+    assert!(state.flags.cy);
+    assert!(state.flags.ac);
+
+    let state = run(" STC\n MVI A,00H\n SBI 00H\n MOV B,A");
+    assert_eq!(state.b, 0xFF);
+    assert!(state.flags.cy);
+
+    let state = run(" STC\n MVI A,10H\n MVI C,0FH\n SBB C");
+    assert_eq!(state.a, 0x00);
+    assert!(state.flags.z);
+    assert!(!state.flags.cy);
+}
+
+#[test]
+fn inr_and_dcr_aux_carry() {
+    let state = run(" MVI A,0FH\n INR A");
+    assert_eq!(state.a, 0x10);
+    assert!(state.flags.ac);
+
+    let state = run(" MVI A,10H\n DCR A");
+    assert_eq!(state.a, 0x0F);
+    assert!(!state.flags.ac);
+
+    let state = run(" MVI A,11H\n DCR A");
+    assert!(state.flags.ac);
+}
+
+#[test]
+fn daa_adjusts_bcd_addition() {
+    let state = run(" MVI A,09H\n ADI 08H\n DAA");
+    assert_eq!(state.a, 0x17);
+    assert!(!state.flags.cy);
+
+    let state = run(" MVI A,99H\n ADI 01H\n DAA");
+    assert_eq!(state.a, 0x00);
+    assert!(state.flags.z);
+    assert!(state.flags.cy);
+
+    // A set carry is kept even if the adjustment does not overflow
+    let state = run(" MVI A,00H\n STC\n DAA");
+    assert_eq!(state.a, 0x60);
+    assert!(state.flags.cy);
+}
+
+#[test]
+fn push_psw_stores_accumulator_in_high_byte() {
+    let state = run(" MVI A,12H\n STC\n PUSH PSW\n POP B");
+    assert_eq!(state.b, 0x12);
+    assert_eq!(state.c, 0x03);
+
+    let state = run(" LXI D,0D7FFH\n PUSH D\n POP PSW");
+    assert_eq!(state.a, 0xD7);
+    assert!(state.flags.s && state.flags.z && state.flags.ac && state.flags.p && state.flags.cy);
+}
+
+#[test]
+fn pchl_jumps_to_hl() {
+    let state = run(" LXI H,TARGET\n PCHL\n MVI A,01H\n JMP 0\nTARGET: MVI A,42H");
+    assert_eq!(state.a, 0x42);
+}
+
+#[test]
+fn conditional_calls_and_returns() {
+    let source = "
+        MVI B,0
+        XRA A
+        CZ INCB
+        XRA A
+        CNZ INCB
+        STC
+        CC INCB
+        JMP 0
+INCB:   INR B
+        RNC
+        INR B
+        RET";
+    let state = run(source);
+    assert_eq!(state.b, 3);
+    assert_eq!(state.sp, 0xF000);
+}
@@ -0,0 +1,39 @@
+use intel8080_core::{
+    assembler::assemble,
+    errors::Error,
+    intel_hex::{Segment, decode, encode},
+};
+
+#[test]
+fn encodes_records_with_checksums() {
+    let text = encode(&[Segment {
+        address: 0x0100,
+        bytes: vec![0x01, 0x02, 0x03],
+    }]);
+    assert_eq!(text, ":03010000010203F6\n:00000001FF\n");
+
+    // Records hold at most 16 bytes
+    let assembly = assemble(" ORG 0FF0H\n DB 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16").unwrap();
+    let text = assembly.to_intel_hex();
+    let records: Vec<&str> = text.lines().collect();
+    assert_eq!(records.len(), 3);
+    assert!(records[0].starts_with(":100FF000"));
+    assert_eq!(records[1], ":0110000010DF");
+    assert_eq!(decode(&text).unwrap().len(), 2);
+}
+
+#[test]
+fn decodes_what_it_encodes() {
+    let assembly = assemble(" ORG 100H\n LXI H,2400H\n ORG 2000H\n DB 'data'").unwrap();
+    let segments = decode(&assembly.to_intel_hex()).unwrap();
+    assert_eq!(segments, assembly.segments);
+
+    assert!(matches!(
+        decode(":03010000010203F7\n:00000001FF\n"),
+        Err(Error::IntelHex { line_num: 1, .. })
+    ));
+    assert!(matches!(
+        decode(":03010000010203F6\n"),
+        Err(Error::IntelHex { message, .. }) if message == "missing EOF record"
+    ));
+}
@@ -0,0 +1,132 @@
+use intel8080_core::{
+    assembler::assemble, helpers::rst_instruction, processor::Processor, registers::Register,
+};
+
+mod common;
+
+use common::TestPort;
+
+fn load(source: &str) -> Processor {
+    let assembly = assemble(source).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor
+        .load_rom(&assembly.to_binary(), assembly.origin())
+        .unwrap();
+
+    processor
+}
+
+#[test]
+fn halt_idles_until_an_interrupt() {
+    let mut processor = load(
+        "
+        ORG 0
+        LXI SP,1000H
+        EI
+        HLT
+        MVI A,1
+        ",
+    );
+    for _ in 0..3 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+    assert!(processor.is_halted());
+
+    // Every call while halted burns 4 cycles and leaves PC past the HLT
+    for _ in 0..3 {
+        assert_eq!(processor.execute(&mut TestPort).unwrap(), 4);
+    }
+    assert!(processor.is_halted());
+    assert_eq!(processor.pc(), 5);
+
+    let cycles = processor
+        .interrupt(&[rst_instruction(1)], &mut TestPort)
+        .unwrap();
+    assert_eq!(cycles, Some(11));
+    assert!(!processor.is_halted());
+    assert_eq!(processor.pc(), 0x08);
+    assert_eq!(processor.memory_slice(0x0FFE, 2).unwrap(), [0x05, 0x00]);
+}
+
+#[test]
+fn halt_with_interrupts_disabled_stays_halted() {
+    let mut processor = load(" ORG 0\n DI\n HLT\n");
+    processor.execute(&mut TestPort).unwrap();
+    processor.execute(&mut TestPort).unwrap();
+
+    let cycles = processor
+        .interrupt(&[rst_instruction(1)], &mut TestPort)
+        .unwrap();
+    assert_eq!(cycles, None);
+    assert!(processor.is_halted());
+    assert_eq!(processor.execute(&mut TestPort).unwrap(), 4);
+    assert_eq!(processor.register(Register::PC), 2);
+}
+
+#[test]
+fn inta_charges_the_injected_instruction() {
+    let mut processor = load(" ORG 0\n LXI SP,1000H\n EI\n NOP\n NOP\n");
+    for _ in 0..3 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+
+    let cycles = processor.interrupt(&[0xD7], &mut TestPort).unwrap();
+    assert_eq!(cycles, Some(11));
+    assert_eq!(processor.pc(), 0x10);
+    assert!(!processor.interrupts_enabled());
+
+    // A 3-byte CALL as supplied by an 8228 or 8259, PC is not advanced
+    processor.set_interrupts_enabled(true);
+    let cycles = processor
+        .interrupt(&[0xCD, 0x34, 0x12], &mut TestPort)
+        .unwrap();
+    assert_eq!(cycles, Some(17));
+    assert_eq!(processor.pc(), 0x1234);
+    assert_eq!(processor.memory_slice(0x0FFC, 2).unwrap(), [0x10, 0x00]);
+}
+
+#[test]
+fn interrupts_wait_out_the_ei_shadow() {
+    let mut processor = load(" ORG 0\n DI\n NOP\n EI\n NOP\n NOP\n");
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.interrupt(&[0xFF], &mut TestPort).unwrap(), None);
+    processor.execute(&mut TestPort).unwrap();
+
+    // Not accepted between EI and the instruction after it
+    processor.execute(&mut TestPort).unwrap();
+    assert!(processor.interrupts_enabled());
+    assert_eq!(processor.interrupt(&[0xFF], &mut TestPort).unwrap(), None);
+
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.pc(), 4);
+    assert_eq!(
+        processor.interrupt(&[0xFF], &mut TestPort).unwrap(),
+        Some(11)
+    );
+    assert_eq!(processor.pc(), 0x38);
+}
+
+#[test]
+fn held_intr_is_serviced_once_ei_takes_effect() {
+    let mut processor = load(" ORG 0\n LXI SP,1000H\n NOP\n EI\n NOP\n NOP\n");
+    processor.execute(&mut TestPort).unwrap();
+    processor.set_intr(&[rst_instruction(2)]);
+
+    // Runs NOP, EI and the instruction after EI with the line held
+    for _ in 0..3 {
+        assert_eq!(processor.execute(&mut TestPort).unwrap(), 4);
+        assert!(!processor.take_inta());
+    }
+    assert_eq!(processor.pc(), 6);
+
+    assert_eq!(processor.execute(&mut TestPort).unwrap(), 11);
+    assert_eq!(processor.pc(), 0x10);
+    assert_eq!(processor.memory_slice(0x0FFE, 2).unwrap(), [0x06, 0x00]);
+
+    // The device sees one acknowledge and drops the line
+    assert!(processor.take_inta());
+    assert!(!processor.take_inta());
+    assert!(processor.intr_pending());
+    processor.clear_intr();
+    assert!(!processor.intr_pending());
+}
@@ -0,0 +1,98 @@
+use intel8080_core::{
+    bus::{Access, Bus},
+    errors::Error,
+    memory::{Fault, FaultPolicy, Memory},
+};
+
+// 8 KiB of ROM followed by 8 KiB of RAM, everything above is unmapped
+fn memory() -> Memory {
+    let mut memory = Memory::new(0x4000, |address| (address as usize, address < 0x2000));
+    memory.load_rom(&[0xC3, 0x00, 0x00], 0).unwrap();
+
+    memory
+}
+
+#[test]
+fn errors_say_what_was_accessed() {
+    let mut memory = memory();
+
+    assert!(matches!(
+        memory.write(0x0001, 0),
+        Err(Error::RomWrite {
+            address: 0x0001,
+            region: None
+        })
+    ));
+    assert!(matches!(
+        memory.read(0x8000),
+        Err(Error::UnmappedRead {
+            address: 0x8000,
+            ..
+        })
+    ));
+    assert!(matches!(
+        memory.write(0x8000, 0),
+        Err(Error::UnmappedWrite {
+            address: 0x8000,
+            ..
+        })
+    ));
+    assert_eq!(memory.read(0x0001).unwrap(), 0x00);
+}
+
+#[test]
+fn ignores_and_logs_faults() {
+    let mut memory = memory();
+    memory.set_rom_write_policy(FaultPolicy::Ignore);
+    memory.set_unmapped_policy(FaultPolicy::Log);
+    memory.set_open_bus(0x5A);
+
+    memory.write(0x0000, 0x00).unwrap();
+    assert_eq!(memory.read(0x0000).unwrap(), 0xC3);
+
+    assert_eq!(memory.read(0x9000).unwrap(), 0x5A);
+    memory.write(0xFFFF, 0x12).unwrap();
+    assert_eq!(memory.peek(0x9000), None);
+
+    let faults = memory.take_faults();
+    assert_eq!(
+        faults,
+        [
+            Fault::UnmappedRead { address: 0x9000 },
+            Fault::UnmappedWrite {
+                address: 0xFFFF,
+                value: 0x12
+            },
+        ]
+    );
+    assert_eq!(faults[1].access(), Access::Write);
+    assert!(memory.take_faults().is_empty());
+}
+
+#[test]
+fn callback_decides_faults() {
+    // Unmapped reads see the high byte of the address plus the number of
+    // earlier faults, writes fail
+    let mut count = 0;
+    let callback = move |fault: Fault| {
+        count += 1;
+        match fault {
+            Fault::UnmappedRead { address } => Ok((address >> 8) as u8 + count - 1),
+            _ => Err(Error::UnmappedWrite {
+                address: fault.address(),
+                region: None,
+            }),
+        }
+    };
+
+    let mut memory = memory();
+    memory.set_unmapped_policy(FaultPolicy::callback(callback));
+
+    assert_eq!(memory.read(0xA123).unwrap(), 0xA1);
+    assert!(memory.write(0xA123, 0).is_err());
+
+    // Clones share the callback and its state
+    let mut clone = memory.clone();
+    assert_eq!(clone.read(0xA123).unwrap(), 0xA3);
+    assert_eq!(memory.read(0xA123).unwrap(), 0xA4);
+}
@@ -0,0 +1,123 @@
+use intel8080_core::{
+    assembler::assemble,
+    helpers::rst_instruction,
+    processor::Processor,
+    profile::{Counter, Profiler},
+    registers::Register,
+};
+
+mod common;
+
+use common::TestPort;
+
+const PROGRAM: &str = "
+        ORG 0
+        JMP START
+        ORG 8
+ISR:    PUSH PSW
+        POP PSW
+        EI
+        RET
+        ORG 100H
+START:  LXI SP,2400H
+        EI
+LOOP:   CALL WORK
+        JMP LOOP
+WORK:   MVI B,2
+AGAIN:  DCR B
+        JNZ AGAIN
+        RET
+";
+
+fn profiled_processor() -> (Processor, u16, u16) {
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+    processor.set_register(Register::PC, 0);
+    processor.set_profiler(Some(Profiler::new()));
+
+    (
+        processor,
+        assembly.symbols["LOOP"],
+        assembly.symbols["WORK"],
+    )
+}
+
+#[test]
+fn counts_addresses_opcodes_and_subroutines() {
+    let (mut processor, lp, work) = profiled_processor();
+
+    // JMP, LXI, EI, then two passes through the loop
+    for _ in 0..3 + 2 * 8 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+    assert_eq!(processor.pc(), lp);
+
+    let profiler = processor.profiler().unwrap();
+    assert_eq!(profiler.instructions(), 19);
+    assert_eq!(
+        profiler.address(lp),
+        Counter {
+            count: 2,
+            cycles: 34
+        }
+    );
+    // DCR B runs twice per call
+    assert_eq!(profiler.opcode(0x05).count, 4);
+
+    // MVI, 2 DCR, 2 JNZ and RET per call
+    let subroutine = profiler.subroutine(Some(work));
+    assert_eq!(subroutine.calls, 2);
+    assert_eq!(subroutine.self_cycles, 2 * (7 + 2 * 5 + 2 * 10 + 10));
+    assert_eq!(subroutine.total_cycles, subroutine.self_cycles);
+
+    let top = profiler.subroutine(None);
+    assert_eq!(top.self_cycles, 10 + 10 + 4 + 2 * (17 + 10));
+    assert_eq!(profiler.hot_subroutines()[0].0, Some(work));
+}
+
+#[test]
+fn attributes_interrupt_handlers() {
+    let (mut processor, _, work) = profiled_processor();
+    for _ in 0..4 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+
+    // Interrupt WORK, the handler's cycles must not count towards WORK
+    assert_eq!(processor.pc(), work);
+    processor
+        .interrupt(&[rst_instruction(1)], &mut TestPort)
+        .unwrap()
+        .unwrap();
+    while processor.pc() != work {
+        processor.execute(&mut TestPort).unwrap();
+    }
+
+    let profiler = processor.profiler().unwrap();
+    let handler = profiler.subroutine(Some(0x08));
+    assert_eq!(handler.calls, 1);
+    // PUSH, POP, EI and RET, the RST itself belongs to WORK
+    assert_eq!(handler.self_cycles, 11 + 10 + 4 + 10);
+    assert_eq!(handler.total_cycles, handler.self_cycles);
+    assert_eq!(profiler.subroutine(Some(work)).self_cycles, 11);
+    assert_eq!(profiler.address(0x08).count, 1);
+
+    let mut csv = Vec::new();
+    profiler.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("kind,key,name,count,cycles,total_cycles,percent")
+    );
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .starts_with("subroutine,0008,,1,35,35,")
+    );
+    assert!(csv.contains("\nopcode,F5,PUSH,1,11,,"));
+
+    let report = profiler.report(3);
+    assert!(report.contains("0008"), "{report}");
+}
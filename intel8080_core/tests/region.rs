@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex};
+
+use intel8080_core::{
+    bus::Bus,
+    errors::Result,
+    memory::Memory,
+    region::{Region, RegionKind, RegionMap},
+};
+
+// Space Invaders style board: ROM, then 8 KiB of RAM, mirrored up to 0xFFFF
+fn invaders() -> Memory {
+    let regions = RegionMap::builder()
+        .rom("invaders.h", 0x0000..=0x07FF)
+        .rom("invaders.g", 0x0800..=0x0FFF)
+        .ram("RAM", 0x2000..=0x3FFF)
+        .mirror("mirror", 0x4000..=0xFFFF, 0x3FFF)
+        .build()
+        .unwrap();
+    let mut memory = Memory::with_regions(regions);
+    memory.load_rom(&[0xC3, 0x00, 0x00], 0).unwrap();
+
+    memory
+}
+
+#[test]
+fn mirrors_reach_the_regions_beneath() {
+    let mut memory = invaders();
+    assert_eq!(memory.size(), 0x4000);
+
+    memory.write(0x6001, 0x42).unwrap();
+    assert_eq!(memory.read(0x2001).unwrap(), 0x42);
+    assert_eq!(memory.read(0xE001).unwrap(), 0x42);
+    assert_eq!(memory.read(0x4000).unwrap(), 0xC3);
+
+    assert_eq!(memory.region(0x0123).unwrap().name, "invaders.h");
+    assert_eq!(memory.region(0xA000).unwrap().name, "RAM");
+}
+
+#[test]
+fn errors_name_the_region() {
+    let mut memory = invaders();
+
+    let error = memory.write(0x0123, 0).unwrap_err();
+    assert_eq!(error.to_string(), "Write to ROM 'invaders.h' at 0x0123");
+    let error = memory.write(0x4923, 0).unwrap_err();
+    assert_eq!(error.to_string(), "Write to ROM 'invaders.g' at 0x4923");
+}
+
+#[test]
+fn mmio_regions_reach_the_device() {
+    // Latches the last write and reads back its complement
+    #[derive(Default)]
+    struct Latch(u8);
+
+    impl Bus for Latch {
+        fn read(&mut self, _address: u16) -> Result<u8> {
+            Ok(!self.0)
+        }
+
+        fn write(&mut self, address: u16, value: u8) -> Result<()> {
+            self.0 = value.wrapping_add(address as u8);
+            Ok(())
+        }
+    }
+
+    let regions = RegionMap::builder()
+        .ram("RAM", 0x0000..=0x0FFF)
+        .region(Region::new("latch", 0x8000..=0x80FF, RegionKind::Mmio).with_mask(0x00FF))
+        .region(Region::new("hole", 0x9000..=0x9FFF, RegionKind::Unmapped))
+        .build()
+        .unwrap();
+    let mut memory = Memory::with_regions(regions);
+    assert!(memory.write(0x8001, 0x10).is_err());
+
+    let latch = Arc::new(Mutex::new(Latch::default()));
+    memory.set_mmio(latch.clone());
+    memory.write(0x8001, 0x10).unwrap();
+    assert_eq!(memory.read(0x8000).unwrap(), !0x11);
+    assert_eq!(memory.size(), 0x1000);
+
+    // The host sees the device state, clones of the memory share the device
+    assert_eq!(latch.lock().unwrap().0, 0x11);
+    memory.clone().write(0x8000, 0x20).unwrap();
+    assert_eq!(latch.lock().unwrap().0, 0x20);
+
+    let error = memory.write(0x9000, 0).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Write to unmapped memory 'hole' at 0x9000"
+    );
+}
+
+#[test]
+fn rejects_regions_reaching_past_the_address_space() {
+    let error = RegionMap::builder()
+        .ram("ram", 0x0000..=0x0FFF)
+        .region(Region::new("mirror", 0xF000..=0xFFFF, RegionKind::Mirror).with_offset(0x1000))
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid region map: region 'mirror' reaches past 0xFFFF"
+    );
+
+    let error = RegionMap::builder()
+        .region(Region::new("io", 0xFF00..=0xFFFF, RegionKind::Mmio).with_offset(0x100))
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid region map: region 'io' reaches past 0xFFFF"
+    );
+
+    // Masking keeps the targets in range
+    assert!(
+        RegionMap::builder()
+            .ram("ram", 0x0000..=0x0FFF)
+            .region(
+                Region::new("mirror", 0xF000..=0xFFFF, RegionKind::Mirror)
+                    .with_mask(0x0FFF)
+                    .with_offset(0x1000)
+            )
+            .build()
+            .is_ok()
+    );
+}
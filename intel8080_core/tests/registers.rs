@@ -0,0 +1,64 @@
+use intel8080_core::{
+    processor::Processor,
+    registers::{Flags, Register},
+};
+
+fn processor() -> Processor {
+    Processor::new(0x10000, |address| (address as usize, false))
+}
+
+#[test]
+fn flag_bytes_keep_the_fixed_bits() {
+    // Bit 1 always reads 1, bits 3 and 5 always read 0
+    assert_eq!(Flags::from_byte(0xFF).to_byte(), 0xD7);
+    assert_eq!(Flags::from_byte(0x00).to_byte(), 0x02);
+
+    let flags = Flags::from_byte(0x85);
+    assert!(flags.s && flags.p && flags.cy);
+    assert!(!flags.z && !flags.ac);
+    assert_eq!(Flags::from_byte(flags.to_byte()), flags);
+
+    // The 8085 reuses bits 1 and 5 for V and K
+    let flags = Flags::from_byte_8085(0xFF);
+    assert!(flags.v && flags.k);
+    assert_eq!(flags.to_byte_8085(), 0xF7);
+    assert_eq!(flags.to_byte(), 0xD7);
+}
+
+#[test]
+fn psw_combines_a_and_flags() {
+    let mut processor = processor();
+    processor.set_register(Register::PSW, 0x12FF);
+
+    assert_eq!(processor.register(Register::PSW), 0x12D7);
+    assert_eq!(processor.register(Register::A), 0x12);
+    assert_eq!(processor.register(Register::F), 0xD7);
+    assert!(processor.flags().z);
+
+    processor.set_register(Register::F, 0x00);
+    assert_eq!(processor.register(Register::PSW), 0x1202);
+}
+
+#[test]
+fn register_pairs_split_into_bytes() {
+    let mut processor = processor();
+    processor.set_register(Register::BC, 0x1234);
+    processor.set_register(Register::DE, 0x5678);
+    processor.set_register(Register::HL, 0x9ABC);
+
+    assert_eq!(processor.register(Register::B), 0x12);
+    assert_eq!(processor.register(Register::C), 0x34);
+    assert_eq!(processor.register(Register::D), 0x56);
+    assert_eq!(processor.register(Register::L), 0xBC);
+
+    processor.set_register(Register::H, 0xFFEE);
+    assert_eq!(processor.register(Register::HL), 0xEEBC);
+
+    let state = processor.state();
+    assert_eq!(
+        (state.b, state.c, state.h, state.l),
+        (0x12, 0x34, 0xEE, 0xBC)
+    );
+    assert_eq!("hl".parse::<Register>().unwrap(), Register::HL);
+    assert!("IX".parse::<Register>().is_err());
+}
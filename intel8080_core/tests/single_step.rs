@@ -0,0 +1,247 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use intel8080_core::{
+    port::Port,
+    processor::Processor,
+    registers::{Flags, Register},
+};
+use serde_json::Value;
+
+/// Registers stored in a vector state, in the order they are compared
+const REGISTERS: [Register; 10] = [
+    Register::A,
+    Register::B,
+    Register::C,
+    Register::D,
+    Register::E,
+    Register::H,
+    Register::L,
+    Register::F,
+    Register::SP,
+    Register::PC,
+];
+
+/// Failing vectors listed in the assertion message
+const MAX_REPORTED: usize = 20;
+
+/// Port that replays the reads listed in a vector and records its writes
+struct VectorPort {
+    reads: RefCell<VecDeque<(u8, u8)>>,
+    writes: Vec<(u8, u8)>,
+}
+
+impl Port for VectorPort {
+    fn read_in(&self, port_num: u8) -> u8 {
+        match self.reads.borrow_mut().pop_front() {
+            Some((expected_port, value)) if expected_port == port_num => value,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_out(&mut self, port_num: u8, value: u8) {
+        self.writes.push((port_num, value));
+    }
+}
+
+fn vector_dir() -> PathBuf {
+    env::var_os("I8080_VECTOR_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/vectors"))
+}
+
+fn field(state: &Value, name: &str) -> u16 {
+    state[name]
+        .as_u64()
+        .unwrap_or_else(|| panic!("missing field '{name}'")) as u16
+}
+
+fn ram(state: &Value) -> Vec<(u16, u8)> {
+    state["ram"]
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|entry| {
+                    (
+                        entry[0].as_u64().unwrap() as u16,
+                        entry[1].as_u64().unwrap() as u8,
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Port accesses listed in a vector with the given direction ("r" or "w")
+fn ports(vector: &Value, direction: &str) -> Vec<(u8, u8)> {
+    vector["ports"]
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter(|entry| entry[2].as_str() == Some(direction))
+                .map(|entry| {
+                    (
+                        entry[0].as_u64().unwrap() as u8,
+                        entry[1].as_u64().unwrap() as u8,
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn flag_differences(found: u8, expected: u8) -> String {
+    let found = Flags::from_byte(found);
+    let expected = Flags::from_byte(expected);
+
+    [
+        ("S", found.s, expected.s),
+        ("Z", found.z, expected.z),
+        ("AC", found.ac, expected.ac),
+        ("P", found.p, expected.p),
+        ("CY", found.cy, expected.cy),
+    ]
+    .iter()
+    .filter(|(_, found, expected)| found != expected)
+    .map(|(name, found, expected)| {
+        format!("{name}={} (expected {})", *found as u8, *expected as u8)
+    })
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Runs a single vector, returning a description of every mismatch
+fn run_vector(vector: &Value) -> Vec<String> {
+    let initial = &vector["initial"];
+    let expected = &vector["final"];
+
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    for (address, value) in ram(initial) {
+        processor.load_rom(&[value], address).unwrap();
+    }
+    for register in REGISTERS {
+        processor.set_register(register, field(initial, &register.name().to_lowercase()));
+    }
+
+    let mut port = VectorPort {
+        reads: RefCell::new(ports(vector, "r").into()),
+        writes: Vec::new(),
+    };
+
+    let cycles = match processor.execute(&mut port) {
+        Ok(cycles) => cycles,
+        Err(error) => return vec![format!("execute failed: {error}")],
+    };
+
+    let mut mismatches = Vec::new();
+
+    for register in REGISTERS {
+        let found = processor.register(register);
+        let wanted = field(expected, &register.name().to_lowercase());
+        if found == wanted {
+            continue;
+        }
+
+        if register == Register::F {
+            mismatches.push(format!(
+                "F={found:02X} (expected {wanted:02X}): {}",
+                flag_differences(found as u8, wanted as u8)
+            ));
+        } else {
+            mismatches.push(format!("{register}={found:04X} (expected {wanted:04X})"));
+        }
+    }
+
+    for (address, wanted) in ram(expected) {
+        let found = processor.memory_slice(address, 1).unwrap()[0];
+        if found != wanted {
+            mismatches.push(format!(
+                "RAM[{address:04X}]={found:02X} (expected {wanted:02X})"
+            ));
+        }
+    }
+
+    let wanted_writes = ports(vector, "w");
+    if port.writes != wanted_writes {
+        mismatches.push(format!(
+            "port writes {:?} (expected {wanted_writes:?})",
+            port.writes
+        ));
+    }
+
+    if let Some(bus_cycles) = vector["cycles"].as_array()
+        && cycles as usize != bus_cycles.len()
+    {
+        mismatches.push(format!("cycles={cycles} (expected {})", bus_cycles.len()));
+    }
+
+    mismatches
+}
+
+/// Runs every vector in a file, returning one line per failing vector
+fn run_file(path: &Path) -> Vec<String> {
+    let text = fs::read_to_string(path).unwrap();
+    let vectors: Vec<Value> = serde_json::from_str(&text)
+        .unwrap_or_else(|error| panic!("{} is not a vector file: {error}", path.display()));
+
+    vectors
+        .iter()
+        .filter_map(|vector| {
+            let mismatches = run_vector(vector);
+            if mismatches.is_empty() {
+                return None;
+            }
+
+            let name = vector["name"].as_str().unwrap_or("unnamed");
+            let opcode = ram(&vector["initial"])
+                .into_iter()
+                .find(|&(address, _)| address == field(&vector["initial"], "pc"))
+                .map(|(_, opcode)| format!("{opcode:02X}"))
+                .unwrap_or_else(|| "??".to_string());
+
+            Some(format!(
+                "{} '{name}' opcode {opcode}: {}",
+                path.file_name().unwrap().to_string_lossy(),
+                mismatches.join("; ")
+            ))
+        })
+        .collect()
+}
+
+#[test]
+fn single_step_vectors() {
+    let dir = vector_dir();
+    let Ok(entries) = fs::read_dir(&dir) else {
+        eprintln!("Skipping test vectors, {} not found", dir.display());
+        return;
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    let failures: Vec<String> = paths.iter().flat_map(|path| run_file(path)).collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} failing vectors:\n{}",
+        failures.len(),
+        failures
+            .iter()
+            .take(MAX_REPORTED)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+}
@@ -0,0 +1,156 @@
+use intel8080_core::{
+    assembler::assemble,
+    bus::Bus,
+    errors::{Error, Result},
+    i8085::{InterruptPin, Variant},
+    processor::{Processor, UndocumentedOpcodes},
+    registers::{CpuState, Register},
+    snapshot::SNAPSHOT_VERSION,
+};
+
+mod common;
+
+use common::TestPort;
+
+fn blank(size: usize) -> Processor {
+    Processor::new(size, |address| (address as usize, false))
+}
+
+/// Processor halted with interrupts enabled and INTR held, after storing
+/// to RAM
+fn running() -> Processor {
+    let assembly = assemble(
+        "
+        ORG 0
+        LXI SP,1000H
+        LXI H,2000H
+        MVI M,42H
+        MVI B,12H
+        STC
+        EI
+        HLT
+        ",
+    )
+    .unwrap();
+    let mut processor = blank(0x10000);
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+    for _ in 0..8 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+    processor.set_intr(&[0xCD, 0x00, 0x30]);
+
+    processor
+}
+
+#[test]
+fn round_trips_the_whole_machine() {
+    let processor = running();
+    let bytes = processor.save_state();
+
+    let mut restored = blank(0x10000);
+    restored.load_state(&bytes).unwrap();
+    assert_eq!(restored.state(), processor.state());
+    assert_eq!(restored.cycles(), processor.cycles());
+    assert_eq!(restored.memory_slice(0x2000, 1).unwrap(), [0x42]);
+    assert!(restored.is_halted());
+    assert!(restored.interrupts_enabled());
+    assert!(restored.flags().cy);
+    assert_eq!(restored.save_state(), bytes);
+
+    // The held INTR line is serviced after restoring
+    assert_eq!(restored.execute(&mut TestPort).unwrap(), 17);
+    assert_eq!(restored.register(Register::PC), 0x3000);
+    assert!(restored.take_inta());
+}
+
+#[test]
+fn round_trips_8085_pins_and_flags() {
+    // SOD high and RST 5.5 and 7.5 masked by SIM, then RIM
+    let assembly = assemble(" ORG 0\n MVI A,0CDH\n DB 30H\n NOP\n DB 20H\n").unwrap();
+    let mut processor = blank(0x10000);
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+    processor.set_variant(Variant::Intel8085Undocumented);
+    for _ in 0..3 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+    processor.set_interrupt_pin(InterruptPin::Rst75, true);
+    let mut flags = processor.flags();
+    (flags.v, flags.k) = (true, true);
+    processor.set_flags(flags);
+    let bytes = processor.save_state();
+
+    let mut restored = blank(0x10000);
+    restored.load_state(&bytes).unwrap();
+    assert_eq!(restored.variant(), Variant::Intel8085Undocumented);
+    assert!(restored.flags().v && restored.flags().k);
+    assert!(restored.sod());
+    assert_eq!(restored.save_state(), bytes);
+
+    // RIM sees the latched RST 7.5 request and the masks
+    assert_eq!(restored.execute(&mut TestPort).unwrap(), 4);
+    assert_eq!(restored.register(Register::A), 0x45);
+}
+
+#[test]
+fn rejects_foreign_and_truncated_data() {
+    let bytes = running().save_state();
+    let mut processor = blank(0x10000);
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'X';
+    assert!(matches!(
+        processor.load_state(&bad_magic),
+        Err(Error::SnapshotFormat)
+    ));
+    assert!(matches!(
+        processor.load_state(&bytes[..bytes.len() - 1]),
+        Err(Error::SnapshotFormat)
+    ));
+
+    let mut newer = bytes.clone();
+    newer[4] = SNAPSHOT_VERSION + 1;
+    assert!(matches!(
+        processor.load_state(&newer),
+        Err(Error::SnapshotVersion { found, supported: SNAPSHOT_VERSION })
+            if found == SNAPSHOT_VERSION + 1
+    ));
+}
+
+#[test]
+fn rejects_a_different_ram_size() {
+    let bytes = running().save_state();
+    let mut processor = blank(0x4000);
+
+    assert!(matches!(
+        processor.load_state(&bytes),
+        Err(Error::SnapshotRamSize {
+            snapshot_size: 0x10000,
+            ram_size: 0x4000
+        })
+    ));
+    assert_eq!(processor.register(Register::PC), 0);
+}
+
+#[test]
+fn callbacks_have_to_be_installed_again() {
+    fn callback(_opcode: u8, _state: &mut CpuState, _bus: &mut dyn Bus) -> Result<u32> {
+        Ok(4)
+    }
+
+    let mut processor = running();
+    processor.set_undocumented_opcodes(UndocumentedOpcodes::Callback(callback));
+    let bytes = processor.save_state();
+
+    assert!(matches!(
+        blank(0x10000).load_state(&bytes),
+        Err(Error::SnapshotCallback)
+    ));
+
+    let mut restored = blank(0x10000);
+    restored.set_undocumented_opcodes(UndocumentedOpcodes::Callback(callback));
+    restored.load_state(&bytes).unwrap();
+    assert!(matches!(
+        restored.undocumented_opcodes(),
+        UndocumentedOpcodes::Callback(_)
+    ));
+}
@@ -0,0 +1,131 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use intel8080_core::{
+    assembler::assemble,
+    i8085::Variant,
+    processor::Processor,
+    registers::Register,
+    trace::{TraceFormat, Tracer},
+};
+
+mod common;
+
+use common::TestPort;
+
+const PROGRAM: &str = "
+        ORG 100H
+        LXI SP,2400H
+        MVI B,3
+LOOP:   DCR B
+        JNZ LOOP
+        HLT
+";
+
+fn processor() -> Processor {
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0x100).unwrap();
+    processor.set_register(Register::PC, 0x100);
+
+    processor
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn reference_format_matches_common_traces() {
+    let buffer = SharedBuffer::default();
+    let mut processor = processor();
+    processor.set_tracer(Some(Tracer::to_writer(buffer.clone())));
+
+    for _ in 0..3 {
+        processor.execute(&mut TestPort).unwrap();
+    }
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "PC: 0100, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 0\t(31 00 24 06)",
+            "PC: 0103, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 2400, CYC: 10\t(06 03 05 C2)",
+            "PC: 0105, AF: 0002, BC: 0300, DE: 0000, HL: 0000, SP: 2400, CYC: 17\t(05 C2 05 01)",
+        ]
+    );
+}
+
+#[test]
+fn ring_buffer_keeps_filtered_window() {
+    let mut processor = processor();
+    processor.set_tracer(Some(
+        Tracer::ring_buffer(2)
+            .with_format(TraceFormat::Detailed)
+            .with_addresses(0x105..=0x105)
+            .with_window(0..7),
+    ));
+
+    while !processor.is_halted() {
+        processor.execute(&mut TestPort).unwrap();
+    }
+
+    let tracer = processor.tracer().unwrap();
+    assert_eq!(tracer.instructions(), 9);
+    assert_eq!(tracer.cycles(), 10 + 7 + 3 * (5 + 10) + 7);
+
+    // DCR B runs at instructions 2, 4 and 6, only the last 2 are kept
+    let lines: Vec<&str> = tracer.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(
+        lines[0].starts_with("0105  05        DCR B"),
+        "{}",
+        lines[0]
+    );
+    assert!(lines[0].contains("B:02"), "{}", lines[0]);
+    assert!(lines[1].contains("B:01"), "{}", lines[1]);
+    assert!(
+        lines[1].ends_with(&format!("CYC:{}", 10 + 7 + 2 * 15)),
+        "{}",
+        lines[1]
+    );
+}
+
+#[test]
+fn follows_interrupts_and_the_8085() {
+    let assembly = assemble(" ORG 0\n EI\n NOP\n HLT\n ORG 38H\n DB 20H\n").unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+    processor.set_register(Register::SP, 0x2400);
+    processor.set_tracer(Some(
+        Tracer::ring_buffer(1).with_format(TraceFormat::Detailed),
+    ));
+    processor.set_variant(Variant::Intel8085);
+
+    let mut cycles = 0;
+    for _ in 0..2 {
+        cycles += processor.execute(&mut TestPort).unwrap();
+    }
+    cycles += processor
+        .interrupt(&[0xFF], &mut TestPort)
+        .unwrap()
+        .unwrap();
+    processor.execute(&mut TestPort).unwrap();
+
+    // The RST 7 cycles count towards the next line, RIM is decoded as such
+    let line = processor.tracer().unwrap().lines().next().unwrap();
+    assert!(line.starts_with("0038  20        RIM"), "{line}");
+    assert!(line.ends_with(&format!("CYC:{cycles}")), "{line}");
+}
@@ -0,0 +1,93 @@
+use intel8080_core::{
+    assembler::assemble,
+    bus::Bus,
+    errors::{Error, Result},
+    i8085::Variant,
+    processor::{Processor, UndocumentedOpcodes},
+    registers::{CpuState, Register},
+};
+
+mod common;
+
+use common::TestPort;
+
+// 0x08 is a NOP alias, 0xDD a CALL alias and 0xD9 a RET alias
+const PROGRAM: &str = "
+        ORG 0
+        LXI SP,1000H
+        DB 08H
+        DB 0DDH
+        DW SUB
+        HLT
+SUB:    MVI A,42H
+        DB 0D9H
+";
+
+fn load(undocumented: UndocumentedOpcodes) -> Processor {
+    let assembly = assemble(PROGRAM).unwrap();
+    let mut processor = Processor::new(0x10000, |address| (address as usize, false));
+    processor.load_rom(&assembly.to_binary(), 0).unwrap();
+    processor.set_undocumented_opcodes(undocumented);
+
+    processor
+}
+
+#[test]
+fn strict_rejects_undocumented_opcodes() {
+    let mut processor = load(UndocumentedOpcodes::Strict);
+    processor.execute(&mut TestPort).unwrap();
+
+    let error = processor.execute(&mut TestPort).unwrap_err();
+    assert!(matches!(error, Error::UnknownOpcode(0x08)));
+    assert_eq!(processor.pc(), 3);
+
+    // The 8085 has no aliases to fall back on
+    let mut processor = load(UndocumentedOpcodes::Alias);
+    processor.set_variant(Variant::Intel8085);
+    processor.execute(&mut TestPort).unwrap();
+    let error = processor.execute(&mut TestPort).unwrap_err();
+    assert!(matches!(error, Error::UnknownOpcode(0x08)));
+}
+
+#[test]
+fn aliases_run_like_silicon() {
+    let mut processor = load(UndocumentedOpcodes::Alias);
+
+    let cycles: Vec<u32> = (0..5)
+        .map(|_| processor.execute(&mut TestPort).unwrap())
+        .collect();
+    assert_eq!(cycles, [10, 4, 17, 7, 10]);
+    assert_eq!(processor.register(Register::A), 0x42);
+    assert_eq!(processor.pc(), 7);
+    assert_eq!(processor.sp(), 0x1000);
+}
+
+#[test]
+fn callback_runs_undocumented_opcodes() {
+    // Loads 0x08 into B, runs 0xDD as a jump through its operand and
+    // rejects the other opcodes
+    fn callback(opcode: u8, state: &mut CpuState, bus: &mut dyn Bus) -> Result<u32> {
+        match opcode {
+            0x08 => state.b = opcode,
+            0xDD => {
+                let low_byte = bus.read(state.pc)?;
+                let high_byte = bus.read(state.pc.wrapping_add(1))?;
+                state.pc = u16::from_le_bytes([low_byte, high_byte]);
+            }
+            _ => return Err(Error::UnknownOpcode(opcode)),
+        }
+        Ok(6)
+    }
+
+    let mut processor = load(UndocumentedOpcodes::Callback(callback));
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.execute(&mut TestPort).unwrap(), 6);
+    assert_eq!(processor.register(Register::B), 0x08);
+    assert_eq!(processor.pc(), 4);
+
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.pc(), 8);
+    processor.execute(&mut TestPort).unwrap();
+    assert!(processor.execute(&mut TestPort).is_err());
+    assert_eq!(processor.pc(), 10);
+}
@@ -0,0 +1,123 @@
+use intel8080_core::{
+    assembler::assemble, debugger::Debugger, processor::Processor, registers::Register,
+};
+
+mod common;
+
+use common::TestPort;
+
+fn processor() -> Processor {
+    Processor::new(0x10000, |address| (address as usize, false))
+}
+
+#[test]
+fn stack_at_zero_wraps() {
+    // CP/M programs often start with the stack at the top of memory
+    let assembly = assemble(
+        "
+        ORG 100H
+        LXI SP,0
+        LXI B,1234H
+        PUSH B
+        CALL SUB
+        POP D
+        XTHL
+        HLT
+SUB:    RET
+        ",
+    )
+    .unwrap();
+    let mut processor = processor();
+    processor
+        .load_rom(&assembly.to_binary(), assembly.origin())
+        .unwrap();
+    processor.set_register(Register::PC, 0x100);
+
+    let mut debugger = Debugger::new(processor);
+    for _ in 0..4 {
+        debugger.step(&mut TestPort).unwrap();
+    }
+    assert_eq!(debugger.processor().sp(), 0xFFFC);
+    assert_eq!(debugger.call_stack().len(), 1);
+
+    debugger.step(&mut TestPort).unwrap();
+    assert!(debugger.call_stack().is_empty());
+    debugger.step(&mut TestPort).unwrap();
+
+    let mut processor = debugger.into_processor();
+    assert_eq!(processor.sp(), 0);
+    assert_eq!(processor.register(Register::DE), 0x1234);
+    assert_eq!(processor.memory_slice(0xFFFE, 2).unwrap(), [0x34, 0x12]);
+
+    // XTHL with SP at 0xFFFF swaps L with 0xFFFF and H with 0x0000
+    processor.set_register(Register::SP, 0xFFFF);
+    processor.set_register(Register::HL, 0xABCD);
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.register(Register::HL), 0x0012);
+    assert_eq!(processor.memory_slice(0xFFFF, 1).unwrap(), [0xCD]);
+    assert_eq!(processor.memory_slice(0, 1).unwrap(), [0xAB]);
+}
+
+#[test]
+fn stepping_out_with_the_stack_at_zero() {
+    let assembly = assemble(
+        "
+        ORG 100H
+        LXI SP,0
+        CALL SUB
+        HLT
+SUB:    CALL SUB2
+        NOP
+        RET
+SUB2:   RET
+        ",
+    )
+    .unwrap();
+    let mut processor = processor();
+    processor
+        .load_rom(&assembly.to_binary(), assembly.origin())
+        .unwrap();
+    processor.set_register(Register::PC, 0x100);
+
+    let mut debugger = Debugger::new(processor);
+    debugger.step(&mut TestPort).unwrap();
+    debugger.step(&mut TestPort).unwrap();
+    assert_eq!(debugger.processor().sp(), 0xFFFE);
+
+    // Stepping over the nested CALL stops on the NOP
+    debugger.step_over(&mut TestPort).unwrap();
+    assert_eq!(debugger.processor().pc(), 0x10A);
+    assert_eq!(debugger.processor().sp(), 0xFFFE);
+
+    // SP returns to 0, which is above 0xFFFE once wrapped
+    debugger.step_out(&mut TestPort).unwrap();
+    assert_eq!(debugger.processor().pc(), 0x106);
+    assert_eq!(debugger.processor().sp(), 0);
+    assert!(!debugger.processor().is_halted());
+}
+
+#[test]
+fn code_runs_past_ffff_into_zero() {
+    let mut processor = processor();
+    // LXI H,1234H with its operand at 0x0000, then LHLD 0FFFFH
+    processor.load_rom(&[0x21], 0xFFFF).unwrap();
+    processor
+        .load_rom(&[0x34, 0x12, 0x2A, 0xFF, 0xFF], 0)
+        .unwrap();
+    processor.set_register(Register::PC, 0xFFFF);
+
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.pc(), 0x0002);
+    assert_eq!(processor.register(Register::HL), 0x1234);
+
+    // The word at 0xFFFF is made of 0x21 and the byte at 0x0000
+    processor.execute(&mut TestPort).unwrap();
+    assert_eq!(processor.register(Register::HL), 0x3421);
+}
+
+#[test]
+fn memory_slice_checks_the_end_of_memory() {
+    let processor = processor();
+    assert_eq!(processor.memory_slice(0xFF00, 0x100).unwrap().len(), 0x100);
+    assert!(processor.memory_slice(0xFF00, 0x101).is_err());
+}
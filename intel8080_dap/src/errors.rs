@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Processor(#[from] intel8080_core::errors::Error),
+
+    #[error("File IO failed:\n{0}")]
+    IO(#[from] std::io::Error),
+
+    #[error("Invalid JSON message:\n{0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid DAP message: {0}")]
+    Protocol(String),
+
+    #[error("Invalid request: {0}")]
+    Request(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
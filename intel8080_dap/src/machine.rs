@@ -0,0 +1,277 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use intel8080_core::{
+    assembler::{Assembly, assemble},
+    debugger::{Debugger, StopReason},
+    helpers::rst_instruction,
+    intel_hex,
+    port::Port,
+    processor::Processor,
+    registers::Register,
+};
+
+use crate::errors::{Error, Result};
+
+const CLOCK_SPEED: u64 = 2000000;
+const FRAME_RATE: u64 = 60;
+const CYCLES_PER_TICK: u64 = CLOCK_SPEED / FRAME_RATE / 2;
+const INVADERS_RAM_SIZE: usize = 16384;
+
+/// Hardware the program runs on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Board {
+    /// 64K of RAM and no devices
+    Bare,
+    /// Space Invaders memory map, shift register and video interrupts
+    Invaders,
+}
+
+impl Board {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bare" => Ok(Board::Bare),
+            "invaders" | "spaceinvaders" => Ok(Board::Invaders),
+            _ => Err(Error::Request(format!("unknown machine '{name}'"))),
+        }
+    }
+}
+
+/// Maps source lines of an assembled program to addresses and back
+pub struct SourceMap {
+    pub path: String,
+    lines: BTreeMap<usize, u16>,
+    addresses: BTreeMap<u16, usize>,
+    labels: BTreeMap<u16, String>,
+}
+
+impl SourceMap {
+    fn new(path: String, assembly: &Assembly) -> Self {
+        let mut labels = BTreeMap::new();
+        for (name, &address) in &assembly.symbols {
+            labels.entry(address).or_insert_with(|| name.clone());
+        }
+
+        Self {
+            path,
+            lines: assembly.lines.clone(),
+            addresses: assembly
+                .lines
+                .iter()
+                .map(|(&line_num, &address)| (address, line_num))
+                .collect(),
+            labels,
+        }
+    }
+
+    /// First line at or after `line_num` that emits code, with its address
+    pub fn line_address(&self, line_num: usize) -> Option<(usize, u16)> {
+        self.lines
+            .range(line_num..)
+            .next()
+            .map(|(&line_num, &address)| (line_num, address))
+    }
+
+    /// Line of the code at or just before `address`
+    pub fn address_line(&self, address: u16) -> Option<usize> {
+        self.addresses
+            .range(..=address)
+            .next_back()
+            .map(|(_, &line_num)| line_num)
+    }
+
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+}
+
+/// Devices on the port bus of each board
+enum BoardPort {
+    Bare,
+    // Mirrors the Space Invaders IoHandler without input, sound or SDL
+    Invaders {
+        shift_register: u16,
+        shift_offset: u8,
+    },
+}
+
+impl Port for BoardPort {
+    fn read_in(&self, port_num: u8) -> u8 {
+        match (self, port_num) {
+            (BoardPort::Invaders { .. }, 1) => 0b00001001,
+            (BoardPort::Invaders { .. }, 2) => 0b10000000,
+            (
+                BoardPort::Invaders {
+                    shift_register,
+                    shift_offset,
+                },
+                3,
+            ) => (shift_register >> (8 - *shift_offset as u16)) as u8,
+            _ => 0,
+        }
+    }
+
+    fn write_out(&mut self, port_num: u8, value: u8) {
+        match (self, port_num) {
+            (BoardPort::Invaders { shift_offset, .. }, 2) => *shift_offset = value & 0b111,
+            (BoardPort::Invaders { shift_register, .. }, 4) => {
+                *shift_register = ((value as u16) << 8) | (*shift_register >> 8);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A program loaded on a board under the debugger
+pub struct Machine {
+    debugger: Debugger,
+    port: BoardPort,
+    entry: u16,
+    source: Option<SourceMap>,
+    // Video interrupts of the Space Invaders board
+    next_interrupt: Option<u64>,
+    next_rst: u8,
+}
+
+impl Machine {
+    /// Loads a program from an assembly source (.asm), Intel HEX (.hex) or
+    /// raw binary file. Raw binaries are loaded at `load_address`.
+    pub fn load(
+        program: &Path,
+        board: Board,
+        load_address: Option<u16>,
+        entry: Option<u16>,
+    ) -> Result<Self> {
+        let mut processor = match board {
+            Board::Bare => Processor::new(0x10000, |address| (address as usize, false)),
+            Board::Invaders => Processor::new(INVADERS_RAM_SIZE, invaders_mapper),
+        };
+
+        let extension = program
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        let mut source = None;
+        let origin = match extension.as_deref() {
+            Some("asm") | Some("s") => {
+                let assembly = assemble(&fs::read_to_string(program)?)?;
+                processor.load_rom(&assembly.to_binary(), assembly.origin())?;
+                source = Some(SourceMap::new(
+                    program.to_string_lossy().into_owned(),
+                    &assembly,
+                ));
+                assembly.origin()
+            }
+            Some("hex") | Some("ihx") => {
+                let segments = intel_hex::decode(&fs::read_to_string(program)?)?;
+                for segment in &segments {
+                    processor.load_rom(&segment.bytes, segment.address)?;
+                }
+                segments
+                    .iter()
+                    .map(|segment| segment.address)
+                    .min()
+                    .unwrap_or(0)
+            }
+            _ => {
+                let address = load_address.unwrap_or(0);
+                processor.load_rom(&fs::read(program)?, address)?;
+                address
+            }
+        };
+
+        let entry = entry.unwrap_or(origin);
+        processor.set_register(Register::PC, entry);
+
+        let (port, next_interrupt) = match board {
+            Board::Bare => (BoardPort::Bare, None),
+            Board::Invaders => (
+                BoardPort::Invaders {
+                    shift_register: 0,
+                    shift_offset: 0,
+                },
+                Some(CYCLES_PER_TICK),
+            ),
+        };
+
+        Ok(Self {
+            debugger: Debugger::new(processor),
+            port,
+            entry,
+            source,
+            next_interrupt,
+            next_rst: 1,
+        })
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    pub fn entry(&self) -> u16 {
+        self.entry
+    }
+
+    pub fn source(&self) -> Option<&SourceMap> {
+        self.source.as_ref()
+    }
+
+    /// Runs for up to `cycles` cycles, stopping early for the debugger
+    pub fn run(&mut self, cycles: u64) -> Result<StopReason> {
+        let end = self.debugger.cycles() + cycles;
+
+        loop {
+            self.deliver_interrupts()?;
+
+            let until = self.next_interrupt.map_or(end, |next| next.min(end));
+            let budget = until.saturating_sub(self.debugger.cycles()).max(1);
+            let reason = self.debugger.run_for(budget, &mut self.port)?;
+
+            if reason != StopReason::CycleLimit || self.debugger.cycles() >= end {
+                return Ok(reason);
+            }
+        }
+    }
+
+    pub fn step(&mut self) -> Result<StopReason> {
+        self.deliver_interrupts()?;
+        Ok(self.debugger.step(&mut self.port)?)
+    }
+
+    pub fn step_over(&mut self) -> Result<StopReason> {
+        self.deliver_interrupts()?;
+        Ok(self.debugger.step_over(&mut self.port)?)
+    }
+
+    pub fn step_out(&mut self) -> Result<StopReason> {
+        self.deliver_interrupts()?;
+        Ok(self.debugger.step_out(&mut self.port)?)
+    }
+
+    /// Raises the mid-frame and end-of-frame interrupts once they are due
+    fn deliver_interrupts(&mut self) -> Result<()> {
+        let Some(next_interrupt) = self.next_interrupt else {
+            return Ok(());
+        };
+        if self.debugger.cycles() < next_interrupt {
+            return Ok(());
+        }
+
+        self.debugger
+            .interrupt(&[rst_instruction(self.next_rst)], &mut self.port)?;
+        self.next_interrupt = Some(next_interrupt + CYCLES_PER_TICK);
+        self.next_rst = if self.next_rst == 1 { 2 } else { 1 };
+
+        Ok(())
+    }
+}
+
+fn invaders_mapper(address: u16) -> (usize, bool) {
+    // Mask out the 2 unused upper RAM pins
+    let address = (address & 0x3FFF) as usize;
+    let is_rom = address < 0x2000;
+
+    (address, is_rom)
+}
@@ -0,0 +1,66 @@
+mod errors;
+mod machine;
+mod protocol;
+mod session;
+
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpListener,
+    process,
+    sync::mpsc,
+    thread,
+};
+
+use errors::Result;
+use session::Session;
+
+const USAGE: &str = "Usage: intel8080_dap [--port <port>]";
+
+/// Debug Adapter Protocol server for 8080 programs. Speaks DAP on stdin and
+/// stdout, or on a local TCP port when started with `--port`.
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.as_slice() {
+        [] => serve(BufReader::new(io::stdin()), io::stdout()),
+        [flag, port] if flag == "--port" => {
+            let Ok(port) = port.parse::<u16>() else {
+                eprintln!("{USAGE}");
+                process::exit(2);
+            };
+
+            let (stream, _) = TcpListener::bind(("127.0.0.1", port))?.accept()?;
+            serve(BufReader::new(stream.try_clone()?), stream)
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    }
+}
+
+/// Reads requests on a separate thread so that a running program can be
+/// paused, and serves them until the client disconnects
+fn serve(mut reader: impl BufRead + Send + 'static, writer: impl Write) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        loop {
+            match protocol::read_message(&mut reader) {
+                Ok(Some(message)) => {
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(error) => {
+                    eprintln!("{error}");
+                    break;
+                }
+            }
+        }
+    });
+
+    Session::new(writer).run(receiver)
+}
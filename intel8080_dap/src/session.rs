@@ -0,0 +1,617 @@
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    iter,
+    path::Path,
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+use intel8080_core::{
+    bus::Bus, debugger::StopReason, disassembler::disassemble_bus, registers::Register,
+};
+use serde_json::{Value, json};
+
+use crate::{
+    errors::{Error, Result},
+    machine::{Board, Machine},
+    protocol::{base64_encode, write_message},
+};
+
+/// Cycles run between checks for new requests while the program runs
+const RUN_CHUNK: u64 = 20000;
+
+const THREAD_ID: u64 = 1;
+const REGISTERS_REFERENCE: u64 = 1;
+const FLAGS_REFERENCE: u64 = 2;
+
+/// State of one debugging session with a single client
+pub struct Session<W: Write> {
+    writer: W,
+    seq: u64,
+    machine: Option<Machine>,
+    stop_on_entry: bool,
+    running: bool,
+    source_breakpoints: BTreeSet<u16>,
+    instruction_breakpoints: BTreeSet<u16>,
+    // Events to send once the current response is out
+    events: Vec<(&'static str, Value)>,
+}
+
+impl<W: Write> Session<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            seq: 0,
+            machine: None,
+            stop_on_entry: false,
+            running: false,
+            source_breakpoints: BTreeSet::new(),
+            instruction_breakpoints: BTreeSet::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Serves requests until the client disconnects. The program runs in
+    /// chunks between requests so that a pause can interrupt it.
+    pub fn run(mut self, requests: Receiver<Value>) -> Result<()> {
+        loop {
+            let request = if self.running {
+                match requests.try_recv() {
+                    Ok(request) => Some(request),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match requests.recv() {
+                    Ok(request) => Some(request),
+                    Err(_) => return Ok(()),
+                }
+            };
+
+            if let Some(request) = request
+                && !self.handle(&request)?
+            {
+                return Ok(());
+            }
+            if self.running {
+                self.run_chunk()?;
+            }
+        }
+    }
+
+    /// Answers a request, returning false once the session has ended
+    fn handle(&mut self, request: &Value) -> Result<bool> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let args = &request["arguments"];
+
+        let result = match command {
+            "initialize" => Ok(capabilities()),
+            "launch" => self.launch(args),
+            "setBreakpoints" => self.set_breakpoints(args),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(args),
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "configurationDone" => self.configuration_done(),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "8080" }] })),
+            "continue" => self.resume(),
+            "pause" => self.pause(),
+            "next" => self.step_with(Machine::step_over),
+            "stepIn" => self.step_with(Machine::step),
+            "stepOut" => self.step_out(),
+            "stackTrace" => self.stack_trace(args),
+            "scopes" => Ok(scopes()),
+            "variables" => self.variables(args),
+            "readMemory" => self.read_memory(args),
+            "disassemble" => self.disassemble(args),
+            "disconnect" | "terminate" => {
+                self.respond(request, Ok(json!({})))?;
+                self.send_event("terminated", json!({}))?;
+                return Ok(false);
+            }
+            _ => Err(Error::Request(format!("unsupported command '{command}'"))),
+        };
+
+        self.respond(request, result)?;
+        for (event, body) in std::mem::take(&mut self.events) {
+            self.send_event(event, body)?;
+        }
+
+        Ok(true)
+    }
+
+    fn run_chunk(&mut self) -> Result<()> {
+        let body = match self.machine_mut()?.run(RUN_CHUNK) {
+            Ok(StopReason::CycleLimit) => return Ok(()),
+            Ok(reason) => stopped_body(reason),
+            Err(error) => exception_body(&error),
+        };
+
+        self.running = false;
+        self.send_event("stopped", body)
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.writer, &message)
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn respond(&mut self, request: &Value, result: Result<Value>) -> Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(error) => response["message"] = json!(error.to_string()),
+        }
+
+        self.send(response)
+    }
+
+    fn machine(&self) -> Result<&Machine> {
+        self.machine
+            .as_ref()
+            .ok_or_else(|| Error::Request("no program has been launched".to_string()))
+    }
+
+    fn machine_mut(&mut self) -> Result<&mut Machine> {
+        self.machine
+            .as_mut()
+            .ok_or_else(|| Error::Request("no program has been launched".to_string()))
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<Value> {
+        let program = args["program"]
+            .as_str()
+            .ok_or_else(|| Error::Request("launch needs a 'program' path".to_string()))?;
+        let board = args["machine"]
+            .as_str()
+            .map(Board::from_name)
+            .transpose()?
+            .unwrap_or(Board::Bare);
+        let load_address = optional_address(&args["loadAddress"])?;
+        let entry = optional_address(&args["entry"])?;
+
+        self.machine = Some(Machine::load(
+            Path::new(program),
+            board,
+            load_address,
+            entry,
+        )?);
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+
+        // Breakpoints can only be resolved once the program is loaded
+        self.events.push(("initialized", json!({})));
+        Ok(json!({}))
+    }
+
+    fn configuration_done(&mut self) -> Result<Value> {
+        if self.stop_on_entry {
+            self.events.push((
+                "stopped",
+                json!({ "reason": "entry", "threadId": THREAD_ID, "allThreadsStopped": true }),
+            ));
+        } else {
+            self.running = true;
+        }
+
+        Ok(json!({}))
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Result<Value> {
+        let path = args["source"]["path"].as_str().unwrap_or_default();
+        let lines = args["breakpoints"]
+            .as_array()
+            .map(|breakpoints| {
+                breakpoints
+                    .iter()
+                    .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let source = self
+            .machine()?
+            .source()
+            .filter(|source| same_file(&source.path, path));
+
+        let mut addresses = BTreeSet::new();
+        let breakpoints: Vec<Value> = lines
+            .iter()
+            .map(|&line| match source.and_then(|source| source.line_address(line as usize)) {
+                Some((line, address)) => {
+                    addresses.insert(address);
+                    json!({
+                        "verified": true,
+                        "line": line,
+                        "instructionReference": format_address(address),
+                    })
+                }
+                None => json!({ "verified": false, "line": line, "message": "No code at this line" }),
+            })
+            .collect();
+
+        // Only the assembled program has source lines
+        if source.is_some() {
+            self.source_breakpoints = addresses;
+            self.sync_breakpoints()?;
+        }
+
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn set_instruction_breakpoints(&mut self, args: &Value) -> Result<Value> {
+        let mut addresses = BTreeSet::new();
+        let mut breakpoints = Vec::new();
+
+        for breakpoint in args["breakpoints"].as_array().into_iter().flatten() {
+            let address = breakpoint["instructionReference"]
+                .as_str()
+                .and_then(parse_address)
+                .map(|address| {
+                    address.wrapping_add(breakpoint["offset"].as_i64().unwrap_or(0) as u16)
+                });
+
+            match address {
+                Some(address) => {
+                    addresses.insert(address);
+                    breakpoints.push(json!({
+                        "verified": true,
+                        "instructionReference": format_address(address),
+                    }));
+                }
+                None => {
+                    breakpoints.push(json!({ "verified": false, "message": "Invalid address" }))
+                }
+            }
+        }
+
+        self.instruction_breakpoints = addresses;
+        self.sync_breakpoints()?;
+
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Replaces the debugger's breakpoints with the source and instruction ones
+    fn sync_breakpoints(&mut self) -> Result<()> {
+        let Some(machine) = self.machine.as_mut() else {
+            return Err(Error::Request("no program has been launched".to_string()));
+        };
+        let debugger = machine.debugger_mut();
+
+        for address in debugger.breakpoints().collect::<Vec<_>>() {
+            debugger.remove_breakpoint(address);
+        }
+        for &address in self.source_breakpoints.union(&self.instruction_breakpoints) {
+            debugger.add_breakpoint(address);
+        }
+
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<Value> {
+        self.machine()?;
+        self.running = true;
+
+        Ok(json!({ "allThreadsContinued": true }))
+    }
+
+    fn pause(&mut self) -> Result<Value> {
+        if self.running {
+            self.running = false;
+            self.events.push((
+                "stopped",
+                json!({ "reason": "pause", "threadId": THREAD_ID, "allThreadsStopped": true }),
+            ));
+        }
+
+        Ok(json!({}))
+    }
+
+    fn step_with(&mut self, step: fn(&mut Machine) -> Result<StopReason>) -> Result<Value> {
+        let body = match step(self.machine_mut()?) {
+            Ok(reason) => stopped_body(reason),
+            Err(error) => exception_body(&error),
+        };
+        self.events.push(("stopped", body));
+
+        Ok(json!({}))
+    }
+
+    fn step_out(&mut self) -> Result<Value> {
+        // Outside of any known subroutine there is nothing to return from
+        if self.machine()?.debugger().call_stack().is_empty() {
+            self.step_with(Machine::step)
+        } else {
+            self.step_with(Machine::step_out)
+        }
+    }
+
+    fn stack_trace(&self, args: &Value) -> Result<Value> {
+        let machine = self.machine()?;
+        let debugger = machine.debugger();
+        let frames = debugger.call_stack();
+
+        // Innermost first: the current PC, then the call site of each frame
+        let locations = iter::once(debugger.processor().pc())
+            .chain(frames.iter().rev().map(|frame| frame.call_site));
+        let functions = frames
+            .iter()
+            .rev()
+            .map(|frame| (frame.entry, frame.interrupt))
+            .chain(iter::once((machine.entry(), false)));
+
+        let stack: Vec<Value> = locations
+            .zip(functions)
+            .enumerate()
+            .map(|(id, (location, (entry, interrupt)))| {
+                stack_frame(machine, id, location, entry, interrupt)
+            })
+            .collect();
+
+        let total = stack.len();
+        let start = args["startFrame"].as_u64().unwrap_or(0) as usize;
+        let levels = args["levels"]
+            .as_u64()
+            .filter(|&levels| levels > 0)
+            .map_or(total, |levels| levels as usize);
+
+        Ok(json!({
+            "stackFrames": stack.into_iter().skip(start).take(levels).collect::<Vec<_>>(),
+            "totalFrames": total,
+        }))
+    }
+
+    fn variables(&self, args: &Value) -> Result<Value> {
+        let processor = self.machine()?.debugger().processor();
+
+        let variables: Vec<Value> = match args["variablesReference"].as_u64() {
+            Some(REGISTERS_REFERENCE) => Register::ALL
+                .iter()
+                .filter(|&&register| register != Register::PSW)
+                .map(|&register| {
+                    let value = processor.register(register);
+                    if register.is_16bit() {
+                        json!({
+                            "name": register.name(),
+                            "value": format_address(value),
+                            "variablesReference": 0,
+                            "memoryReference": format_address(value),
+                        })
+                    } else {
+                        json!({
+                            "name": register.name(),
+                            "value": format!("0x{value:02X}"),
+                            "variablesReference": 0,
+                        })
+                    }
+                })
+                .collect(),
+            Some(FLAGS_REFERENCE) => {
+                let flags = processor.flags();
+                [
+                    ("S", flags.s),
+                    ("Z", flags.z),
+                    ("AC", flags.ac),
+                    ("P", flags.p),
+                    ("CY", flags.cy),
+                ]
+                .iter()
+                .map(|&(name, set)| {
+                    json!({ "name": name, "value": (set as u8).to_string(), "variablesReference": 0 })
+                })
+                .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn read_memory(&self, args: &Value) -> Result<Value> {
+        let bus = self.machine()?.debugger().processor().bus();
+        let address = memory_reference(args)?;
+        let count = args["count"].as_u64().unwrap_or(0).min(0x10000) as usize;
+
+        // Stop at the first byte that cannot be read without side effects
+        let bytes: Vec<u8> = (0..count)
+            .map_while(|offset| bus.peek(address.wrapping_add(offset as u16)))
+            .collect();
+
+        Ok(json!({
+            "address": format_address(address),
+            "data": base64_encode(&bytes),
+            "unreadableBytes": count - bytes.len(),
+        }))
+    }
+
+    fn disassemble(&self, args: &Value) -> Result<Value> {
+        let machine = self.machine()?;
+        let bus = machine.debugger().processor().bus();
+        let source = machine.source();
+        let count = args["instructionCount"].as_u64().unwrap_or(0).min(0x10000);
+
+        // Instructions are 1 to 3 bytes long, so a negative instruction
+        // offset can only be estimated. Decoding resynchronizes quickly.
+        let instruction_offset = args["instructionOffset"].as_i64().unwrap_or(0);
+        let mut address = memory_reference(args)?.wrapping_add(instruction_offset as u16);
+
+        let mut instructions = Vec::new();
+        for _ in 0..count {
+            let Some(instruction) = disassemble_bus(bus, address) else {
+                instructions.push(json!({
+                    "address": format_address(address),
+                    "instruction": "??",
+                    "presentationHint": "invalid",
+                }));
+                address = address.wrapping_add(1);
+                continue;
+            };
+
+            let mut entry = json!({
+                "address": format_address(address),
+                "instructionBytes": instruction
+                    .bytes()
+                    .iter()
+                    .map(|byte| format!("{byte:02X}"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                "instruction": instruction.to_string(),
+            });
+            if let Some(source) = source {
+                if let Some(label) = source.label(address) {
+                    entry["symbol"] = json!(label);
+                }
+                if let Some(line) = source.address_line(address) {
+                    entry["location"] = source_json(&source.path);
+                    entry["line"] = json!(line);
+                }
+            }
+
+            instructions.push(entry);
+            address = instruction.next_address();
+        }
+
+        Ok(json!({ "instructions": instructions }))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsInstructionBreakpoints": true,
+        "supportsReadMemoryRequest": true,
+        "supportsDisassembleRequest": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+fn scopes() -> Value {
+    json!({
+        "scopes": [
+            { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+            { "name": "Flags", "variablesReference": FLAGS_REFERENCE, "expensive": false },
+        ]
+    })
+}
+
+fn stopped_body(reason: StopReason) -> Value {
+    let (reason, description) = match reason {
+        StopReason::Breakpoint(_) => ("breakpoint", None),
+        StopReason::Watchpoint { .. } | StopReason::PortBreakpoint { .. } => {
+            ("data breakpoint", None)
+        }
+        StopReason::Halted => ("pause", Some("Processor halted")),
+        StopReason::Step
+        | StopReason::Reached(_)
+        | StopReason::CycleLimit
+        | StopReason::HistoryStart => ("step", None),
+    };
+
+    let mut body = json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true });
+    if let Some(description) = description {
+        body["description"] = json!(description);
+    }
+
+    body
+}
+
+fn exception_body(error: &Error) -> Value {
+    json!({
+        "reason": "exception",
+        "description": "Execution failed",
+        "text": error.to_string(),
+        "threadId": THREAD_ID,
+        "allThreadsStopped": true,
+    })
+}
+
+fn stack_frame(machine: &Machine, id: usize, location: u16, entry: u16, interrupt: bool) -> Value {
+    let source = machine.source();
+    let name = match source.and_then(|source| source.label(entry)) {
+        Some(label) => label.to_string(),
+        None if interrupt => format!("interrupt {entry:04X}H"),
+        None => format!("sub_{entry:04X}"),
+    };
+
+    let mut frame = json!({
+        "id": id,
+        "name": name,
+        "line": 0,
+        "column": 0,
+        "instructionPointerReference": format_address(location),
+    });
+    if let Some(source) = source
+        && let Some(line) = source.address_line(location)
+    {
+        frame["source"] = source_json(&source.path);
+        frame["line"] = json!(line);
+        frame["column"] = json!(1);
+    }
+
+    frame
+}
+
+fn source_json(path: &str) -> Value {
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    json!({ "name": name, "path": path })
+}
+
+fn same_file(a: &str, b: &str) -> bool {
+    match (Path::new(a).canonicalize(), Path::new(b).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn format_address(address: u16) -> String {
+    format!("0x{address:04X}")
+}
+
+/// Parses "0x1234", "1234H" or a decimal address
+fn parse_address(text: &str) -> Option<u16> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = text.strip_suffix(['h', 'H']) {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Reads an optional launch address given as a number or a string
+fn optional_address(value: &Value) -> Result<Option<u16>> {
+    let address = match value {
+        Value::Null => return Ok(None),
+        Value::Number(number) => number
+            .as_u64()
+            .and_then(|number| u16::try_from(number).ok()),
+        Value::String(text) => parse_address(text),
+        _ => None,
+    };
+
+    address
+        .map(Some)
+        .ok_or_else(|| Error::Request(format!("invalid address {value}")))
+}
+
+/// Start address of a readMemory or disassemble request
+fn memory_reference(args: &Value) -> Result<u16> {
+    let base = args["memoryReference"]
+        .as_str()
+        .and_then(parse_address)
+        .ok_or_else(|| Error::Request("invalid memory reference".to_string()))?;
+
+    Ok(base.wrapping_add(args["offset"].as_i64().unwrap_or(0) as u16))
+}
@@ -0,0 +1,184 @@
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::PathBuf,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde_json::{Value, json};
+
+const PROGRAM: &str = "        ORG 100H
+START:  LXI SP,2000H
+        MVI A,21H
+        CALL DOUBLE
+        STA 3000H
+        HLT
+DOUBLE: ADD A
+        RET
+";
+
+// Line numbers in PROGRAM
+const CALL_LINE: u64 = 4;
+const STA_LINE: u64 = 5;
+const ADD_LINE: u64 = 7;
+
+/// Scripted client talking to the adapter over its stdin and stdout
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    seq: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_intel8080_dap"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        Self {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            seq: 0,
+        }
+    }
+
+    fn send(&mut self, command: &str, arguments: Value) {
+        self.seq += 1;
+        let content = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{content}",
+            content.len()
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            length = header["Content-Length: ".len()..].parse().unwrap();
+        }
+
+        let mut content = vec![0; length];
+        self.stdout.read_exact(&mut content).unwrap();
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    /// Sends a request and returns the body of its successful response
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.send(command, arguments);
+
+        let response = self.receive();
+        assert_eq!(response["type"], "response", "{response}");
+        assert_eq!(response["command"], command);
+        assert_eq!(response["success"], true, "{response}");
+        response["body"].clone()
+    }
+
+    fn expect_event(&mut self, event: &str) -> Value {
+        let message = self.receive();
+        assert_eq!(message["event"], event, "{message}");
+        message["body"].clone()
+    }
+}
+
+fn program_path() -> PathBuf {
+    let path = env::temp_dir().join(format!("intel8080_dap_{}.asm", std::process::id()));
+    fs::write(&path, PROGRAM).unwrap();
+    path
+}
+
+fn register(client: &mut Client, name: &str) -> String {
+    let variables = client.request("variables", json!({ "variablesReference": 1 }));
+    variables["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|variable| variable["name"] == name)
+        .unwrap()["value"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn source_level_debugging_session() {
+    let path = program_path();
+    let mut client = Client::start();
+
+    let capabilities = client.request("initialize", json!({ "adapterID": "i8080" }));
+    assert_eq!(capabilities["supportsDisassembleRequest"], true);
+
+    client.request("launch", json!({ "program": path, "stopOnEntry": true }));
+    client.expect_event("initialized");
+
+    let breakpoints = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": path }, "breakpoints": [{ "line": ADD_LINE }, { "line": 1 }] }),
+    );
+    assert_eq!(breakpoints["breakpoints"][0]["verified"], true);
+    assert_eq!(breakpoints["breakpoints"][0]["line"], ADD_LINE);
+    // ORG emits nothing, so the breakpoint moves to the first instruction
+    assert_eq!(breakpoints["breakpoints"][1]["line"], 2);
+
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.expect_event("stopped")["reason"], "entry");
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.expect_event("stopped")["reason"], "breakpoint");
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    let frames = trace["stackFrames"].as_array().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["name"], "DOUBLE");
+    assert_eq!(frames[0]["line"], ADD_LINE);
+    assert_eq!(frames[1]["name"], "START");
+    assert_eq!(frames[1]["line"], CALL_LINE);
+    assert_eq!(register(&mut client, "A"), "0x21");
+
+    client.request("stepOut", json!({ "threadId": 1 }));
+    assert_eq!(client.expect_event("stopped")["reason"], "step");
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(trace["stackFrames"][0]["line"], STA_LINE);
+    assert_eq!(register(&mut client, "A"), "0x42");
+
+    client.request("next", json!({ "threadId": 1 }));
+    client.expect_event("stopped");
+    let memory = client.request(
+        "readMemory",
+        json!({ "memoryReference": "0x3000", "count": 2 }),
+    );
+    assert_eq!(memory["data"], "QgA=");
+
+    let disassembly = client.request(
+        "disassemble",
+        json!({ "memoryReference": "0x0100", "instructionCount": 3 }),
+    );
+    let instructions = disassembly["instructions"].as_array().unwrap();
+    assert_eq!(instructions[0]["instruction"], "LXI SP,2000H");
+    assert_eq!(instructions[0]["symbol"], "START");
+    assert_eq!(instructions[2]["instructionBytes"], "CD 0C 01");
+
+    client.request("disconnect", json!({}));
+    client.expect_event("terminated");
+    assert!(client.child.wait().unwrap().success());
+    fs::remove_file(path).unwrap();
+}
@@ -3,7 +3,9 @@ use crate::{
     errors::{Error, Result},
     io_handler::IoHandler,
 };
-use intel8080_core::processor::Processor;
+use intel8080_core::{
+    helpers::rst_instruction, memory::Memory, processor::Processor, region::RegionMap,
+};
 use sdl2::{EventPump, Sdl, VideoSubsystem, event::Event, keyboard::Keycode};
 use std::{
     fs,
@@ -15,7 +17,6 @@ const CLOCK_SPEED: u32 = 2000000;
 const FRAME_RATE: u32 = 60;
 const CYCLES_PER_TICK: u32 = CLOCK_SPEED / FRAME_RATE / 2;
 const TICK_LENGTH: Duration = Duration::from_nanos(1e9 as u64 / FRAME_RATE as u64 / 2);
-const RAM_SIZE: usize = 16384;
 
 pub struct Emulator {
     processor: Processor,
@@ -35,7 +36,7 @@ impl Emulator {
         let eventpump = _sdl_context.event_pump().map_err(Error::Sdl)?;
 
         let rom: Vec<u8> = fs::read(rom_path)?;
-        let mut processor = Processor::new(RAM_SIZE, memory_mapper);
+        let mut processor = Processor::with_bus(memory()?);
         processor.load_rom(&rom, 0x0)?;
         let display = Display::try_new(video_subsystem)?;
 
@@ -77,11 +78,15 @@ impl Emulator {
             // Run first tick
             let mut cycles = 0;
             while cycles < CYCLES_PER_TICK {
+                // Skip ahead to the interrupt if the CPU is idling in HLT
+                if self.processor.is_halted() {
+                    break;
+                }
                 cycles += self.processor.execute(&mut self.io_handler)?;
             }
 
             // Mid-frame interrupt
-            self.processor.interrupt(1)?;
+            self.processor.interrupt(&[rst_instruction(1)], &mut self.io_handler)?;
 
             // Time padding
             let elapsed = tick_start.elapsed();
@@ -92,6 +97,9 @@ impl Emulator {
             // Run second tick
             cycles = 0;
             while cycles < 2 * CYCLES_PER_TICK {
+                if self.processor.is_halted() {
+                    break;
+                }
                 cycles += self.processor.execute(&mut self.io_handler)?;
             }
 
@@ -105,10 +113,17 @@ impl Emulator {
     }
 }
 
-fn memory_mapper(address: u16) -> (usize, bool) {
-    // Mask out the 2 unused upper RAM pins
-    let address = (address & 0x3FFF) as usize;
-    let is_rom = address < 0x2000;
-
-    (address, is_rom)
+fn memory() -> Result<Memory> {
+    let regions = RegionMap::builder()
+        .rom("invaders.h", 0x0000..=0x07FF)
+        .rom("invaders.g", 0x0800..=0x0FFF)
+        .rom("invaders.f", 0x1000..=0x17FF)
+        .rom("invaders.e", 0x1800..=0x1FFF)
+        .ram("work RAM", 0x2000..=0x23FF)
+        .ram("video RAM", 0x2400..=0x3FFF)
+        // The 2 upper address pins are not connected
+        .mirror("mirror", 0x4000..=0xFFFF, 0x3FFF)
+        .build()?;
+
+    Ok(Memory::with_regions(regions))
 }